@@ -80,6 +80,9 @@ pub fn derive_answer_fn(input: TokenStream) -> TokenStream {
                                 fspec_num.to_string(),
                                 frn.to_string(),
                             ));
+                        } else if meta.input.peek(syn::Token![=]) {
+                            // skip the value of other attributes, such as `ctx`
+                            meta.value()?.parse::<syn::LitStr>()?;
                         }
                         Ok(())
                     })
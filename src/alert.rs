@@ -0,0 +1,424 @@
+//! Emergency and special condition alerts from CAT048 records
+//!
+//! `AlertEngine` follows the conditions of each target, by Mode S address, else by track number,
+//! and reports when one starts and when it ends:
+//! - Mode-3/A codes 7500, 7600 and 7700 from I048/070, unless garbled
+//! - SPI from I048/020 or the I048/230 flight status, and military identification from I048/020 MI
+//! - Military emergency from I048/020 ME
+//! - An ACAS Resolution Advisory in I048/260, or in BDS 3,0 of I048/250
+//! - The alert of the I048/230 flight status
+//!
+//! A condition only ends on a record that reports it, so a record without I048/070 doesn't end
+//! an emergency code. A record without an I048/020 first extension ends a military emergency,
+//! and a record with I048/260 or BDS 3,0 and no active Resolution Advisory ends one. Conditions
+//! of a target not updated for a minute are cleared at the next North Marker of its radar, with
+//! its last state. I048/030 warnings carry no emergency information, and aren't alerted.
+
+use std::collections::HashMap;
+
+use crate::data_item::{
+    ACASResolutionAdvisoryReport, CalculatedPositionCartesianCorr, DataSourceIdentifier,
+    MeasuredPositionInPolarCoordinates, TimeOfDay,
+};
+use crate::modifier::elapsed;
+use crate::picture::TargetKey;
+use crate::types::{Callsign, SpecialCode, Squawk, G, ME, MI, MTYPE, SPI, STAT};
+use crate::{AsterixMessage, Cat34, Cat48};
+
+/// Targets not updated for this many seconds are forgotten at the next North Marker of their
+/// radar, clearing their active alerts
+const MAX_AGE: f64 = 60.0;
+
+/// Condition of a target that raises an alert
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AlertKind {
+    /// Mode-3/A 7500
+    UnlawfulInterference,
+    /// Mode-3/A 7600
+    RadioFailure,
+    /// Mode-3/A 7700
+    Emergency,
+    /// Special Position Identification, the pilot pressed IDENT, or military identification
+    Ident,
+    /// I048/020 ME
+    MilitaryEmergency,
+    /// I048/260, or BDS 3,0 of I048/250
+    AcasResolutionAdvisory,
+    /// I048/230 STAT alert, a change of the Mode-3/A code
+    FlightStatusAlert,
+}
+
+impl AlertKind {
+    const ALL: [Self; 7] = [
+        Self::UnlawfulInterference,
+        Self::RadioFailure,
+        Self::Emergency,
+        Self::Ident,
+        Self::MilitaryEmergency,
+        Self::AcasResolutionAdvisory,
+        Self::FlightStatusAlert,
+    ];
+
+    /// Is the condition reported by `record`, `None` if the record doesn't tell
+    fn reported(&self, record: &Cat48) -> Option<bool> {
+        let stat = record.communications_capability_flight_status.as_ref().map(|c| c.stat);
+        let descriptor = record.target_report_descriptor.as_ref();
+        match self {
+            Self::UnlawfulInterference => special_code(record, SpecialCode::UnlawfulInterference),
+            Self::RadioFailure => special_code(record, SpecialCode::RadioFailure),
+            Self::Emergency => special_code(record, SpecialCode::Emergency),
+            Self::Ident => {
+                let spi = descriptor.map(|d| {
+                    d.spi == SPI::SpecialPositionIdentification
+                        || d.mi == Some(MI::MilitaryIdentification)
+                });
+                let stat = stat.and_then(|s| match s {
+                    STAT::AlertSPIAircraftAirborneOrOnGround
+                    | STAT::NoAlertSPIAircraftAirborneOrOnGround => Some(true),
+                    STAT::NotAssigned | STAT::Unknown => None,
+                    _ => Some(false),
+                });
+                either(spi, stat)
+            }
+            Self::MilitaryEmergency => descriptor.map(|d| d.me == Some(ME::MilitaryEmergency)),
+            Self::AcasResolutionAdvisory => {
+                let mb = record.mode_smb_data.iter().flat_map(|m| &m.mb_data);
+                let bds30 = mb
+                    .filter(|mb| (mb.bds1, mb.bds2) == (3, 0))
+                    .filter_map(|mb| mb.data.as_slice().try_into().ok())
+                    .map(|mb_data| ACASResolutionAdvisoryReport { mb_data });
+                record
+                    .acas_resolution_advisory_report
+                    .iter()
+                    .cloned()
+                    .chain(bds30)
+                    .map(|ra| ra.is_active())
+                    .reduce(|a, b| a || b)
+            }
+            Self::FlightStatusAlert => stat.and_then(|s| match s {
+                STAT::AlertNoSPIAircraftAirborne
+                | STAT::AlertNoSPIAircraftOnGround
+                | STAT::AlertSPIAircraftAirborneOrOnGround => Some(true),
+                STAT::NotAssigned | STAT::Unknown => None,
+                _ => Some(false),
+            }),
+        }
+    }
+}
+
+/// Is the validated Mode-3/A code `code`
+fn special_code(record: &Cat48, code: SpecialCode) -> Option<bool> {
+    let mode_3_a = record.mode_3_a_code_in_octal_representation.as_ref()?;
+    (mode_3_a.g == G::Default).then(|| mode_3_a.reply.special() == Some(code))
+}
+
+/// Active if either source says so, inactive if one says so and the other doesn't tell
+fn either(a: Option<bool>, b: Option<bool>) -> Option<bool> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a || b),
+        (a, b) => a.or(b),
+    }
+}
+
+/// Condition of a target, with its identity and position from the record that started or ended
+/// it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Alert {
+    pub kind: AlertKind,
+    pub key: TargetKey,
+    pub source: DataSourceIdentifier,
+    /// I048/140
+    pub time_of_day: Option<TimeOfDay>,
+    /// I048/220
+    pub address: Option<u32>,
+    /// I048/161
+    pub track_number: Option<u16>,
+    /// I048/240 of the record, else of the last record of the target with one
+    pub callsign: Option<Callsign>,
+    /// I048/070 of the record, else of the last record of the target with one
+    pub squawk: Option<Squawk>,
+    /// I048/040
+    pub polar: Option<MeasuredPositionInPolarCoordinates>,
+    /// I048/042
+    pub cartesian: Option<CalculatedPositionCartesianCorr>,
+    /// I048/090 of the record, else of the last record of the target with one
+    pub flight_level: Option<f64>,
+}
+
+/// Start or end of a condition
+#[derive(Debug, Clone, PartialEq)]
+pub enum AlertEvent {
+    Raised(Alert),
+    Cleared(Alert),
+}
+
+impl AlertEvent {
+    pub fn alert(&self) -> &Alert {
+        match self {
+            Self::Raised(alert) | Self::Cleared(alert) => alert,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct TargetState {
+    active: Vec<AlertKind>,
+    callsign: Option<Callsign>,
+    squawk: Option<Squawk>,
+    flight_level: Option<f64>,
+    radar: (u8, u8),
+    time: Option<f64>,
+    /// Items of the last record
+    time_of_day: Option<TimeOfDay>,
+    address: Option<u32>,
+    track_number: Option<u16>,
+    polar: Option<MeasuredPositionInPolarCoordinates>,
+    cartesian: Option<CalculatedPositionCartesianCorr>,
+}
+
+impl TargetState {
+    fn alert(&self, kind: AlertKind, key: TargetKey) -> Alert {
+        Alert {
+            kind,
+            key,
+            source: DataSourceIdentifier { sac: self.radar.0, sic: self.radar.1 },
+            time_of_day: self.time_of_day.clone(),
+            address: self.address,
+            track_number: self.track_number,
+            callsign: self.callsign,
+            squawk: self.squawk,
+            polar: self.polar.clone(),
+            cartesian: self.cartesian.clone(),
+            flight_level: self.flight_level,
+        }
+    }
+}
+
+/// Alerts of every target, see the module documentation
+#[derive(Debug, Clone, Default)]
+pub struct AlertEngine {
+    targets: HashMap<TargetKey, TargetState>,
+}
+
+impl AlertEngine {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Conditions active for the target `key`
+    pub fn active(&self, key: &TargetKey) -> &[AlertKind] {
+        self.targets.get(key).map_or(&[], |t| &t.active)
+    }
+
+    /// Every target with an active condition
+    pub fn alerting(&self) -> impl Iterator<Item = (&TargetKey, &[AlertKind])> {
+        self.targets.iter().filter(|(_, t)| !t.active.is_empty()).map(|(k, t)| (k, &t.active[..]))
+    }
+
+    /// Check a record for conditions, or forget old targets on a North Marker
+    pub fn update(&mut self, message: &AsterixMessage) -> Vec<AlertEvent> {
+        match message {
+            AsterixMessage::Cat48(record) => self.update_cat48(record),
+            AsterixMessage::Cat34(record) => self.update_cat34(record),
+            AsterixMessage::Cat4(_) => vec![],
+        }
+    }
+
+    /// Forget the targets of the radar that weren't updated recently on a North Marker,
+    /// clearing their conditions with the last record of each
+    pub fn update_cat34(&mut self, record: &Cat34) -> Vec<AlertEvent> {
+        let (Some(source), Some(time), Some(MTYPE::NorthMarker)) = (
+            &record.data_source_identifier,
+            &record.time_of_day,
+            record.message_type.as_ref().map(|t| t.t),
+        ) else {
+            return vec![];
+        };
+        let (id, time) = ((source.sac, source.sic), time.seconds());
+        let stale: Vec<TargetKey> = self
+            .targets
+            .iter()
+            .filter(|(_, t)| t.radar == id)
+            .filter(|(_, t)| !t.time.is_some_and(|t| elapsed(t, time) <= MAX_AGE))
+            .map(|(key, _)| *key)
+            .collect();
+        let mut events = vec![];
+        for key in stale {
+            let Some(target) = self.targets.remove(&key) else {
+                continue;
+            };
+            events.extend(
+                target.active.iter().map(|kind| AlertEvent::Cleared(target.alert(*kind, key))),
+            );
+        }
+        events
+    }
+
+    /// Conditions of the target of a record that started or ended
+    ///
+    /// Records without I048/010, or without I048/220 and I048/161, are ignored.
+    pub fn update_cat48(&mut self, record: &Cat48) -> Vec<AlertEvent> {
+        let Some(source) = &record.data_source_identifier else {
+            return vec![];
+        };
+        let key = match (&record.aircraft_address, &record.track_number) {
+            (Some(address), _) => TargetKey::ModeS(address.address),
+            (None, Some(track)) => {
+                TargetKey::Track { sac: source.sac, sic: source.sic, number: track.number }
+            }
+            (None, None) => return vec![],
+        };
+
+        let target = self.targets.entry(key).or_default();
+        if let Some(callsign) = &record.aircraft_identification {
+            target.callsign = Some(callsign.identification);
+        }
+        target.squawk = record.squawk().or(target.squawk);
+        target.flight_level =
+            record.flight_level_in_binary_repre.as_ref().map(|f| f.fl()).or(target.flight_level);
+        target.radar = (source.sac, source.sic);
+        target.time = record.time_of_day.as_ref().map(TimeOfDay::seconds).or(target.time);
+        target.time_of_day.clone_from(&record.time_of_day);
+        target.address = record.aircraft_address.as_ref().map(|a| a.address);
+        target.track_number = record.track_number.as_ref().map(|t| t.number);
+        target.polar.clone_from(&record.measured_position_in_polar_coordinates);
+        target.cartesian.clone_from(&record.calculated_position_cartesian_coor);
+
+        let mut events = vec![];
+        for kind in AlertKind::ALL {
+            let was_active = target.active.contains(&kind);
+            match kind.reported(record) {
+                Some(true) if !was_active => {
+                    events.push(AlertEvent::Raised(target.alert(kind, key)))
+                }
+                Some(false) if was_active => {
+                    events.push(AlertEvent::Cleared(target.alert(kind, key)))
+                }
+                _ => {}
+            }
+        }
+        for event in &events {
+            match event {
+                AlertEvent::Raised(alert) => target.active.push(alert.kind),
+                AlertEvent::Cleared(alert) => target.active.retain(|k| *k != alert.kind),
+            }
+        }
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_item::CommunicationsCapabilityFlightStatus;
+    use crate::test_util::{cat34, cat48, tod};
+    use crate::types::{AIC, ARC, COM, MSSC, SI};
+
+    fn target() -> Cat48 {
+        cat48().with_address(0xabcdef)
+    }
+
+    fn status(code: u16, stat: STAT) -> Cat48 {
+        Cat48 {
+            communications_capability_flight_status: Some(CommunicationsCapabilityFlightStatus {
+                com: COM::CommACommB,
+                stat,
+                si: SI::SICodeCapable,
+                reserved: 0,
+                mssc: MSSC::Yes,
+                arc: ARC::Resolution25ft,
+                aic: AIC::Yes,
+                b1a: 1,
+                b1b: 0,
+            }),
+            ..target().with_squawk(code)
+        }
+    }
+
+    fn advisory(mb_data: [u8; 7]) -> Cat48 {
+        Cat48 {
+            acas_resolution_advisory_report: Some(ACASResolutionAdvisoryReport { mb_data }),
+            ..target()
+        }
+    }
+
+    fn kinds(events: &[AlertEvent]) -> Vec<(bool, AlertKind)> {
+        events.iter().map(|e| (matches!(e, AlertEvent::Raised(_)), e.alert().kind)).collect()
+    }
+
+    #[test]
+    fn alerts() {
+        let key = TargetKey::ModeS(0xabcdef);
+        let mut engine = AlertEngine::new();
+        let named = target().with_squawk(0o7000).with_callsign("DLH123");
+        assert!(engine.update_cat48(&named).is_empty());
+
+        let events = engine.update_cat48(&target().with_squawk(0o7700));
+        assert_eq!(kinds(&events), [(true, AlertKind::Emergency)]);
+        let alert = events[0].alert();
+        assert_eq!(alert.callsign.unwrap().trimmed(), "DLH123");
+        assert_eq!(alert.squawk, Some(Squawk::EMERGENCY));
+        assert_eq!(alert.address, Some(0xabcdef));
+
+        // without I048/070 the emergency goes on
+        assert!(engine.update_cat48(&target()).is_empty());
+        assert_eq!(engine.active(&key), [AlertKind::Emergency]);
+        assert_eq!(
+            kinds(&engine.update_cat48(&status(0o7600, STAT::AlertSPIAircraftAirborneOrOnGround))),
+            [
+                (true, AlertKind::RadioFailure),
+                (false, AlertKind::Emergency),
+                (true, AlertKind::Ident),
+                (true, AlertKind::FlightStatusAlert),
+            ]
+        );
+        assert_eq!(
+            kinds(&engine.update_cat48(&status(0o7600, STAT::NoAlertNoSPIAircraftAirborne))),
+            [(false, AlertKind::Ident), (false, AlertKind::FlightStatusAlert)]
+        );
+        assert_eq!(engine.alerting().count(), 1);
+    }
+
+    #[test]
+    fn acas_resolution_advisory() {
+        // BDS 3,0 with ARA climb, then terminated
+        let active = [0x30, 0b1000_0000, 0, 0, 0, 0, 0];
+        let terminated = [0x30, 0b1000_0000, 0, 0b0010_0000, 0, 0, 0];
+        let report = ACASResolutionAdvisoryReport { mb_data: active };
+        assert_eq!(
+            (report.ara(), report.rac(), report.rat(), report.mte()),
+            (0x2000, 0, false, false)
+        );
+        assert!(report.is_active());
+        assert!(!ACASResolutionAdvisoryReport { mb_data: terminated }.is_active());
+
+        let mut engine = AlertEngine::new();
+        assert_eq!(
+            kinds(&engine.update_cat48(&advisory(active))),
+            [(true, AlertKind::AcasResolutionAdvisory)]
+        );
+        assert!(engine.update_cat48(&advisory(active)).is_empty());
+        // without I048/260 or BDS 3,0 the advisory goes on
+        assert!(engine.update_cat48(&target()).is_empty());
+        assert_eq!(
+            kinds(&engine.update_cat48(&advisory(terminated))),
+            [(false, AlertKind::AcasResolutionAdvisory)]
+        );
+    }
+
+    #[test]
+    fn stale() {
+        let key = TargetKey::ModeS(0xabcdef);
+        let mut engine = AlertEngine::new();
+        engine.update_cat48(&target().with_squawk(0o7700).with_time(100.0));
+        let north = cat34(MTYPE::NorthMarker);
+        assert!(engine.update_cat34(&north.clone().with_time(150.0)).is_empty());
+        assert_eq!(engine.active(&key), [AlertKind::Emergency]);
+
+        let events = engine.update(&AsterixMessage::Cat34(north.with_time(161.0)));
+        assert_eq!(kinds(&events), [(false, AlertKind::Emergency)]);
+        assert_eq!(events[0].alert().time_of_day, Some(tod(100.0)));
+        assert_eq!(events[0].alert().squawk, Some(Squawk::EMERGENCY));
+        assert_eq!(engine.alerting().count(), 0);
+    }
+}
@@ -0,0 +1,535 @@
+//! Registration bias estimation from plots of the same Mode S targets seen by several radars
+//!
+//! Each radar is modelled with a range gain, a range offset and an azimuth offset: a target at
+//! slant range `rho` and azimuth `theta` is measured at `rho * (1 + gain) + offset` and
+//! `theta + azimuth`. When two radars measure the same target at about the same time, the
+//! difference of their earth-centred positions is linear in the biases of both, to first order.
+//! `BiasEstimator` pairs the plots of each Mode S address across radars and accumulates these
+//! equations, solving them by least squares.
+//!
+//! Only differences between radars are observable, so one reference radar is held unbiased: the
+//! one given with `BiasEstimator::with_reference`, else the first radar seen. Time stamping
+//! biases are not estimated, plots of the other radar are moved to the time of the new plot with
+//! their I048/200.
+
+use std::collections::HashMap;
+
+use deku::DekuError;
+
+use crate::data_item::MeasuredPositionInPolarCoordinates;
+use crate::geodesy::{self, Ecef, Wgs84, METERS_PER_NM};
+use crate::modifier::{self, elapsed, HEADING_16};
+use crate::sensor::SensorRegistry;
+use crate::{AsterixMessage, Cat34, Cat48};
+
+/// Biases per radar
+const UNKNOWNS: usize = 3;
+/// Steps of the numerical derivatives of positions, in NM and degrees
+const RHO_STEP: f64 = 0.01;
+const THETA_STEP: f64 = 0.01;
+/// LSB of I063/080 SRG
+const RANGE_GAIN_LSB: f64 = 1e-5;
+/// LSB of I063/080 SRB in NM
+const RANGE_BIAS_LSB: f64 = 1.0 / 128.0;
+
+/// Parameters of `BiasEstimator`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BiasConfig {
+    /// Largest time in seconds between the plots of a pair
+    pub max_dt: f64,
+    /// Pairs a radar needs before its biases are estimated
+    pub min_pairs: u32,
+}
+
+impl Default for BiasConfig {
+    fn default() -> Self {
+        Self { max_dt: 2.0, min_pairs: 20 }
+    }
+}
+
+/// Estimated biases of a radar, as in CAT063
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SensorBias {
+    /// System Area Code of the radar
+    pub sac: u8,
+    /// System Identification Code of the radar
+    pub sic: u8,
+    /// I063/080 SSR range gain
+    pub range_gain: f64,
+    /// I063/080 SSR range bias in NM
+    pub range_bias_nm: f64,
+    /// I063/081 SSR azimuth bias in degrees
+    pub azimuth_bias_deg: f64,
+    /// Plot pairs with other radars
+    pub pairs: u32,
+}
+
+impl SensorBias {
+    /// I063/080 SRG, LSB of 10^-5
+    pub fn srg(&self) -> Result<i16, DekuError> {
+        Ok(modifier::signed(self.range_gain, RANGE_GAIN_LSB, 16, "I063/080 SRG")? as i16)
+    }
+
+    /// I063/080 SRB, LSB of 1/128 NM
+    pub fn srb(&self) -> Result<i16, DekuError> {
+        Ok(modifier::signed(self.range_bias_nm, RANGE_BIAS_LSB, 16, "I063/080 SRB")? as i16)
+    }
+
+    /// I063/081 SAB, LSB of 360/2^16 degrees
+    pub fn sab(&self) -> Result<i16, DekuError> {
+        Ok(modifier::signed(self.azimuth_bias_deg, HEADING_16, 16, "I063/081 SAB")? as i16)
+    }
+
+    /// I048/040 of the radar with the biases removed
+    pub fn correct(
+        &self,
+        position: &MeasuredPositionInPolarCoordinates,
+    ) -> Result<MeasuredPositionInPolarCoordinates, DekuError> {
+        let mut corrected = position.clone();
+        corrected.set_rho_nm((position.rho_nm() - self.range_bias_nm) / (1.0 + self.range_gain))?;
+        corrected.set_theta_deg_wrapped(position.theta_deg() - self.azimuth_bias_deg)?;
+        Ok(corrected)
+    }
+}
+
+/// Plot of a Mode S target, with the derivatives of its position by the biases of its radar
+#[derive(Debug, Clone, Copy)]
+struct Observation {
+    time: f64,
+    position: Ecef,
+    /// Earth-centred velocity in m/s from I048/200
+    velocity: Option<Ecef>,
+    /// Derivatives by range gain, range offset and azimuth offset
+    jacobian: [Ecef; UNKNOWNS],
+}
+
+impl Observation {
+    fn new(record: &Cat48, radar: &Wgs84) -> Option<Self> {
+        let polar = record.measured_position_in_polar_coordinates.as_ref()?;
+        let time = record.time_of_day.as_ref()?.seconds();
+        let height = geodesy::plot_height(record)?;
+        let (rho, theta) = (polar.rho_nm(), polar.theta_deg());
+        let at = |rho, theta| {
+            geodesy::polar_to_wgs84(radar, rho, theta, height).ok().map(|p| (p, p.to_ecef()))
+        };
+        let (wgs84, position) = at(rho, theta)?;
+        let by_rho = difference(&at(rho + RHO_STEP, theta)?.1, &position, RHO_STEP);
+        let by_theta = difference(&at(rho, theta + THETA_STEP)?.1, &position, THETA_STEP);
+
+        let velocity = record.calculated_track_velocity.as_ref().map(|velocity| {
+            let speed = velocity.groundspeed_nm_s() * METERS_PER_NM;
+            let (sin, cos) = velocity.heading_deg().to_radians().sin_cos();
+            let ahead =
+                wgs84.offset(&geodesy::Enu { east: speed * sin, north: speed * cos, up: 0.0 });
+            difference(&ahead.to_ecef(), &position, 1.0)
+        });
+        Some(Self { time, position, velocity, jacobian: [scale(&by_rho, rho), by_rho, by_theta] })
+    }
+
+    /// Position predicted at `time`, `None` without I048/200 unless at the same time
+    fn predict(&self, time: f64) -> Option<Ecef> {
+        let dt = elapsed(self.time, time);
+        if dt == 0.0 {
+            return Some(self.position);
+        }
+        let velocity = self.velocity?;
+        Some(Ecef {
+            x: self.position.x + velocity.x * dt,
+            y: self.position.y + velocity.y * dt,
+            z: self.position.z + velocity.z * dt,
+        })
+    }
+}
+
+/// `(a - b) / step`
+fn difference(a: &Ecef, b: &Ecef, step: f64) -> Ecef {
+    Ecef { x: (a.x - b.x) / step, y: (a.y - b.y) / step, z: (a.z - b.z) / step }
+}
+
+fn scale(a: &Ecef, factor: f64) -> Ecef {
+    Ecef { x: a.x * factor, y: a.y * factor, z: a.z * factor }
+}
+
+/// Least squares estimation of radar biases, see the module documentation
+#[derive(Debug, Clone, Default)]
+pub struct BiasEstimator {
+    config: BiasConfig,
+    sensors: SensorRegistry,
+    reference: Option<(u8, u8)>,
+    /// Radars in the order of their unknowns
+    radars: Vec<(u8, u8)>,
+    pairs: Vec<u32>,
+    /// Last plot of each Mode S address from each radar, at most `max_dt` before the last plot
+    latest: HashMap<(u32, usize), Observation>,
+    /// Normal equations, row major, of the biases of every radar
+    normal: Vec<f64>,
+    rhs: Vec<f64>,
+}
+
+impl BiasEstimator {
+    pub fn new(config: BiasConfig) -> Self {
+        Self { config, ..Self::default() }
+    }
+
+    /// Hold radar `sac`, `sic` unbiased
+    pub fn with_reference(mut self, sac: u8, sic: u8) -> Self {
+        self.reference = Some((sac, sic));
+        self
+    }
+
+    /// Use the positions of the sensors of `sensors`, updated by I034/120
+    pub fn with_sensors(mut self, sensors: SensorRegistry) -> Self {
+        self.sensors = sensors;
+        self
+    }
+
+    /// Sensors with the radar positions
+    pub fn sensors(&self) -> &SensorRegistry {
+        &self.sensors
+    }
+
+    /// Radar held unbiased
+    pub fn reference(&self) -> Option<(u8, u8)> {
+        self.reference
+    }
+
+    /// Learn a radar position, or pair a plot
+    pub fn update(&mut self, message: &AsterixMessage) {
+        match message {
+            AsterixMessage::Cat48(record) => self.update_cat48(record),
+            AsterixMessage::Cat34(record) => self.update_cat34(record),
+            AsterixMessage::Cat4(_) => {}
+        }
+    }
+
+    /// Learn the position of the radar from I034/120, see `SensorRegistry::update_cat34`
+    pub fn update_cat34(&mut self, record: &Cat34) {
+        self.sensors.update_cat34(record);
+    }
+
+    /// Pair a plot with the last plots of its Mode S address from the other radars
+    ///
+    /// Plots need I048/010, I048/040, I048/140, I048/220 and a height, and a radar of known
+    /// position.
+    pub fn update_cat48(&mut self, record: &Cat48) {
+        let (Some(source), Some(address)) =
+            (&record.data_source_identifier, &record.aircraft_address)
+        else {
+            return;
+        };
+        let id = (source.sac, source.sic);
+        let Some(observation) =
+            self.sensors.position(source).and_then(|radar| Observation::new(record, &radar))
+        else {
+            return;
+        };
+        self.reference.get_or_insert(id);
+        let radar = self.radar(id);
+        let max_dt = self.config.max_dt;
+        self.latest.retain(|_, previous| elapsed(previous.time, observation.time).abs() <= max_dt);
+
+        for other in 0..self.radars.len() {
+            if other == radar {
+                continue;
+            }
+            let Some(previous) = self.latest.get(&(address.address, other)).copied() else {
+                continue;
+            };
+            if let Some(position) = previous.predict(observation.time) {
+                self.add_pair(radar, &observation, other, &previous, &position);
+            }
+        }
+        self.latest.insert((address.address, radar), observation);
+    }
+
+    /// Estimated biases of the radars with enough pairs, and of the reference
+    ///
+    /// `None` until a radar other than the reference has `min_pairs` pairs, or if the pairs
+    /// don't determine the biases.
+    pub fn estimate(&self) -> Option<Vec<SensorBias>> {
+        let reference = self.reference?;
+        let estimated: Vec<usize> = (0..self.radars.len())
+            .filter(|&r| self.radars[r] != reference && self.pairs[r] >= self.config.min_pairs)
+            .collect();
+        if estimated.is_empty() {
+            return None;
+        }
+
+        let columns: Vec<usize> =
+            estimated.iter().flat_map(|r| (0..UNKNOWNS).map(move |k| r * UNKNOWNS + k)).collect();
+        let size = self.rhs.len();
+        let matrix = columns
+            .iter()
+            .map(|&row| columns.iter().map(|&column| self.normal[row * size + column]).collect())
+            .collect();
+        let rhs = columns.iter().map(|&row| self.rhs[row]).collect();
+        let solution = solve(matrix, rhs)?;
+
+        let mut biases = vec![];
+        for (r, &(sac, sic)) in self.radars.iter().enumerate() {
+            let bias =
+                |k| estimated.iter().position(|&e| e == r).map(|i| solution[i * UNKNOWNS + k]);
+            if (sac, sic) != reference && bias(0).is_none() {
+                continue;
+            }
+            biases.push(SensorBias {
+                sac,
+                sic,
+                range_gain: bias(0).unwrap_or_default(),
+                range_bias_nm: bias(1).unwrap_or_default(),
+                azimuth_bias_deg: bias(2).unwrap_or_default(),
+                pairs: self.pairs[r],
+            });
+        }
+        Some(biases)
+    }
+
+    /// Index of radar `id`, growing the normal equations for a new radar
+    fn radar(&mut self, id: (u8, u8)) -> usize {
+        if let Some(index) = self.radars.iter().position(|r| *r == id) {
+            return index;
+        }
+        let size = self.rhs.len();
+        let grown = size + UNKNOWNS;
+        let mut normal = vec![0.0; grown * grown];
+        for row in 0..size {
+            normal[row * grown..row * grown + size]
+                .copy_from_slice(&self.normal[row * size..(row + 1) * size]);
+        }
+        self.normal = normal;
+        self.rhs.resize(grown, 0.0);
+        self.radars.push(id);
+        self.pairs.push(0);
+        self.radars.len() - 1
+    }
+
+    /// Accumulate `observation` of radar `a` and `previous` of radar `b`, predicted to
+    /// `position`, into the normal equations
+    ///
+    /// `observation - position = J_a · bias_a - J_b · bias_b`
+    fn add_pair(
+        &mut self,
+        a: usize,
+        observation: &Observation,
+        b: usize,
+        previous: &Observation,
+        position: &Ecef,
+    ) {
+        let size = self.rhs.len();
+        let residual = difference(&observation.position, position, 1.0);
+        for (axis, y) in [residual.x, residual.y, residual.z].into_iter().enumerate() {
+            let component = |e: &Ecef| [e.x, e.y, e.z][axis];
+            let row: Vec<(usize, f64)> = (0..UNKNOWNS)
+                .map(|k| (a * UNKNOWNS + k, component(&observation.jacobian[k])))
+                .chain((0..UNKNOWNS).map(|k| (b * UNKNOWNS + k, -component(&previous.jacobian[k]))))
+                .collect();
+            for &(i, vi) in &row {
+                self.rhs[i] += vi * y;
+                for &(j, vj) in &row {
+                    self.normal[i * size + j] += vi * vj;
+                }
+            }
+        }
+        self.pairs[a] += 1;
+        self.pairs[b] += 1;
+    }
+}
+
+/// Solve `matrix · x = rhs` by Gaussian elimination with partial pivoting, `None` if singular
+fn solve(mut matrix: Vec<Vec<f64>>, mut rhs: Vec<f64>) -> Option<Vec<f64>> {
+    let n = rhs.len();
+    let largest = matrix.iter().flatten().fold(0.0_f64, |m, v| m.max(v.abs()));
+    for column in 0..n {
+        let pivot = (column..n)
+            .max_by(|&a, &b| matrix[a][column].abs().total_cmp(&matrix[b][column].abs()))?;
+        if matrix[pivot][column].abs() <= largest * 1e-12 {
+            return None;
+        }
+        matrix.swap(column, pivot);
+        rhs.swap(column, pivot);
+        let (pivot_rows, rows) = matrix.split_at_mut(column + 1);
+        let pivot_row = &pivot_rows[column];
+        for (offset, row) in rows.iter_mut().enumerate() {
+            let factor = row[column] / pivot_row[column];
+            for (value, pivot) in row[column..].iter_mut().zip(&pivot_row[column..]) {
+                *value -= factor * pivot;
+            }
+            rhs[column + 1 + offset] -= factor * rhs[column];
+        }
+    }
+    let mut x = vec![0.0; n];
+    for row in (0..n).rev() {
+        let sum: f64 = (row + 1..n).map(|k| matrix[row][k] * x[k]).sum();
+        x[row] = (rhs[row] - sum) / matrix[row][row];
+    }
+    Some(x)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sensor::SensorConfig;
+    use crate::test_util::cat48;
+
+    const A: Wgs84 = Wgs84 { latitude: 50.5, longitude: 8.5, height: 100.0 };
+    const B: Wgs84 = Wgs84 { latitude: 49.8, longitude: 9.4, height: 300.0 };
+
+    fn plot(sic: u8, radar: &Wgs84, target: &Wgs84, bias: (f64, f64, f64), address: u32) -> Cat48 {
+        let (rho, theta) = geodesy::wgs84_to_polar(radar, target);
+        cat48()
+            .with_source(25, sic)
+            .with_time(f64::from(address))
+            .with_polar(rho * (1.0 + bias.0) + bias.1, theta + bias.2)
+            .with_flight_level(target.height / geodesy::METERS_PER_FT / 100.0)
+            .with_address(address)
+    }
+
+    const C: Wgs84 = Wgs84 { latitude: 50.1, longitude: 8.1, height: 200.0 };
+    const UNBIASED: (f64, f64, f64) = (0.0, 0.0, 0.0);
+
+    fn estimator(config: BiasConfig) -> BiasEstimator {
+        let mut registry = SensorRegistry::new();
+        for (sic, position) in [(1, A), (2, B), (3, C)] {
+            registry
+                .insert(SensorConfig { position: Some(position), ..SensorConfig::new(25, sic) });
+        }
+        BiasEstimator::new(config).with_sensors(registry)
+    }
+
+    /// Plots of twenty targets, one address each, seen by each of `radars` in turn
+    fn feed(estimator: &mut BiasEstimator, radars: &[(u8, Wgs84, (f64, f64, f64))]) {
+        let mut address = 1;
+        for latitude in [49.6, 49.9, 50.2, 50.5, 50.8] {
+            for longitude in [8.0, 8.6, 9.2, 9.8] {
+                let target = Wgs84::new(latitude, longitude, 9000.0);
+                for (sic, radar, bias) in radars {
+                    estimator.update_cat48(&plot(*sic, radar, &target, *bias, address));
+                }
+                address += 1;
+            }
+        }
+    }
+
+    fn assert_bias(estimated: &SensorBias, bias: (f64, f64, f64)) {
+        assert!((estimated.range_gain - bias.0).abs() < 1e-4, "{estimated:?}");
+        assert!((estimated.range_bias_nm - bias.1).abs() < 0.01, "{estimated:?}");
+        assert!((estimated.azimuth_bias_deg - bias.2).abs() < 0.01, "{estimated:?}");
+    }
+
+    #[test]
+    fn estimate() {
+        let mut estimator = estimator(BiasConfig::default());
+        let bias = (0.002, 0.1, 0.2);
+
+        let mut address = 1;
+        for latitude in [49.6, 49.9, 50.2, 50.5, 50.8] {
+            for longitude in [8.0, 8.6, 9.2, 9.8] {
+                let target = Wgs84::new(latitude, longitude, 9000.0);
+                estimator.update_cat48(&plot(1, &A, &target, UNBIASED, address));
+                assert!(estimator.estimate().is_none());
+                estimator.update_cat48(&plot(2, &B, &target, bias, address));
+                address += 1;
+            }
+        }
+        assert_eq!(estimator.reference(), Some((25, 1)));
+
+        let biases = estimator.estimate().unwrap();
+        assert_eq!(biases.len(), 2);
+        assert_eq!((biases[0].sic, biases[0].range_gain, biases[0].pairs), (1, 0.0, 20));
+        let b = biases[1];
+        assert_bias(&b, bias);
+        assert_eq!(b.sab().unwrap(), (0.2 / HEADING_16).round() as i16);
+        assert_eq!(b.srb().unwrap(), 13);
+        assert!((f64::from(b.srg().unwrap()) - 200.0).abs() <= 10.0);
+
+        let target = Wgs84::new(50.0, 9.0, 9000.0);
+        let measured =
+            plot(2, &B, &target, bias, 1).measured_position_in_polar_coordinates.unwrap();
+        let corrected = b.correct(&measured).unwrap();
+        let (rho, theta) = geodesy::wgs84_to_polar(&B, &target);
+        assert!((corrected.rho_nm() - rho).abs() < 0.01);
+        assert!((corrected.theta_deg() - theta).abs() < 0.01);
+    }
+
+    #[test]
+    fn reference() {
+        let mut estimator = estimator(BiasConfig::default()).with_reference(25, 2);
+        let bias = (-0.001, -0.2, 0.1);
+        feed(&mut estimator, &[(1, A, bias), (2, B, UNBIASED)]);
+        assert_eq!(estimator.reference(), Some((25, 2)));
+        let biases = estimator.estimate().unwrap();
+        assert_eq!((biases[1].sic, biases[1].range_gain), (2, 0.0));
+        assert_eq!(biases[0].sic, 1);
+        assert_bias(&biases[0], bias);
+    }
+
+    #[test]
+    fn three_radars() {
+        let mut estimator = estimator(BiasConfig::default());
+        let (b, c) = ((0.002, 0.1, 0.2), (-0.001, 0.05, -0.3));
+        feed(&mut estimator, &[(1, A, UNBIASED), (2, B, b), (3, C, c)]);
+        let biases = estimator.estimate().unwrap();
+        assert_eq!(biases.len(), 3);
+        assert_eq!(biases.iter().map(|b| b.pairs).collect::<Vec<_>>(), [40, 40, 40]);
+        assert_bias(&biases[1], b);
+        assert_bias(&biases[2], c);
+    }
+
+    #[test]
+    fn max_dt() {
+        let mut estimator = estimator(BiasConfig { min_pairs: 1, ..BiasConfig::default() });
+        let target = Wgs84::new(50.0, 9.0, 9000.0);
+        estimator.update_cat48(&plot(1, &A, &target, UNBIASED, 1).with_time(10.0));
+        estimator.update_cat48(&plot(2, &B, &target, UNBIASED, 1).with_time(12.5));
+        assert!(estimator.estimate().is_none());
+        // plots older than max_dt are dropped
+        assert_eq!(estimator.latest.len(), 1);
+    }
+
+    #[test]
+    fn predict() {
+        let mut estimator = estimator(BiasConfig::default());
+        let bias = (0.002, 0.1, 0.2);
+        let (speed, heading) = (0.1, 60.0);
+        let mut address = 1;
+        for latitude in [49.6, 49.9, 50.2, 50.5, 50.8] {
+            for longitude in [8.0, 8.6, 9.2, 9.8] {
+                let target = Wgs84::new(latitude, longitude, 9000.0);
+                let time = f64::from(address) * 10.0;
+                let record = plot(1, &A, &target, UNBIASED, address).with_time(time);
+                estimator.update_cat48(&record.with_velocity(speed, heading));
+                // a second later
+                let distance = speed * METERS_PER_NM;
+                let (sin, cos) = heading.to_radians().sin_cos();
+                let moved = target.offset(&geodesy::Enu {
+                    east: distance * sin,
+                    north: distance * cos,
+                    up: 0.0,
+                });
+                estimator.update_cat48(&plot(2, &B, &moved, bias, address).with_time(time + 1.0));
+                address += 1;
+            }
+        }
+        let biases = estimator.estimate().unwrap();
+        assert_eq!(biases[1].pairs, 20);
+        assert_bias(&biases[1], bias);
+    }
+
+    #[test]
+    fn singular() {
+        // a single target position can't separate range gain from range offset
+        let mut estimator = estimator(BiasConfig::default());
+        let target = Wgs84::new(50.0, 9.0, 9000.0);
+        for address in 1..=20 {
+            estimator.update_cat48(&plot(1, &A, &target, UNBIASED, address));
+            estimator.update_cat48(&plot(2, &B, &target, UNBIASED, address));
+        }
+        assert!(estimator.estimate().is_none());
+
+        assert_eq!(solve(vec![vec![1.0, 2.0], vec![2.0, 4.0]], vec![1.0, 2.0]), None);
+        assert_eq!(
+            solve(vec![vec![2.0, 0.0], vec![0.0, 4.0]], vec![1.0, 2.0]),
+            Some(vec![0.5, 0.5])
+        );
+    }
+}
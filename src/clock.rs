@@ -0,0 +1,214 @@
+//! Absolute UTC time from the time of day of a record
+//!
+//! `TimeOfDay` only counts seconds since UTC midnight. The date is taken from a reference, such
+//! as the time a packet was received, choosing the day that puts the record closest to the
+//! reference. Records received shortly after midnight that were stamped shortly before it are
+//! therefore placed on the previous day.
+//!
+//! Times are `SystemTime`, which like ASTERIX doesn't count leap seconds.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use deku::DekuError;
+
+use crate::data_item::TimeOfDay;
+use crate::error::Error;
+
+const SECONDS_PER_DAY: u64 = 86_400;
+
+/// Calendar date in UTC
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Date {
+    pub year: i32,
+    /// 1..=12
+    pub month: u8,
+    /// 1..=31
+    pub day: u8,
+}
+
+impl Date {
+    pub fn new(year: i32, month: u8, day: u8) -> Result<Self, Error> {
+        let date = Self { year, month, day };
+        if !(1..=12).contains(&month) || day == 0 || Self::from_days(date.days()) != date {
+            return Err(Error::InvalidDate { year, month, day });
+        }
+        Ok(date)
+    }
+
+    /// Days since 1970-01-01
+    pub fn days(&self) -> i64 {
+        let year = i64::from(self.year) - i64::from(self.month <= 2);
+        let era = year.div_euclid(400);
+        let year_of_era = year.rem_euclid(400);
+        let month = i64::from(self.month);
+        let day_of_year =
+            (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + i64::from(self.day) - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+        era * 146_097 + day_of_era - 719_468
+    }
+
+    /// Date `days` after 1970-01-01
+    pub fn from_days(days: i64) -> Self {
+        let days = days + 719_468;
+        let era = days.div_euclid(146_097);
+        let day_of_era = days.rem_euclid(146_097);
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let mp = (5 * day_of_year + 2) / 153;
+        let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u8;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u8;
+        let year = (year_of_era + era * 400 + i64::from(month <= 2)) as i32;
+        Self { year, month, day }
+    }
+
+    /// Date of `time`
+    pub fn of(time: SystemTime) -> Self {
+        Self::from_days(seconds_since_epoch(time).div_euclid(SECONDS_PER_DAY as f64) as i64)
+    }
+
+    /// Midnight at the start of this date
+    pub fn midnight(&self) -> SystemTime {
+        let seconds = self.days() * SECONDS_PER_DAY as i64;
+        if seconds >= 0 {
+            UNIX_EPOCH + Duration::from_secs(seconds.unsigned_abs())
+        } else {
+            UNIX_EPOCH - Duration::from_secs(seconds.unsigned_abs())
+        }
+    }
+}
+
+fn seconds_since_epoch(time: SystemTime) -> f64 {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(since) => since.as_secs_f64(),
+        Err(before) => -before.duration().as_secs_f64(),
+    }
+}
+
+/// `time_of_day` on `date`
+pub fn on_date(time_of_day: &TimeOfDay, date: Date) -> SystemTime {
+    date.midnight() + Duration::from_secs_f64(time_of_day.seconds())
+}
+
+/// `time_of_day` on the day that puts it closest to `reference`, such as the time the record was
+/// received
+pub fn nearest(time_of_day: &TimeOfDay, reference: SystemTime) -> SystemTime {
+    let reference_seconds = seconds_since_epoch(reference);
+    let day = reference_seconds.div_euclid(SECONDS_PER_DAY as f64) as i64;
+    let offset = time_of_day.seconds() - reference_seconds.rem_euclid(SECONDS_PER_DAY as f64);
+    // within half a day of the reference
+    let day = if offset > SECONDS_PER_DAY as f64 / 2.0 {
+        day - 1
+    } else if offset < -(SECONDS_PER_DAY as f64) / 2.0 {
+        day + 1
+    } else {
+        day
+    };
+    on_date(time_of_day, Date::from_days(day))
+}
+
+/// Time of day of `time`, rounded to the nearest 1/128 s
+pub fn time_of_day(time: SystemTime) -> Result<TimeOfDay, DekuError> {
+    TimeOfDay::from_seconds(seconds_since_epoch(time).rem_euclid(SECONDS_PER_DAY as f64))
+}
+
+/// Reconstructs absolute times from a stream of records in order, such as a recording without
+/// receive timestamps
+///
+/// Each time of day is placed closest to the previous one, so the date advances when the stream
+/// crosses midnight.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Clock {
+    last: SystemTime,
+}
+
+impl Clock {
+    /// Start from a known time, such as the receive time of the first packet
+    pub fn new(reference: SystemTime) -> Self {
+        Self { last: reference }
+    }
+
+    /// Start from noon on `date`, for a stream that starts on `date`
+    pub fn from_date(date: Date) -> Self {
+        Self::new(date.midnight() + Duration::from_secs(SECONDS_PER_DAY / 2))
+    }
+
+    /// Absolute time of `time_of_day`, which becomes the reference for the next one
+    pub fn absolute(&mut self, time_of_day: &TimeOfDay) -> SystemTime {
+        self.last = nearest(time_of_day, self.last);
+        self.last
+    }
+
+    /// Latest absolute time
+    pub fn last(&self) -> SystemTime {
+        self.last
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::tod;
+
+    #[test]
+    fn civil_days() {
+        assert_eq!(Date::new(1970, 1, 1).unwrap().days(), 0);
+        assert_eq!(Date::new(2000, 3, 1).unwrap().days(), 11_017);
+        assert_eq!(Date::new(2024, 2, 29).unwrap().days(), 19_782);
+        assert_eq!(Date::from_days(-1), Date::new(1969, 12, 31).unwrap());
+        let err = Date::new(2023, 2, 29).unwrap_err();
+        assert_eq!(err, Error::InvalidDate { year: 2023, month: 2, day: 29 });
+        assert_eq!(err.to_string(), "2023-02-29 is not a date");
+        assert!(Date::new(2023, 13, 1).is_err());
+        // era, century and leap day boundaries
+        for (year, month, day) in [
+            (0, 3, 1),
+            (1600, 2, 29),
+            (1900, 2, 28),
+            (1900, 3, 1),
+            (1999, 12, 31),
+            (2000, 2, 29),
+            (2000, 3, 1),
+            (2100, 3, 1),
+            (2399, 12, 31),
+            (2400, 1, 1),
+        ] {
+            let date = Date::new(year, month, day).unwrap();
+            assert_eq!(Date::from_days(date.days()), date);
+            assert_eq!(Date::from_days(date.days() - 1).days(), date.days() - 1);
+        }
+        assert!(Date::new(1900, 2, 29).is_err());
+        assert!(Date::new(2100, 2, 29).is_err());
+        for days in (-800_000..800_000).step_by(997) {
+            assert_eq!(Date::from_days(days).days(), days);
+        }
+    }
+
+    #[test]
+    fn midnight_rollover() {
+        let date = Date::new(2024, 2, 29).unwrap();
+        let midnight = date.midnight();
+        assert_eq!(on_date(&tod(60.0), date), midnight + Duration::from_secs(60));
+
+        // stamped just before midnight, received just after
+        let received = midnight + Duration::from_secs(2);
+        assert_eq!(nearest(&tod(86_399.0), received), midnight - Duration::from_secs(1));
+        // stamped just after midnight, received just before
+        let received = midnight - Duration::from_secs(1);
+        assert_eq!(nearest(&tod(1.0), received), midnight + Duration::from_secs(1));
+        assert_eq!(Date::of(nearest(&tod(1.0), received)), date);
+
+        let mut clock = Clock::from_date(Date::new(2024, 2, 28).unwrap());
+        for seconds in [86_000.0, 86_399.5, 0.5, 10.0] {
+            clock.absolute(&tod(seconds));
+        }
+        assert_eq!(clock.last(), midnight + Duration::from_secs(10));
+    }
+
+    #[test]
+    fn encode_time_of_day() {
+        let time = Date::new(2024, 3, 1).unwrap().midnight() + Duration::from_millis(27_354_602);
+        assert_eq!(time_of_day(time).unwrap().time, 3_501_389);
+        assert_eq!(time_of_day(UNIX_EPOCH - Duration::from_secs(1)).unwrap().seconds(), 86_399.0);
+    }
+}
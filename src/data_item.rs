@@ -1,23 +1,25 @@
 //! Defined Data Items that are used for formal parsing of data structs in categories
 
-use crate::custom_read_write::{read, write, Op};
+use crate::custom_read_write::write;
 use crate::fspec::is_fspec;
 use crate::modifier;
 use crate::types::{
-    DataFilterTYP, MessageCounterTYP, AIC, ANT, ARC, CDM, CHAB, CLU, CNF, CODE, COM, D, DLF, DOU,
-    ERR, FOEFRI, FX, G, GHO, L, MAH, ME, MI, MSC, MSSC, MTYPE, NOGO, OVL, POL, RAB, RAD, RDP, RDPC,
-    RDPR, RED, SCF, SI, SIM, SPI, STAT, STC, SUP, TCC, TRE, TST, TSV, TYP, V, XPP,
+    Callsign, DataFilterTYP, MessageCounterTYP, Mode1Code, SafetyNetMTYPE, Squawk, AIC, ANT, ARC,
+    CDM, CHAB, CLU, CNF, CODE, COM, D, DLF, DOU, EP, ERR, FOEFRI, FX, G, GHO, L, MAH, ME, MI, MSC,
+    MSSC, MTYPE, NOGO, OVL, POL, RAB, RAD, RDP, RDPC, RDPR, RED, SCF, SI, SIM, SPI, STAT, STC, SUP,
+    TCC, TRE, TST, TSV, TYP, V, VAL, XPP,
 };
-use deku::bitvec::{BitSlice, BitVec, Msb0};
 use deku::prelude::*;
 
-const RHO_MODIFIER: f32 = 1.0 / 256.0;
-const THETA_MODIFIER: f32 = 360.0 / 65536.0;
+/// 1/256 NM
+const RHO_MODIFIER: f64 = 1.0 / 256.0;
+/// 360/2^16 degrees
+const THETA_MODIFIER: f64 = 360.0 / 65536.0;
 
 /// Identification of the radar station from which the data is received
 ///
 /// Data Item I048/010
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, Clone, DekuRead, DekuWrite)]
 #[deku(ctx = "_: deku::ctx::Endian")]
 pub struct DataSourceIdentifier {
     /// System Area Code
@@ -27,35 +29,66 @@ pub struct DataSourceIdentifier {
 }
 
 impl DataSourceIdentifier {
+    pub const FRN_4: u8 = 0b1000_0000;
     pub const FRN_34: u8 = 0b1000_0000;
     pub const FRN_48: u8 = 0b1000_0000;
 }
 
 /// Absolute time stamping expressed as Co-ordinated Universal Time (UTC)
 ///
-/// Data Item I048/140
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
-#[deku(ctx = "_: deku::ctx::Endian")]
+/// Data Item I048/140, I034/030 and I004/020
+#[derive(Debug, PartialEq, Clone, DekuRead, DekuWrite)]
+// `_category` is only used when writing, to name the data item in encoding errors
+#[deku(ctx = "_: deku::ctx::Endian, _category: u8")]
 pub struct TimeOfDay {
-    #[deku(
-        reader = "read::bits_to_f32(deku::rest, 24, Self::MODIFIER, Op::Divide)",
-        writer = "write::f32_u32(&self.time, 24, Self::MODIFIER, Op::Multiply, deku::output)"
-    )]
-    pub time: f32,
+    /// Elapsed time since last midnight, LSB of 1/128 s
+    #[deku(writer = "write::unsigned(&self.time, 24, Self::name(_category), deku::output)")]
+    #[deku(bits = "24", endian = "big")]
+    pub time: u32,
 }
 
 impl TimeOfDay {
+    pub const FRN_4: u8 = 0b1_0000;
     pub const FRN_34: u8 = 0b10_0000;
     pub const FRN_48: u8 = 0b100_0000;
-    const MODIFIER: f32 = 128.0;
+    const MODIFIER: f64 = 1.0 / 128.0;
+    const LSB_PER_DAY: u64 = 86400 * 128;
+
+    fn name(category: u8) -> &'static str {
+        match category {
+            4 => "I004/020 time of message",
+            34 => "I034/030 time of day",
+            _ => "I048/140 time of day",
+        }
+    }
+
+    /// Create from seconds since midnight, rounded to the nearest 1/128 s
+    pub fn from_seconds(seconds: f64) -> Result<Self, DekuError> {
+        if !(0.0..modifier::SECONDS_PER_DAY).contains(&seconds) {
+            return Err(DekuError::InvalidParam(format!(
+                "time of day: {seconds} s is outside of 0..86400"
+            )));
+        }
+        // 86399.999 rounds up to midnight
+        let time = modifier::unsigned(seconds, Self::MODIFIER, 24, "time of day")?;
+        Ok(Self { time: (time % Self::LSB_PER_DAY) as u32 })
+    }
+
+    /// Seconds since midnight
+    pub fn seconds(&self) -> f64 {
+        f64::from(self.time) * Self::MODIFIER
+    }
+
+    pub fn set_seconds(&mut self, seconds: f64) -> Result<(), DekuError> {
+        *self = Self::from_seconds(seconds)?;
+        Ok(())
+    }
 }
 
 /// Type and properties of the target report
 ///
 /// Data Item I048/020
-///
-/// TODO: This can extend with FX bit.
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, Clone, DekuRead, DekuWrite)]
 #[deku(ctx = "_: deku::ctx::Endian")]
 pub struct TargetReportDescriptor {
     pub typ: TYP,
@@ -63,6 +96,8 @@ pub struct TargetReportDescriptor {
     pub rdp: RDP,
     pub spi: SPI,
     pub rab: RAB,
+    /// Writing checks that the FX bits match the extents that are present
+    #[deku(writer = "self.check_extents().and_then(|_| fx1.write(deku::output, ()))")]
     pub fx1: FX,
     #[deku(skip, cond = "*fx1 != FX::ExtensionIntoFirstExtent")]
     pub tst: Option<TST>,
@@ -78,38 +113,151 @@ pub struct TargetReportDescriptor {
     pub foe_fri: Option<FOEFRI>,
     #[deku(skip, cond = "*fx1 != FX::ExtensionIntoFirstExtent")]
     pub fx2: Option<FX>,
+    /// ADS-B element populated
+    #[deku(skip, cond = "*fx2 != Some(FX::ExtensionIntoFirstExtent)")]
+    pub adsb_ep: Option<EP>,
+    /// ADS-B information available
+    #[deku(skip, cond = "*fx2 != Some(FX::ExtensionIntoFirstExtent)")]
+    pub adsb_val: Option<VAL>,
+    /// Surveillance Cluster Network element populated
+    #[deku(skip, cond = "*fx2 != Some(FX::ExtensionIntoFirstExtent)")]
+    pub scn_ep: Option<EP>,
+    /// Surveillance Cluster Network information available
+    #[deku(skip, cond = "*fx2 != Some(FX::ExtensionIntoFirstExtent)")]
+    pub scn_val: Option<VAL>,
+    /// Passive Acquisition Interface element populated
+    #[deku(skip, cond = "*fx2 != Some(FX::ExtensionIntoFirstExtent)")]
+    pub pai_ep: Option<EP>,
+    /// Passive Acquisition Interface information available
+    #[deku(skip, cond = "*fx2 != Some(FX::ExtensionIntoFirstExtent)")]
+    pub pai_val: Option<VAL>,
+    #[deku(skip, cond = "*fx2 != Some(FX::ExtensionIntoFirstExtent)", bits = "1")]
+    pub spare: Option<u8>,
+    #[deku(skip, cond = "*fx2 != Some(FX::ExtensionIntoFirstExtent)")]
+    pub fx3: Option<FX>,
+    /// Raw octets of any further extents, up to and including the one without FX
+    #[deku(
+        skip,
+        cond = "*fx3 != Some(FX::ExtensionIntoFirstExtent)",
+        until = "|b: &u8| *b & 0b0000_0001 == 0"
+    )]
+    pub extents: Vec<u8>,
 }
 
 impl TargetReportDescriptor {
     pub const FRN_48: u8 = 0b10_0000;
+
+    /// Every extent announced by an FX bit is complete, and nothing follows the last one
+    fn check_extents(&self) -> Result<(), DekuError> {
+        let first = [
+            self.tst.is_some(),
+            self.err.is_some(),
+            self.xpp.is_some(),
+            self.me.is_some(),
+            self.mi.is_some(),
+            self.foe_fri.is_some(),
+            self.fx2.is_some(),
+        ];
+        check_extent("I048/020 first extent", self.fx1 == FX::ExtensionIntoFirstExtent, &first)?;
+        let second = [
+            self.adsb_ep.is_some(),
+            self.adsb_val.is_some(),
+            self.scn_ep.is_some(),
+            self.scn_val.is_some(),
+            self.pai_ep.is_some(),
+            self.pai_val.is_some(),
+            self.spare.is_some(),
+            self.fx3.is_some(),
+        ];
+        check_extent(
+            "I048/020 second extent",
+            self.fx2 == Some(FX::ExtensionIntoFirstExtent),
+            &second,
+        )?;
+        check_raw_extents("I048/020", self.fx3, &self.extents)
+    }
+}
+
+/// The subfields of an extent are all present if `announced` by the FX bit before it, or all
+/// absent otherwise
+fn check_extent(name: &str, announced: bool, present: &[bool]) -> Result<(), DekuError> {
+    match (announced, present.iter().all(|p| *p == announced)) {
+        (_, true) => Ok(()),
+        (true, false) => Err(DekuError::InvalidParam(format!(
+            "{name}: announced by the FX bit, but not every subfield is set"
+        ))),
+        (false, false) => {
+            Err(DekuError::InvalidParam(format!("{name}: set without the FX bit announcing it")))
+        }
+    }
+}
+
+/// Raw extents are present if announced by `fx`, each with its FX bit set except the last one
+fn check_raw_extents(name: &str, fx: Option<FX>, extents: &[u8]) -> Result<(), DekuError> {
+    let valid = match extents.split_last() {
+        None => fx != Some(FX::ExtensionIntoFirstExtent),
+        Some((last, init)) => {
+            fx == Some(FX::ExtensionIntoFirstExtent)
+                && last & 0b0000_0001 == 0
+                && init.iter().all(|b| b & 0b0000_0001 != 0)
+        }
+    };
+    if valid {
+        Ok(())
+    } else {
+        Err(DekuError::InvalidParam(format!(
+            "{name}: extents {extents:02x?} don't match their FX bits"
+        )))
+    }
 }
 
 /// Measured position of an aircraft in local polar co-ordinates
 ///
 /// Data Item I048/040
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, Clone, DekuRead, DekuWrite)]
 #[deku(ctx = "_: deku::ctx::Endian")]
 pub struct MeasuredPositionInPolarCoordinates {
-    #[deku(
-        reader = "read::bits_to_f32(deku::rest, 16, RHO_MODIFIER, Op::Multiply)",
-        writer = "write::f32_u32(&self.rho, 16, RHO_MODIFIER, Op::Divide, deku::output)"
-    )]
-    pub rho: f32,
-    #[deku(
-        reader = "read::bits_to_f32(deku::rest, 16, THETA_MODIFIER, Op::Multiply)",
-        writer = "write::f32_u32(&self.theta, 16, THETA_MODIFIER, Op::Divide, deku::output)"
-    )]
-    pub theta: f32,
+    /// Slant range, LSB of 1/256 NM
+    #[deku(endian = "big")]
+    pub rho: u16,
+    /// Azimuth, LSB of 360/2^16 degrees
+    #[deku(endian = "big")]
+    pub theta: u16,
 }
 
 impl MeasuredPositionInPolarCoordinates {
     pub const FRN_48: u8 = 0b1_0000;
+
+    /// Slant range in NM
+    pub fn rho_nm(&self) -> f64 {
+        f64::from(self.rho) * RHO_MODIFIER
+    }
+
+    /// Azimuth in degrees
+    pub fn theta_deg(&self) -> f64 {
+        f64::from(self.theta) * THETA_MODIFIER
+    }
+
+    pub fn set_rho_nm(&mut self, rho: f64) -> Result<(), DekuError> {
+        self.rho = modifier::unsigned(rho, RHO_MODIFIER, 16, "I048/040 rho")? as u16;
+        Ok(())
+    }
+
+    pub fn set_theta_deg(&mut self, theta: f64) -> Result<(), DekuError> {
+        self.theta = modifier::angle(theta, 16, "I048/040 theta")? as u16;
+        Ok(())
+    }
+
+    /// Set azimuth, wrapping it into 0..360 degrees first, NaN and infinity are rejected
+    pub fn set_theta_deg_wrapped(&mut self, theta: f64) -> Result<(), DekuError> {
+        self.set_theta_deg(modifier::wrap_deg(theta))
+    }
 }
 
 /// Mode-3/A code converted into octal representation
 ///
 /// Data Item I048/070
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, Clone, DekuRead, DekuWrite)]
 #[deku(ctx = "_: deku::ctx::Endian")]
 pub struct Mode3ACodeInOctalRepresentation {
     pub v: V,
@@ -118,8 +266,7 @@ pub struct Mode3ACodeInOctalRepresentation {
     #[deku(bits = "1")]
     pub reserved: u8,
     /// Mode-3/A reply in octal representation
-    #[deku(bits = "12", endian = "big")]
-    pub reply: u16,
+    pub reply: Squawk,
 }
 
 impl Mode3ACodeInOctalRepresentation {
@@ -129,30 +276,32 @@ impl Mode3ACodeInOctalRepresentation {
 /// Flight Level converted into binary representation
 ///
 /// Data Item I048/090
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, Clone, DekuRead, DekuWrite)]
 #[deku(ctx = "_: deku::ctx::Endian")]
 pub struct FlightLevelInBinaryRepresentation {
     pub v: V,
     pub g: G,
+    /// Two's complement, LSB of 1/4 FL
     #[deku(
-        reader = "Self::read(deku::rest)",
-        writer = "Self::write(&self.flight_level, deku::output)"
+        writer = "write::signed(&self.flight_level, 14, \"I048/090 flight level\", deku::output)"
     )]
-    pub flight_level: u16,
+    #[deku(bits = "14", endian = "big")]
+    pub flight_level: i16,
 }
 
 impl FlightLevelInBinaryRepresentation {
     pub const FRN_48: u8 = 0b100;
-    const CTX: (deku::ctx::Endian, deku::ctx::BitSize) =
-        (deku::ctx::Endian::Big, deku::ctx::BitSize(14_usize));
+    const MODIFIER: f64 = 1.0 / 4.0;
 
-    fn read(rest: &BitSlice<u8, Msb0>) -> Result<(&BitSlice<u8, Msb0>, u16), DekuError> {
-        u16::read(rest, Self::CTX).map(|(rest, value)| (rest, value / 4))
+    /// Flight level, in units of 100 ft, negative below the 1013.25 hPa pressure level
+    pub fn fl(&self) -> f64 {
+        f64::from(self.flight_level) * Self::MODIFIER
     }
 
-    fn write(flight_level: &u16, output: &mut BitVec<u8, Msb0>) -> Result<(), DekuError> {
-        let value = *flight_level * 4;
-        value.write(output, Self::CTX)
+    pub fn set_fl(&mut self, fl: f64) -> Result<(), DekuError> {
+        self.flight_level =
+            modifier::signed(fl, Self::MODIFIER, 14, "I048/090 flight level")? as i16;
+        Ok(())
     }
 }
 
@@ -160,7 +309,7 @@ impl FlightLevelInBinaryRepresentation {
 /// each aircraft
 ///
 /// Data Item I048/220
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, Clone, DekuRead, DekuWrite)]
 #[deku(ctx = "_: deku::ctx::Endian")]
 pub struct AircraftAddress {
     #[deku(bytes = "3", endian = "big")]
@@ -175,132 +324,53 @@ impl AircraftAddress {
 /// equipped with a Mode S transponder
 ///
 /// Data Item I048/240
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, Clone, DekuRead, DekuWrite)]
 #[deku(ctx = "_: deku::ctx::Endian")]
 pub struct AircraftIdentification {
-    /// IA5 char array
-    #[deku(
-        reader = "Self::read(deku::rest)",
-        writer = "Self::write(&self.identification, deku::output)"
-    )]
-    pub identification: String,
+    pub identification: Callsign,
 }
 
 impl AircraftIdentification {
     pub const FRN_48: u8 = 0b100_0000;
-    /// Read and convert to String
-    fn read(rest: &BitSlice<u8, Msb0>) -> Result<(&BitSlice<u8, Msb0>, String), DekuError> {
-        let (rest, one) = u8::read(rest, (deku::ctx::Endian::Big, deku::ctx::BitSize(6_usize)))?;
-        let (rest, two) = u8::read(rest, (deku::ctx::Endian::Big, deku::ctx::BitSize(6_usize)))?;
-        let (rest, three) = u8::read(rest, (deku::ctx::Endian::Big, deku::ctx::BitSize(6_usize)))?;
-        let (rest, four) = u8::read(rest, (deku::ctx::Endian::Big, deku::ctx::BitSize(6_usize)))?;
-        let (rest, five) = u8::read(rest, (deku::ctx::Endian::Big, deku::ctx::BitSize(6_usize)))?;
-        let (rest, six) = u8::read(rest, (deku::ctx::Endian::Big, deku::ctx::BitSize(6_usize)))?;
-        let (rest, seven) = u8::read(rest, (deku::ctx::Endian::Big, deku::ctx::BitSize(6_usize)))?;
-        let (rest, _) = u8::read(rest, (deku::ctx::Endian::Big, deku::ctx::BitSize(6_usize)))?;
-        let value = format!(
-            "{}{}{}{}{}{}{}",
-            Self::asterix_char_to_ascii(one) as char,
-            Self::asterix_char_to_ascii(two) as char,
-            Self::asterix_char_to_ascii(three) as char,
-            Self::asterix_char_to_ascii(four) as char,
-            Self::asterix_char_to_ascii(five) as char,
-            Self::asterix_char_to_ascii(six) as char,
-            Self::asterix_char_to_ascii(seven) as char
-        );
-        Ok((rest, value))
-    }
-
-    /// Parse from String to u8 and write
-    fn write(field_a: &str, output: &mut BitVec<u8, Msb0>) -> Result<(), DekuError> {
-        for c in field_a.chars() {
-            Self::asterix_ascii_to_ia5_char(c as u8)
-                .write(output, (deku::ctx::Endian::Big, deku::ctx::BitSize(6_usize)))?;
-        }
-        0_u8.write(output, (deku::ctx::Endian::Big, deku::ctx::BitSize(6_usize)))
-    }
-
-    const IA5_ALPHA: u8 = 0x01;
-    const IA5_SPACE: u8 = 0x20;
-    const IA5_DIGIT: u8 = 0x30;
-    const ASC_DIGIT: u8 = b'0';
-    const ASC_ALPHA: u8 = b'A';
-    const ASC_SPACE: u8 = b' ';
-    const ASC_ERROR: u8 = b'?';
-
-    /// parse into ascii from IA5 char array
-    const fn asterix_char_to_ascii(code: u8) -> u8 {
-        // space
-        if code == Self::IA5_SPACE {
-            Self::ASC_SPACE
-        }
-        // digit
-        else if Self::IA5_DIGIT <= code && code < Self::IA5_DIGIT + 10 {
-            Self::ASC_DIGIT + (code - Self::IA5_DIGIT)
-        }
-        // letter
-        else if Self::IA5_ALPHA <= code && code < Self::IA5_ALPHA + 26 {
-            Self::ASC_ALPHA + (code - Self::IA5_ALPHA)
-        } else {
-            Self::ASC_ERROR
-        }
-    }
-
-    /// parse from IA5 char as u8 to u8 value
-    const fn asterix_ascii_to_ia5_char(code: u8) -> u8 {
-        // space
-        if code == Self::ASC_SPACE {
-            Self::IA5_SPACE
-        }
-        // digit
-        else if Self::ASC_DIGIT <= code && code < Self::ASC_DIGIT + 10 {
-            Self::IA5_DIGIT + (code - Self::ASC_DIGIT)
-        }
-        // letter
-        else if Self::ASC_ALPHA <= code && code < Self::ASC_ALPHA + 26 {
-            Self::IA5_ALPHA + (code - Self::ASC_ALPHA)
-        } else {
-            Self::ASC_ERROR
-        }
-    }
 }
 
 /// Mode S Comm B data as extracted from the aircraft
 /// transponder
 ///
 /// Data Item I048/250, Mode S MB Data
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, Clone, DekuRead, DekuWrite)]
 #[deku(ctx = "_: deku::ctx::Endian")]
 pub struct ModeSMBData {
     #[deku(update = "self.mb_data.len()")]
     pub count: u8,
     #[deku(count = "count")]
     pub mb_data: Vec<MBData>,
-    #[deku(bits = "4")]
-    pub bds1: u8,
-    #[deku(bits = "4")]
-    pub bds2: u8,
 }
 
 impl ModeSMBData {
     pub const FRN_48: u8 = 0b10_0000;
 }
 
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, Clone, DekuRead, DekuWrite)]
 pub struct MBData {
     #[deku(count = "7")]
     pub data: Vec<u8>,
+    #[deku(bits = "4")]
+    pub bds1: u8,
+    #[deku(bits = "4")]
+    pub bds2: u8,
 }
 
 /// An integer value representing a unique reference to a track
 /// record within a particular track file
 ///
 /// Data Item I048/161
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, Clone, DekuRead, DekuWrite)]
 #[deku(ctx = "_: deku::ctx::Endian")]
 pub struct TrackNumber {
     #[deku(bits = "4")]
     pub reserved: u8,
+    #[deku(writer = "write::unsigned(&self.number, 12, \"I048/161 track number\", deku::output)")]
     #[deku(bits = "12", endian = "big")]
     pub number: u16,
 }
@@ -312,52 +382,100 @@ impl TrackNumber {
 /// Calculated position of an aircraft in Cartesian co-ordinates
 ///
 /// Data Item I048/042
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, Clone, DekuRead, DekuWrite)]
 #[deku(ctx = "_: deku::ctx::Endian")]
 pub struct CalculatedPositionCartesianCorr {
-    #[deku(
-        reader = "read::bits_i16_to_f32(deku::rest, 16, Self::MODIFIER, Op::Multiply)",
-        writer = "write::f32_i32(&self.x, 16, Self::MODIFIER, Op::Divide, deku::output)"
-    )]
-    pub x: f32,
-    #[deku(
-        reader = "read::bits_i16_to_f32(deku::rest, 16, Self::MODIFIER, Op::Multiply)",
-        writer = "write::f32_i32(&self.y, 16, Self::MODIFIER, Op::Divide, deku::output)"
-    )]
-    pub y: f32,
+    /// LSB of 1/128 NM
+    #[deku(endian = "big")]
+    pub x: i16,
+    /// LSB of 1/128 NM
+    #[deku(endian = "big")]
+    pub y: i16,
 }
 
 impl CalculatedPositionCartesianCorr {
     pub const FRN_48: u8 = 0b1000;
-    const MODIFIER: f32 = 1.0 / 128.0;
+    const MODIFIER: f64 = 1.0 / 128.0;
+
+    /// X component in NM
+    pub fn x_nm(&self) -> f64 {
+        f64::from(self.x) * Self::MODIFIER
+    }
+
+    /// Y component in NM
+    pub fn y_nm(&self) -> f64 {
+        f64::from(self.y) * Self::MODIFIER
+    }
+
+    pub fn set_x_nm(&mut self, x: f64) -> Result<(), DekuError> {
+        self.x = modifier::signed(x, Self::MODIFIER, 16, "I048/042 x")? as i16;
+        Ok(())
+    }
+
+    pub fn set_y_nm(&mut self, y: f64) -> Result<(), DekuError> {
+        self.y = modifier::signed(y, Self::MODIFIER, 16, "I048/042 y")? as i16;
+        Ok(())
+    }
 }
 
 /// Calculated track velocity expressed in polar co-ordinates
 ///
 /// Data Item I048/200
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, Clone, DekuRead, DekuWrite)]
 #[deku(ctx = "_: deku::ctx::Endian")]
 pub struct CalculatedTrackVelocity {
-    #[deku(
-        reader = "read::bits_to_f32(deku::rest, 16, modifier::groundspeed(), Op::Multiply)",
-        writer = "write::f32_u32(&self.groundspeed, 16, modifier::groundspeed(), Op::Divide, deku::output)"
-    )]
-    pub groundspeed: f32,
-    #[deku(
-        reader = "read::bits_to_f32(deku::rest, 16, modifier::heading1(), Op::Multiply)",
-        writer = "write::f32_u32(&self.heading, 16, modifier::heading1(), Op::Divide, deku::output)"
-    )]
-    pub heading: f32,
+    /// LSB of 2^-14 NM/s
+    #[deku(endian = "big")]
+    pub groundspeed: u16,
+    /// LSB of 360/2^16 degrees
+    #[deku(endian = "big")]
+    pub heading: u16,
 }
 
 impl CalculatedTrackVelocity {
     pub const FRN_48: u8 = 0b100;
+
+    /// Groundspeed in NM/s
+    pub fn groundspeed_nm_s(&self) -> f64 {
+        f64::from(self.groundspeed) * modifier::GROUNDSPEED
+    }
+
+    /// Groundspeed in knots
+    pub fn groundspeed_kt(&self) -> f64 {
+        self.groundspeed_nm_s() * modifier::SECONDS_PER_HOUR
+    }
+
+    /// Heading in degrees
+    pub fn heading_deg(&self) -> f64 {
+        f64::from(self.heading) * modifier::HEADING_16
+    }
+
+    pub fn set_groundspeed_nm_s(&mut self, groundspeed: f64) -> Result<(), DekuError> {
+        self.groundspeed =
+            modifier::unsigned(groundspeed, modifier::GROUNDSPEED, 16, "I048/200 groundspeed")?
+                as u16;
+        Ok(())
+    }
+
+    pub fn set_groundspeed_kt(&mut self, groundspeed: f64) -> Result<(), DekuError> {
+        self.set_groundspeed_nm_s(groundspeed / modifier::SECONDS_PER_HOUR)
+    }
+
+    pub fn set_heading_deg(&mut self, heading: f64) -> Result<(), DekuError> {
+        self.heading = modifier::angle(heading, 16, "I048/200 heading")? as u16;
+        Ok(())
+    }
+
+    /// Set heading, wrapping it into 0..360 degrees first, NaN and infinity are rejected
+    pub fn set_heading_deg_wrapped(&mut self, heading: f64) -> Result<(), DekuError> {
+        self.set_heading_deg(modifier::wrap_deg(heading))
+    }
 }
 
 /// Status of monoradar track (PSR and/or SSR updated)
 ///
 /// Data Item I048/170
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, Clone, DekuRead, DekuWrite)]
 #[deku(ctx = "_: deku::ctx::Endian")]
 pub struct TrackStatus {
     pub cnf: CNF,
@@ -365,6 +483,8 @@ pub struct TrackStatus {
     pub dou: DOU,
     pub mah: MAH,
     pub cdm: CDM,
+    /// Writing checks that the FX bits match the extents that are present
+    #[deku(writer = "self.check_extents().and_then(|_| fx1.write(deku::output, ()))")]
     pub fx1: FX,
     #[deku(skip, cond = "*fx1 != FX::ExtensionIntoFirstExtent")]
     pub tre: Option<TRE>,
@@ -378,50 +498,120 @@ pub struct TrackStatus {
     pub reserved: Option<u8>,
     #[deku(skip, cond = "*fx1 != FX::ExtensionIntoFirstExtent")]
     pub fx2: Option<FX>,
+    /// Second extent, no subfield of it is defined up to CAT048 edition 1.31
+    #[deku(skip, cond = "*fx2 != Some(FX::ExtensionIntoFirstExtent)", bits = "7")]
+    pub spare: Option<u8>,
+    #[deku(skip, cond = "*fx2 != Some(FX::ExtensionIntoFirstExtent)")]
+    pub fx3: Option<FX>,
+    /// Raw octets of any further extents, up to and including the one without FX
+    #[deku(
+        skip,
+        cond = "*fx3 != Some(FX::ExtensionIntoFirstExtent)",
+        until = "|b: &u8| *b & 0b0000_0001 == 0"
+    )]
+    pub extents: Vec<u8>,
 }
 
 impl TrackStatus {
     pub const FRN_48: u8 = 0b10;
+
+    /// Every extent announced by an FX bit is complete, and nothing follows the last one
+    fn check_extents(&self) -> Result<(), DekuError> {
+        let first = [
+            self.tre.is_some(),
+            self.gho.is_some(),
+            self.sup.is_some(),
+            self.tcc.is_some(),
+            self.reserved.is_some(),
+            self.fx2.is_some(),
+        ];
+        check_extent("I048/170 first extent", self.fx1 == FX::ExtensionIntoFirstExtent, &first)?;
+        let second = [self.spare.is_some(), self.fx3.is_some()];
+        check_extent(
+            "I048/170 second extent",
+            self.fx2 == Some(FX::ExtensionIntoFirstExtent),
+            &second,
+        )?;
+        check_raw_extents("I048/170", self.fx3, &self.extents)
+    }
 }
 
 /// Track quality in the form of a vector of standard deviations
 ///
 /// Data Item I048/210
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, Clone, DekuRead, DekuWrite)]
 #[deku(ctx = "_: deku::ctx::Endian")]
 pub struct TrackQuality {
-    #[deku(
-        reader = "read::bits_to_f32(deku::rest, 8, Self::MODIFIER, Op::Divide)",
-        writer = "write::f32_u32(&self.horizontal_stddev, 8, Self::MODIFIER, Op::Multiply, deku::output)"
-    )]
-    pub horizontal_stddev: f32,
-    #[deku(
-        reader = "read::bits_to_f32(deku::rest, 8, Self::MODIFIER, Op::Divide)",
-        writer = "write::f32_u32(&self.vertical_stddev, 8, Self::MODIFIER, Op::Multiply, deku::output)"
-    )]
-    pub vertical_stddev: f32,
-    #[deku(
-        reader = "read::bits_to_f32(deku::rest, 8, modifier::groundspeed(), Op::Multiply)",
-        writer = "write::f32_u32(&self.groundspeed_stddev, 8, modifier::groundspeed(), Op::Divide, deku::output)"
-    )]
-    pub groundspeed_stddev: f32,
-    #[deku(
-        reader = "read::bits_to_f32(deku::rest, 8, modifier::heading2(), Op::Multiply)",
-        writer = "write::f32_u32(&self.heading_stddev, 8, modifier::heading2(), Op::Divide, deku::output)"
-    )]
-    pub heading_stddev: f32,
+    /// Standard deviation on the horizontal axis of the local grid system, LSB of 1/128 NM
+    pub horizontal_stddev: u8,
+    /// Standard deviation on the vertical axis of the local grid system, LSB of 1/128 NM
+    pub vertical_stddev: u8,
+    /// LSB of 2^-14 NM/s
+    pub groundspeed_stddev: u8,
+    /// LSB of 360/2^12 degrees
+    pub heading_stddev: u8,
 }
 
 impl TrackQuality {
     pub const FRN_48: u8 = 0b1000_0000;
-    const MODIFIER: f32 = 1.0 / 128.0;
+    const MODIFIER: f64 = 1.0 / 128.0;
+
+    /// Horizontal standard deviation in NM
+    pub fn horizontal_stddev_nm(&self) -> f64 {
+        f64::from(self.horizontal_stddev) * Self::MODIFIER
+    }
+
+    /// Vertical standard deviation in NM
+    pub fn vertical_stddev_nm(&self) -> f64 {
+        f64::from(self.vertical_stddev) * Self::MODIFIER
+    }
+
+    /// Groundspeed standard deviation in NM/s
+    pub fn groundspeed_stddev_nm_s(&self) -> f64 {
+        f64::from(self.groundspeed_stddev) * modifier::GROUNDSPEED
+    }
+
+    /// Groundspeed standard deviation in knots
+    pub fn groundspeed_stddev_kt(&self) -> f64 {
+        self.groundspeed_stddev_nm_s() * modifier::SECONDS_PER_HOUR
+    }
+
+    /// Heading standard deviation in degrees
+    pub fn heading_stddev_deg(&self) -> f64 {
+        f64::from(self.heading_stddev) * modifier::HEADING_12
+    }
+
+    pub fn set_horizontal_stddev_nm(&mut self, stddev: f64) -> Result<(), DekuError> {
+        self.horizontal_stddev =
+            modifier::unsigned(stddev, Self::MODIFIER, 8, "I048/210 horizontal_stddev")? as u8;
+        Ok(())
+    }
+
+    pub fn set_vertical_stddev_nm(&mut self, stddev: f64) -> Result<(), DekuError> {
+        self.vertical_stddev =
+            modifier::unsigned(stddev, Self::MODIFIER, 8, "I048/210 vertical_stddev")? as u8;
+        Ok(())
+    }
+
+    pub fn set_groundspeed_stddev_nm_s(&mut self, stddev: f64) -> Result<(), DekuError> {
+        self.groundspeed_stddev =
+            modifier::unsigned(stddev, modifier::GROUNDSPEED, 8, "I048/210 groundspeed_stddev")?
+                as u8;
+        Ok(())
+    }
+
+    pub fn set_heading_stddev_deg(&mut self, stddev: f64) -> Result<(), DekuError> {
+        self.heading_stddev =
+            modifier::unsigned(stddev, modifier::HEADING_12, 8, "I048/210 heading_stddev")? as u8;
+        Ok(())
+    }
 }
 
 /// Communications capability of the transponder, capability of the onboard ACAS equipment and
 /// flight status
 ///
 /// Data Item I048/230
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, Clone, DekuRead, DekuWrite)]
 #[deku(ctx = "_: deku::ctx::Endian")]
 pub struct CommunicationsCapabilityFlightStatus {
     pub com: COM,
@@ -445,60 +635,76 @@ impl CommunicationsCapabilityFlightStatus {
 /// Additional information on the quality of the target report
 ///
 /// Data Item I048/130
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, Clone, DekuRead, DekuWrite)]
 #[deku(ctx = "_: deku::ctx::Endian")]
 pub struct RadarPlotCharacteristics {
     #[deku(until = "|b: &u8| *b & 0b0000_0001 == 0")]
     pub fspec: Vec<u8>,
-    #[deku(
-        skip,
-        cond = "is_fspec(0b1000_0000, fspec, 0)",
-        reader = "read::bits_to_optionf32(deku::rest, 8, Self::runlength_modifier(), Op::Multiply)",
-        writer = "write::f32_optionu32(&self.srl, 8, Self::runlength_modifier(), Op::Divide, deku::output)"
-    )]
-    pub srl: Option<f32>,
+    /// SSR plot runlength, LSB of 360/2^13 degrees
+    #[deku(skip, cond = "is_fspec(0b1000_0000, fspec, 0)")]
+    pub srl: Option<u8>,
     #[deku(skip, cond = "is_fspec(0b100_0000, fspec, 0)")]
     pub srr: Option<u8>,
     #[deku(skip, cond = "is_fspec(0b10_0000, fspec, 0)")]
     pub sam: Option<i8>,
-    #[deku(
-        skip,
-        cond = "is_fspec(0b1_0000, fspec, 0)",
-        reader = "read::bits_to_optionf32(deku::rest, 8, Self::runlength_modifier(), Op::Multiply)",
-        writer = "write::f32_optionu32(&self.prl, 8, Self::runlength_modifier(), Op::Divide, deku::output)"
-    )]
-    pub prl: Option<f32>,
+    /// PSR plot runlength, LSB of 360/2^13 degrees
+    #[deku(skip, cond = "is_fspec(0b1_0000, fspec, 0)")]
+    pub prl: Option<u8>,
     #[deku(skip, cond = "is_fspec(0b1000, fspec, 0)")]
     pub pam: Option<u8>,
-    #[deku(
-        skip,
-        cond = "is_fspec(0b100, fspec, 0)",
-        reader = "read::bits_to_optionf32(deku::rest, 8, Self::nm_modifier(), Op::Multiply)",
-        writer = "write::f32_optionu32(&self.rpd, 8, Self::nm_modifier(), Op::Divide, deku::output)"
-    )]
-    pub rpd: Option<f32>,
-    #[deku(
-        skip,
-        cond = "is_fspec(0b100, fspec, 0)",
-        reader = "read::bits_to_optionf32(deku::rest, 8, Self::apd_modifier(), Op::Multiply)",
-        writer = "write::f32_optionu32(&self.apd, 8, Self::apd_modifier(), Op::Divide, deku::output)"
-    )]
-    pub apd: Option<f32>,
+    /// Difference in range between PSR and SSR plot, two's complement, LSB of 1/256 NM
+    #[deku(skip, cond = "is_fspec(0b100, fspec, 0)")]
+    pub rpd: Option<i8>,
+    /// Difference in azimuth between PSR and SSR plot, two's complement, LSB of 360/2^14 degrees
+    #[deku(skip, cond = "is_fspec(0b100, fspec, 0)")]
+    pub apd: Option<i8>,
 }
 
 impl RadarPlotCharacteristics {
     pub const FRN_48: u8 = 0b10;
+    const RUNLENGTH_MODIFIER: f64 = 360.0 / 8192.0;
+    const APD_MODIFIER: f64 = 360.0 / 16384.0;
+
+    /// SSR plot runlength in degrees
+    pub fn srl_deg(&self) -> Option<f64> {
+        self.srl.map(|srl| f64::from(srl) * Self::RUNLENGTH_MODIFIER)
+    }
+
+    /// PSR plot runlength in degrees
+    pub fn prl_deg(&self) -> Option<f64> {
+        self.prl.map(|prl| f64::from(prl) * Self::RUNLENGTH_MODIFIER)
+    }
+
+    /// Range difference between PSR and SSR plot in NM
+    pub fn rpd_nm(&self) -> Option<f64> {
+        self.rpd.map(|rpd| f64::from(rpd) * RHO_MODIFIER)
+    }
 
-    fn runlength_modifier() -> f32 {
-        360.0 / f32::from(2_u16.pow(13))
+    /// Azimuth difference between PSR and SSR plot in degrees
+    pub fn apd_deg(&self) -> Option<f64> {
+        self.apd.map(|apd| f64::from(apd) * Self::APD_MODIFIER)
     }
 
-    fn nm_modifier() -> f32 {
-        1.0 / 256.0
+    pub fn set_srl_deg(&mut self, srl: f64) -> Result<(), DekuError> {
+        self.srl =
+            Some(modifier::unsigned(srl, Self::RUNLENGTH_MODIFIER, 8, "I048/130 SRL")? as u8);
+        Ok(())
     }
 
-    fn apd_modifier() -> f32 {
-        360.0 / f32::from(2_u16.pow(14))
+    pub fn set_prl_deg(&mut self, prl: f64) -> Result<(), DekuError> {
+        self.prl =
+            Some(modifier::unsigned(prl, Self::RUNLENGTH_MODIFIER, 8, "I048/130 PRL")? as u8);
+        Ok(())
+    }
+
+    pub fn set_rpd_nm(&mut self, rpd: f64) -> Result<(), DekuError> {
+        self.rpd = Some(modifier::signed(rpd, RHO_MODIFIER, 8, "I048/130 RPD")? as i8);
+        Ok(())
+    }
+
+    pub fn set_apd_deg(&mut self, apd: f64) -> Result<(), DekuError> {
+        self.apd = Some(modifier::signed(apd, Self::APD_MODIFIER, 8, "I048/130 APD")? as i8);
+        Ok(())
     }
 }
 
@@ -507,7 +713,7 @@ impl RadarPlotCharacteristics {
 /// transaction
 ///
 /// Data Item I034/000
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, Clone, DekuRead, DekuWrite)]
 #[deku(ctx = "_: deku::ctx::Endian")]
 pub struct MessageType {
     pub t: MTYPE,
@@ -521,31 +727,31 @@ impl MessageType {
 /// particular azimuth sector
 ///
 /// Data Item I034/020
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, Clone, DekuRead, DekuWrite)]
 #[deku(ctx = "_: deku::ctx::Endian")]
 pub struct SectorNumber {
-    #[deku(reader = "Self::read(deku::rest)", writer = "Self::write(&self.num, deku::output)")]
-    pub num: u16,
+    /// LSB of 360/2^8 degrees
+    pub num: u8,
 }
 
 impl SectorNumber {
     pub const FRN_34: u8 = 0b1_0000;
     pub const FRN_48: u8 = 0b1_0000;
-    const CTX: (deku::ctx::Endian, deku::ctx::BitSize) =
-        (deku::ctx::Endian::Big, deku::ctx::BitSize(8_usize));
+    const MODIFIER: f64 = 360.0 / 256.0;
 
-    fn modifier() -> f32 {
-        360.0 / 2_f32.powi(8)
+    /// Create from an azimuth in degrees, rounded to the nearest sector
+    pub fn from_deg(azimuth: f64) -> Result<Self, DekuError> {
+        Ok(Self { num: modifier::angle(azimuth, 8, "I034/020 sector number")? as u8 })
     }
 
-    fn read(rest: &BitSlice<u8, Msb0>) -> Result<(&BitSlice<u8, Msb0>, u16), DekuError> {
-        u16::read(rest, Self::CTX)
-            .map(|(rest, value)| (rest, (f32::from(value) * Self::modifier()) as u16))
+    /// Create from an azimuth in degrees, wrapping it into 0..360 degrees first
+    pub fn from_deg_wrapped(azimuth: f64) -> Result<Self, DekuError> {
+        Self::from_deg(modifier::wrap_deg(azimuth))
     }
 
-    fn write(num: &u16, output: &mut BitVec<u8, Msb0>) -> Result<(), DekuError> {
-        let value = (f32::from(*num) / Self::modifier()) as u8;
-        value.write(output, Self::CTX)
+    /// Sector azimuth in degrees
+    pub fn deg(&self) -> f64 {
+        f64::from(self.num) * Self::MODIFIER
     }
 }
 
@@ -554,7 +760,7 @@ impl SectorNumber {
 /// involved
 ///
 /// Data Item I048/030
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, Clone, DekuRead, DekuWrite)]
 #[deku(ctx = "_: deku::ctx::Endian")]
 pub struct WarningErrorConditionsTargetClass {
     #[deku(until = "|codefx: &CodeFx| codefx.fx == FX::EndOfDataItem")]
@@ -575,7 +781,7 @@ pub struct CodeFx {
 /// by a monopulse SSR station
 ///
 /// Data Item I048/080
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, Clone, DekuRead, DekuWrite)]
 #[deku(ctx = "_: deku::ctx::Endian")]
 pub struct Mode3ACodeConfidenceIndicator {
     #[deku(bits = "4", endian = "big")]
@@ -593,7 +799,7 @@ impl Mode3ACodeConfidenceIndicator {
 /// as provided by a MSSR/Mode S station
 ///
 /// Data Item I048/100
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, Clone, DekuRead, DekuWrite)]
 #[deku(ctx = "_: deku::ctx::Endian")]
 pub struct ModeCCodeAndConfidenceIndicator {
     pub v: V,
@@ -616,35 +822,36 @@ impl ModeCCodeAndConfidenceIndicator {
 /// use mean sea level as the zero reference level
 ///
 /// Data Item I048/110
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, Clone, DekuRead, DekuWrite)]
 #[deku(ctx = "_: deku::ctx::Endian")]
 pub struct HeightMeasuredBy3dRadar {
     #[deku(bits = "2", endian = "big")]
     pub reserved: u8,
-    #[deku(reader = "Self::read(deku::rest)", writer = "Self::write(&self.height, deku::output)")]
-    pub height: i32,
+    /// Two's complement, LSB of 25 ft
+    #[deku(writer = "write::signed(&self.height, 14, \"I048/110 height\", deku::output)")]
+    #[deku(bits = "14", endian = "big")]
+    pub height: i16,
 }
 
 impl HeightMeasuredBy3dRadar {
     pub const FRN_48: u8 = 0b1000;
-    const CTX: (deku::ctx::Endian, deku::ctx::BitSize) =
-        (deku::ctx::Endian::Big, deku::ctx::BitSize(14_usize));
-    pub const MODIFIER: i32 = 25;
+    pub const MODIFIER: f64 = 25.0;
 
-    fn read(rest: &BitSlice<u8, Msb0>) -> Result<(&BitSlice<u8, Msb0>, i32), DekuError> {
-        i32::read(rest, Self::CTX).map(|(rest, value)| (rest, value * Self::MODIFIER))
+    /// Height above mean sea level in ft
+    pub fn height_ft(&self) -> f64 {
+        f64::from(self.height) * Self::MODIFIER
     }
 
-    fn write(height: &i32, output: &mut BitVec<u8, Msb0>) -> Result<(), DekuError> {
-        let value = height / Self::MODIFIER;
-        value.write(output, Self::CTX)
+    pub fn set_height_ft(&mut self, height: f64) -> Result<(), DekuError> {
+        self.height = modifier::signed(height, Self::MODIFIER, 14, "I048/110 height")? as i16;
+        Ok(())
     }
 }
 
 /// Information on the Doppler Speed of the target report
 ///
 /// Data Item I048/120
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, Clone, DekuRead, DekuWrite)]
 #[deku(ctx = "_: deku::ctx::Endian")]
 pub struct RadialDopplerSpeed {
     #[deku(bits = "1", endian = "big")]
@@ -664,18 +871,20 @@ impl RadialDopplerSpeed {
 }
 
 /// Subfield of `HeightMeasuredBy3dRadar`
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, Clone, DekuRead, DekuWrite)]
 #[deku(ctx = "_: deku::ctx::Endian")]
 pub struct CalculatedDopplerSpeed {
     pub d: D,
     #[deku(bits = "5", endian = "big")]
     pub spare: u8,
+    /// Two's complement, LSB of 1 m/s
+    #[deku(writer = "write::signed(&self.cal, 10, \"I048/120 CAL\", deku::output)")]
     #[deku(bits = "10", endian = "big")]
-    pub cal: u16,
+    pub cal: i16,
 }
 
 /// Subfield of `HeightMeasuredBy3dRadar`
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, Clone, DekuRead, DekuWrite)]
 #[deku(ctx = "_: deku::ctx::Endian")]
 pub struct RawDopplerSpeed {
     /// Repetition Factor
@@ -697,7 +906,7 @@ pub struct RawDopplerSpeed {
 /// threat identity data
 ///
 /// Data Item I048/260
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, Clone, DekuRead, DekuWrite)]
 #[deku(ctx = "_: deku::ctx::Endian")]
 pub struct ACASResolutionAdvisoryReport {
     pub mb_data: [u8; 7],
@@ -705,9 +914,43 @@ pub struct ACASResolutionAdvisoryReport {
 
 impl ACASResolutionAdvisoryReport {
     pub const FRN_48: u8 = 0b1000_0000;
+
+    /// Bits `first..=last` of the 56 bit MB field, numbered from 1 as in BDS 3,0
+    fn bits(&self, first: u32, last: u32) -> u64 {
+        let mb = self.mb_data.iter().fold(0_u64, |mb, byte| mb << 8 | u64::from(*byte));
+        (mb >> (56 - last)) & ((1 << (last - first + 1)) - 1)
+    }
+
+    /// Active Resolution Advisories
+    pub fn ara(&self) -> u16 {
+        self.bits(9, 22) as u16
+    }
+
+    /// Resolution Advisory Complements
+    pub fn rac(&self) -> u8 {
+        self.bits(23, 26) as u8
+    }
+
+    /// Resolution Advisory Terminated
+    pub fn rat(&self) -> bool {
+        self.bits(27, 27) == 1
+    }
+
+    /// Multiple Threat Encounter
+    pub fn mte(&self) -> bool {
+        self.bits(28, 28) == 1
+    }
+
+    /// A Resolution Advisory is in force: ARA or MTE is set, and it isn't terminated
+    pub fn is_active(&self) -> bool {
+        (self.ara() != 0 || self.mte()) && !self.rat()
+    }
 }
 
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+/// Reply to Mode-1 interrogation
+///
+/// Data Item I048/055
+#[derive(Debug, PartialEq, Clone, DekuRead, DekuWrite)]
 #[deku(ctx = "_: deku::ctx::Endian")]
 pub struct Mode1CodeOctalRepresentation {
     pub v: V,
@@ -719,12 +962,17 @@ pub struct Mode1CodeOctalRepresentation {
 
 impl Mode1CodeOctalRepresentation {
     pub const FRN_48: u8 = 0b100_0000;
+
+    /// Two digit Mode-1 code: A (A4 A2 A1) followed by B (B2 B1)
+    pub fn code(&self) -> Mode1Code {
+        Mode1Code::new(self.data & Mode1Code::MAX).unwrap_or_default()
+    }
 }
 
 /// Reply to Mode-2 interrogation
 ///
 /// Data Item I048/050
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, Clone, DekuRead, DekuWrite)]
 #[deku(ctx = "_: deku::ctx::Endian")]
 pub struct Mode2CodeOctalRepresentation {
     pub v: V,
@@ -732,8 +980,8 @@ pub struct Mode2CodeOctalRepresentation {
     pub l: L,
     #[deku(bits = "1", endian = "big")]
     pub spare: u8,
-    #[deku(bits = "12", endian = "big")]
-    pub data: u16,
+    /// Mode-2 reply in octal representation
+    pub data: Squawk,
 }
 
 impl Mode2CodeOctalRepresentation {
@@ -744,7 +992,7 @@ impl Mode2CodeOctalRepresentation {
 /// a monopulse SSR station
 ///
 /// Data Item I048/065
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, Clone, DekuRead, DekuWrite)]
 #[deku(ctx = "_: deku::ctx::Endian")]
 pub struct Mode1CodeConfidenceIndicator {
     #[deku(bits = "3", endian = "big")]
@@ -761,7 +1009,7 @@ impl Mode1CodeConfidenceIndicator {
 /// a monopulse SSR station
 ///
 /// Data Item I048/060
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, Clone, DekuRead, DekuWrite)]
 #[deku(ctx = "_: deku::ctx::Endian")]
 pub struct Mode2CodeConfidenceIndicator {
     #[deku(bits = "4", endian = "big")]
@@ -778,25 +1026,34 @@ impl Mode2CodeConfidenceIndicator {
 /// North crossings or as averaged during a period of time
 ///
 /// Data Item I034/041
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, Clone, DekuRead, DekuWrite)]
 #[deku(ctx = "_: deku::ctx::Endian")]
 pub struct AntennaRotationSpeed {
-    #[deku(
-        reader = "read::bits_to_f32(deku::rest, 16, Self::MODIFIER, Op::Divide)",
-        writer = "write::f32_u32(&self.period, 16, Self::MODIFIER, Op::Multiply, deku::output)"
-    )]
-    pub period: f32,
+    /// LSB of 1/128 s
+    #[deku(endian = "big")]
+    pub period: u16,
 }
 
 impl AntennaRotationSpeed {
     pub const FRN_34: u8 = 0b1000;
-    const MODIFIER: f32 = 128.0;
+    const MODIFIER: f64 = 1.0 / 128.0;
+
+    /// Create from a rotation period in seconds, rounded to the nearest 1/128 s
+    pub fn from_seconds(period: f64) -> Result<Self, DekuError> {
+        let period = modifier::unsigned(period, Self::MODIFIER, 16, "I034/041 rotation period")?;
+        Ok(Self { period: period as u16 })
+    }
+
+    /// Rotation period in seconds
+    pub fn seconds(&self) -> f64 {
+        f64::from(self.period) * Self::MODIFIER
+    }
 }
 
 /// Information concerning the configuration and status of a System
 ///
 /// Data Item I034/050
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, Clone, DekuRead, DekuWrite)]
 #[deku(ctx = "_: deku::ctx::Endian")]
 pub struct SystemConfigurationAndStatus {
     #[deku(bits = "1")]
@@ -826,7 +1083,7 @@ impl SystemConfigurationAndStatus {
     pub const FRN_34: u8 = 0b0100;
 }
 
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, Clone, DekuRead, DekuWrite)]
 pub struct ComSubField {
     pub nogo: NOGO,
     pub rdpc: RDPC,
@@ -839,7 +1096,7 @@ pub struct ComSubField {
     pub spare: u8,
 }
 
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, Clone, DekuRead, DekuWrite)]
 pub struct Sensor {
     pub ant: ANT,
     pub chab: CHAB,
@@ -849,7 +1106,7 @@ pub struct Sensor {
     pub spare: u8,
 }
 
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, Clone, DekuRead, DekuWrite)]
 pub struct MdsSubField {
     pub ant: ANT,
     pub chab: CHAB,
@@ -867,7 +1124,7 @@ pub struct MdsSubField {
 /// for the various Sensors, composing the System
 ///
 /// Data Item I034/060
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, Clone, DekuRead, DekuWrite)]
 #[deku(ctx = "_: deku::ctx::Endian")]
 pub struct SystemProcessingMode {
     #[deku(bits = "1")]
@@ -897,7 +1154,7 @@ impl SystemProcessingMode {
     pub const FRN_34: u8 = 0b0010;
 }
 
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, Clone, DekuRead, DekuWrite)]
 pub struct ComSubField2 {
     #[deku(bits = "1")]
     pub spare0: u8,
@@ -907,7 +1164,7 @@ pub struct ComSubField2 {
     pub spare1: u8,
 }
 
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, Clone, DekuRead, DekuWrite)]
 pub struct PsrSubField {
     pub pol: POL,
     pub red_rad: RED,
@@ -916,14 +1173,14 @@ pub struct PsrSubField {
     pub spare: u8,
 }
 
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, Clone, DekuRead, DekuWrite)]
 pub struct SsrSubField {
     pub red_rad: RED,
     #[deku(bits = "5")]
     pub spare: u8,
 }
 
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, Clone, DekuRead, DekuWrite)]
 pub struct MdsSubField2 {
     pub red_rad: RED,
     pub clu: CLU,
@@ -936,7 +1193,7 @@ pub struct MdsSubField2 {
 /// North crossings
 ///
 /// Data Item I034/070
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, Clone, DekuRead, DekuWrite)]
 #[deku(ctx = "_: deku::ctx::Endian")]
 pub struct MessageCountValues {
     #[deku(update = "self.counters.len()")]
@@ -945,7 +1202,7 @@ pub struct MessageCountValues {
     pub counters: Vec<MessageCounter>,
 }
 
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, Clone, DekuRead, DekuWrite)]
 pub struct MessageCounter {
     pub typ: MessageCounterTYP,
     #[deku(bits = "11")]
@@ -959,39 +1216,77 @@ impl MessageCountValues {
 /// Geographical window defined in polar co-ordinates.
 ///
 /// Data Item I034/100
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, Clone, DekuRead, DekuWrite)]
 #[deku(ctx = "_: deku::ctx::Endian")]
 pub struct GenericPolarWindow {
-    #[deku(
-        reader = "read::bits_to_f32(deku::rest, 16, RHO_MODIFIER, Op::Multiply)",
-        writer = "write::f32_u32(&self.rho_start, 16, RHO_MODIFIER, Op::Divide, deku::output)"
-    )]
-    pub rho_start: f32,
-    #[deku(
-        reader = "read::bits_to_f32(deku::rest, 16, RHO_MODIFIER, Op::Multiply)",
-        writer = "write::f32_u32(&self.rho_end, 16, RHO_MODIFIER, Op::Divide, deku::output)"
-    )]
-    pub rho_end: f32,
-    #[deku(
-        reader = "read::bits_to_f32(deku::rest, 16, THETA_MODIFIER, Op::Multiply)",
-        writer = "write::f32_u32(&self.theta_start, 16, THETA_MODIFIER, Op::Divide, deku::output)"
-    )]
-    pub theta_start: f32,
-    #[deku(
-        reader = "read::bits_to_f32(deku::rest, 16, THETA_MODIFIER, Op::Multiply)",
-        writer = "write::f32_u32(&self.theta_end, 16, THETA_MODIFIER, Op::Divide, deku::output)"
-    )]
-    pub theta_end: f32,
+    /// LSB of 1/256 NM
+    #[deku(endian = "big")]
+    pub rho_start: u16,
+    /// LSB of 1/256 NM
+    #[deku(endian = "big")]
+    pub rho_end: u16,
+    /// LSB of 360/2^16 degrees
+    #[deku(endian = "big")]
+    pub theta_start: u16,
+    /// LSB of 360/2^16 degrees
+    #[deku(endian = "big")]
+    pub theta_end: u16,
 }
 
 impl GenericPolarWindow {
     pub const FRN_34: u8 = 0b0100_0000;
+
+    pub fn rho_start_nm(&self) -> f64 {
+        f64::from(self.rho_start) * RHO_MODIFIER
+    }
+
+    pub fn rho_end_nm(&self) -> f64 {
+        f64::from(self.rho_end) * RHO_MODIFIER
+    }
+
+    pub fn theta_start_deg(&self) -> f64 {
+        f64::from(self.theta_start) * THETA_MODIFIER
+    }
+
+    pub fn theta_end_deg(&self) -> f64 {
+        f64::from(self.theta_end) * THETA_MODIFIER
+    }
+
+    pub fn set_rho_start_nm(&mut self, rho: f64) -> Result<(), DekuError> {
+        self.rho_start = modifier::unsigned(rho, RHO_MODIFIER, 16, "I034/100 rho start")? as u16;
+        Ok(())
+    }
+
+    pub fn set_rho_end_nm(&mut self, rho: f64) -> Result<(), DekuError> {
+        self.rho_end = modifier::unsigned(rho, RHO_MODIFIER, 16, "I034/100 rho end")? as u16;
+        Ok(())
+    }
+
+    pub fn set_theta_start_deg(&mut self, theta: f64) -> Result<(), DekuError> {
+        self.theta_start = modifier::angle(theta, 16, "I034/100 theta start")? as u16;
+        Ok(())
+    }
+
+    /// Set theta start, wrapping it into 0..360 degrees first
+    pub fn set_theta_start_deg_wrapped(&mut self, theta: f64) -> Result<(), DekuError> {
+        self.set_theta_start_deg(modifier::wrap_deg(theta))
+    }
+
+    pub fn set_theta_end_deg(&mut self, theta: f64) -> Result<(), DekuError> {
+        self.theta_end = modifier::angle(theta, 16, "I034/100 theta end")? as u16;
+        Ok(())
+    }
+
+    /// Set theta end, wrapping it into 0..360 degrees first
+    pub fn set_theta_end_deg_wrapped(&mut self, theta: f64) -> Result<(), DekuError> {
+        self.set_theta_end_deg(modifier::wrap_deg(theta))
+    }
 }
 
 /// Data Filter, which allows suppression of individual data types.
 ///
 /// Data Item I034/110
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, Clone, DekuRead, DekuWrite)]
 #[deku(ctx = "_: deku::ctx::Endian")]
 pub struct DataFilter {
     pub typ: DataFilterTYP,
@@ -1004,25 +1299,51 @@ impl DataFilter {
 /// 3D-Position of Data Source in WGS 84 Co-ordinates
 ///
 /// Data Item I034/120
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, Clone, DekuRead, DekuWrite)]
 #[deku(ctx = "_: deku::ctx::Endian")]
 pub struct ThreeDPositionOfDataSource {
-    pub height_of_wgs_84: u16,
+    /// Height above WGS-84 ellipsoid, two's complement, LSB of 1 m
+    #[deku(endian = "big")]
+    pub height_of_wgs_84: i16,
+    /// Two's complement, LSB of 180/2^23 degrees, positive north
     #[deku(
-        reader = "read::bits_to_f32(deku::rest, 24, Self::WGS_MODIFIER, Op::Multiply)",
-        writer = "write::f32_u32(&self.latitude_in_wgs_84, 24, Self::WGS_MODIFIER, Op::Divide, deku::output)"
+        writer = "write::signed(&self.latitude_in_wgs_84, 24, \"I034/120 latitude\", deku::output)"
     )]
-    pub latitude_in_wgs_84: f32,
+    #[deku(bits = "24", endian = "big")]
+    pub latitude_in_wgs_84: i32,
+    /// Two's complement, LSB of 180/2^23 degrees, positive east
     #[deku(
-        reader = "read::bits_to_f32(deku::rest, 24, Self::WGS_MODIFIER, Op::Multiply)",
-        writer = "write::f32_u32(&self.longitude_in_wgs_84, 24, Self::WGS_MODIFIER, Op::Divide, deku::output)"
+        writer = "write::signed(&self.longitude_in_wgs_84, 24, \"I034/120 longitude\", deku::output)"
     )]
-    pub longitude_in_wgs_84: f32,
+    #[deku(bits = "24", endian = "big")]
+    pub longitude_in_wgs_84: i32,
 }
 
 impl ThreeDPositionOfDataSource {
-    pub const WGS_MODIFIER: f32 = 180.0 / 8_388_608.0;
+    pub const WGS_MODIFIER: f64 = 180.0 / 8_388_608.0;
     pub const FRN_34: u8 = 0b0001_0000;
+
+    /// Latitude in degrees
+    pub fn latitude_deg(&self) -> f64 {
+        f64::from(self.latitude_in_wgs_84) * Self::WGS_MODIFIER
+    }
+
+    /// Longitude in degrees
+    pub fn longitude_deg(&self) -> f64 {
+        f64::from(self.longitude_in_wgs_84) * Self::WGS_MODIFIER
+    }
+
+    pub fn set_latitude_deg(&mut self, latitude: f64) -> Result<(), DekuError> {
+        self.latitude_in_wgs_84 =
+            modifier::signed(latitude, Self::WGS_MODIFIER, 24, "I034/120 latitude")? as i32;
+        Ok(())
+    }
+
+    pub fn set_longitude_deg(&mut self, longitude: f64) -> Result<(), DekuError> {
+        self.longitude_in_wgs_84 =
+            modifier::signed(longitude, Self::WGS_MODIFIER, 24, "I034/120 longitude")? as i32;
+        Ok(())
+    }
 }
 
 /// Averaged difference in range and in azimuth for the primary target
@@ -1030,42 +1351,240 @@ impl ThreeDPositionOfDataSource {
 /// the radar station
 ///
 /// Data Item I034/090
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, Clone, DekuRead, DekuWrite)]
 #[deku(ctx = "_: deku::ctx::Endian")]
 pub struct CollimationError {
-    #[deku(
-        reader = "read::bits_to_f32(deku::rest, 8, Self::MODIFIER, Op::Multiply)",
-        writer = "write::f32_u32(&self.range_error, 8, Self::MODIFIER, Op::Divide, deku::output)"
-    )]
-    pub range_error: f32,
-    #[deku(
-        reader = "read::bits_to_f32(deku::rest, 8, Self::AZIMUTH_MODIFIER, Op::Multiply)",
-        writer = "write::f32_u32(&self.azimuth_error, 8, Self::AZIMUTH_MODIFIER, Op::Divide, deku::output)"
-    )]
-    pub azimuth_error: f32,
+    /// Two's complement, LSB of 1/128 NM
+    pub range_error: i8,
+    /// Two's complement, LSB of 360/2^14 degrees
+    pub azimuth_error: i8,
 }
 
 impl CollimationError {
-    pub const MODIFIER: f32 = 1.0 / 128.0;
-    // TODO #![feature(const_int_pow)]
-    //pub const AZIMUTH_MODIFIER: f32 = 360.0 / f32::from(2_u16.pow(14));
-    pub const AZIMUTH_MODIFIER: f32 = 360.0 / 16384.0;
+    pub const MODIFIER: f64 = 1.0 / 128.0;
+    pub const AZIMUTH_MODIFIER: f64 = 360.0 / 16384.0;
     pub const FRN_34: u8 = 0b0000_1000;
+
+    /// Range error in NM
+    pub fn range_error_nm(&self) -> f64 {
+        f64::from(self.range_error) * Self::MODIFIER
+    }
+
+    /// Azimuth error in degrees
+    pub fn azimuth_error_deg(&self) -> f64 {
+        f64::from(self.azimuth_error) * Self::AZIMUTH_MODIFIER
+    }
+
+    pub fn set_range_error_nm(&mut self, error: f64) -> Result<(), DekuError> {
+        self.range_error =
+            modifier::signed(error, Self::MODIFIER, 8, "I034/090 range error")? as i8;
+        Ok(())
+    }
+
+    pub fn set_azimuth_error_deg(&mut self, error: f64) -> Result<(), DekuError> {
+        self.azimuth_error =
+            modifier::signed(error, Self::AZIMUTH_MODIFIER, 8, "I034/090 azimuth error")? as i8;
+        Ok(())
+    }
+}
+
+/// Special Purpose Field, kept as raw octets
+///
+/// Data Item I048/SP, I034/SP and I004/SP
+#[derive(Debug, PartialEq, Clone, DekuRead, DekuWrite)]
+#[deku(ctx = "_: deku::ctx::Endian")]
+pub struct SpecialPurposeField {
+    /// Length in octets, including this one
+    #[deku(update = "self.data.len() + 1")]
+    pub len: u8,
+    #[deku(count = "usize::from(*len).saturating_sub(1)")]
+    pub data: Vec<u8>,
+}
+
+impl SpecialPurposeField {
+    pub const FRN_4: u8 = 0b10;
+    pub const FRN_48: u8 = 0b100;
+    pub const FRN_34: u8 = 0b10;
+}
+
+/// Reserved Expansion Field, kept as raw octets
+///
+/// Data Item I048/RE, I034/RE and I004/RE
+#[derive(Debug, PartialEq, Clone, DekuRead, DekuWrite)]
+#[deku(ctx = "_: deku::ctx::Endian")]
+pub struct ReservedExpansionField {
+    /// Length in octets, including this one
+    #[deku(update = "self.data.len() + 1")]
+    pub len: u8,
+    #[deku(count = "usize::from(*len).saturating_sub(1)")]
+    pub data: Vec<u8>,
+}
+
+impl ReservedExpansionField {
+    pub const FRN_4: u8 = 0b100;
+    pub const FRN_48: u8 = 0b10;
+    pub const FRN_34: u8 = 0b100;
+}
+
+/// Safety net function of the message
+///
+/// Data Item I004/000
+#[derive(Debug, PartialEq, Clone, DekuRead, DekuWrite)]
+#[deku(ctx = "_: deku::ctx::Endian")]
+pub struct SafetyNetMessageType {
+    pub t: SafetyNetMTYPE,
+}
+
+impl SafetyNetMessageType {
+    pub const FRN_4: u8 = 0b100_0000;
+}
+
+/// Surveillance Data Processing Systems whose tracks the safety net processed
+///
+/// Data Item I004/015
+#[derive(Debug, PartialEq, Clone, DekuRead, DekuWrite)]
+#[deku(ctx = "_: deku::ctx::Endian")]
+pub struct SdpsIdentifier {
+    #[deku(update = "self.sdps.len()")]
+    pub count: u8,
+    #[deku(count = "count", ctx = "deku::ctx::Endian::Big")]
+    pub sdps: Vec<DataSourceIdentifier>,
+}
+
+impl SdpsIdentifier {
+    pub const FRN_4: u8 = 0b10_0000;
+}
+
+/// Identification of an alert, unique per safety net function while the alert lasts
+///
+/// Data Item I004/040
+#[derive(Debug, PartialEq, Clone, DekuRead, DekuWrite)]
+#[deku(ctx = "_: deku::ctx::Endian")]
+pub struct AlertIdentifier {
+    #[deku(endian = "big")]
+    pub id: u16,
+}
+
+impl AlertIdentifier {
+    pub const FRN_4: u8 = 0b1000;
+}
+
+/// Status of an alert, with system dependent values
+///
+/// Data Item I004/045
+#[derive(Debug, PartialEq, Clone, DekuRead, DekuWrite)]
+#[deku(ctx = "_: deku::ctx::Endian")]
+pub struct AlertStatus {
+    #[deku(bits = "5")]
+    pub reserved: u8,
+    #[deku(bits = "3")]
+    pub stat: u8,
+}
+
+impl AlertStatus {
+    pub const FRN_4: u8 = 0b100;
+}
+
+/// Predicted times and separations of a conflict
+///
+/// Data Item I004/070
+#[derive(Debug, PartialEq, Clone, DekuRead, DekuWrite)]
+#[deku(ctx = "_: deku::ctx::Endian")]
+pub struct ConflictTimingAndSeparation {
+    #[deku(until = "|b: &u8| *b & 0b0000_0001 == 0")]
+    pub fspec: Vec<u8>,
+    /// Time to conflict, LSB of 1/128 s
+    #[deku(skip, cond = "is_fspec(0b1000_0000, fspec, 0)", bits = "24", endian = "big")]
+    pub tf: Option<u32>,
+    /// Time to closest approach, LSB of 1/128 s
+    #[deku(skip, cond = "is_fspec(0b100_0000, fspec, 0)", bits = "24", endian = "big")]
+    pub tc: Option<u32>,
+    /// Current horizontal separation, LSB of 0.5 m
+    #[deku(skip, cond = "is_fspec(0b10_0000, fspec, 0)", bits = "24", endian = "big")]
+    pub chs: Option<u32>,
+    /// Estimated minimum horizontal separation, LSB of 0.5 m
+    #[deku(skip, cond = "is_fspec(0b1_0000, fspec, 0)", endian = "big")]
+    pub mhs: Option<u16>,
+    /// Current vertical separation, LSB of 25 ft
+    #[deku(skip, cond = "is_fspec(0b1000, fspec, 0)", endian = "big")]
+    pub cvs: Option<u16>,
+    /// Estimated minimum vertical separation, LSB of 25 ft
+    #[deku(skip, cond = "is_fspec(0b100, fspec, 0)", endian = "big")]
+    pub mvs: Option<u16>,
+}
+
+impl ConflictTimingAndSeparation {
+    pub const FRN_4: u8 = 0b1_0000;
+}
+
+/// Identification and characteristics of an aircraft in the alert
+///
+/// Data Item I004/170 for the first aircraft and I004/171 for the second
+#[derive(Debug, PartialEq, Clone, DekuRead, DekuWrite)]
+#[deku(ctx = "_: deku::ctx::Endian")]
+pub struct AircraftIdentificationAndCharacteristics {
+    #[deku(until = "|b: &u8| *b & 0b0000_0001 == 0")]
+    pub fspec: Vec<u8>,
+    /// Aircraft identifier from the flight plan, seven ASCII characters
+    #[deku(skip, cond = "is_fspec(0b1000_0000, fspec, 0)")]
+    pub ai: Option<[u8; 7]>,
+    /// Mode 3/A code in the twelve least significant bits
+    #[deku(skip, cond = "is_fspec(0b100_0000, fspec, 0)", endian = "big")]
+    pub m3: Option<u16>,
+    /// Predicted conflict position in WGS-84, kept as raw octets
+    #[deku(skip, cond = "is_fspec(0b10_0000, fspec, 0)")]
+    pub cpw: Option<[u8; 10]>,
+    /// Predicted conflict position in Cartesian coordinates, kept as raw octets
+    #[deku(skip, cond = "is_fspec(0b1_0000, fspec, 0)")]
+    pub cpc: Option<[u8; 8]>,
+    /// Time to runway threshold, LSB of 1/128 s
+    #[deku(skip, cond = "is_fspec(0b1000, fspec, 0)", bits = "24", endian = "big")]
+    pub tt: Option<u32>,
+    /// Distance to runway threshold, LSB of 0.5 m
+    #[deku(skip, cond = "is_fspec(0b100, fspec, 0)", endian = "big")]
+    pub dt: Option<u16>,
+    /// Aircraft characteristics, kept as raw octets up to the one without FX
+    #[deku(
+        skip,
+        cond = "is_fspec(0b1000_0000, fspec, 1)",
+        until = "|b: &u8| *b & 0b0000_0001 == 0"
+    )]
+    pub ac: Option<Vec<u8>>,
+    /// 24 bit Mode S address
+    #[deku(skip, cond = "is_fspec(0b100_0000, fspec, 1)", bytes = "3", endian = "big")]
+    pub ms: Option<u32>,
+    /// Flight plan number
+    #[deku(skip, cond = "is_fspec(0b10_0000, fspec, 1)", endian = "big")]
+    pub fp: Option<u32>,
+    /// Cleared flight level, LSB of 1/4 FL
+    #[deku(skip, cond = "is_fspec(0b1_0000, fspec, 1)", endian = "big")]
+    pub cf: Option<i16>,
+}
+
+impl AircraftIdentificationAndCharacteristics {
+    /// I004/170
+    pub const FRN_4: u8 = 0b100_0000;
+    /// I004/171
+    pub const FRN_4_SECOND: u8 = 0b10_0000;
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use deku::bitvec::{BitSlice, BitVec};
     // tests from https://github.com/wireshark/wireshark/blob/master/test/suite_dissectors/group_asterix.py
 
     #[test]
+    #[allow(clippy::unnecessary_mut_passed, clippy::needless_borrow)]
     fn tod_140() {
         let mut input = BitSlice::from_slice(&[0xa8, 0xbf, 0xff]);
-        let item = TimeOfDay::read(&mut input, deku::ctx::Endian::Big).unwrap().1;
-        assert_eq!(item.time, 86_399.99);
+        let item = TimeOfDay::read(&mut input, (deku::ctx::Endian::Big, 48)).unwrap().1;
+        assert_eq!(item.time, 0xa8_bfff);
+        assert_eq!(item.seconds(), 86_399.992_187_5);
     }
 
     #[test]
+    #[allow(clippy::unnecessary_mut_passed, clippy::needless_borrow)]
     fn target_report_descriptor_020() {
         let mut input = BitSlice::from_slice(&[
             0xe0 | 0x08 | 0x04 | 0x02 | 0x01,
@@ -1085,4 +1604,34 @@ mod tests {
         assert_eq!(item.mi, Some(MI::MilitaryIdentification));
         assert_eq!(item.foe_fri, Some(FOEFRI::NoReply));
     }
+
+    #[test]
+    fn target_report_descriptor_020_extents() {
+        let bytes = [0x01, 0x01, 0b1100_0111, 0b0000_0011, 0b1000_0000];
+        let (rest, item) =
+            TargetReportDescriptor::read(BitSlice::from_slice(&bytes), deku::ctx::Endian::Big)
+                .unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(item.adsb_ep, Some(EP::ElementPopulated));
+        assert_eq!(item.adsb_val, Some(VAL::Available));
+        assert_eq!(item.scn_ep, Some(EP::ElementNotPopulated));
+        assert_eq!(item.scn_val, Some(VAL::NotAvailable));
+        assert_eq!(item.pai_ep, Some(EP::ElementNotPopulated));
+        assert_eq!(item.pai_val, Some(VAL::Available));
+        assert_eq!(item.fx3, Some(FX::ExtensionIntoFirstExtent));
+        assert_eq!(item.extents, [0b0000_0011, 0b1000_0000]);
+
+        let mut output = BitVec::new();
+        item.write(&mut output, deku::ctx::Endian::Big).unwrap();
+        assert_eq!(output.into_vec(), bytes);
+
+        // the last extent must end the item
+        let mut item = item;
+        item.extents = vec![0b0000_0011];
+        assert!(item.write(&mut BitVec::new(), deku::ctx::Endian::Big).is_err());
+        item.extents = vec![];
+        assert!(item.write(&mut BitVec::new(), deku::ctx::Endian::Big).is_err());
+        item.fx3 = Some(FX::EndOfDataItem);
+        item.write(&mut BitVec::new(), deku::ctx::Endian::Big).unwrap();
+    }
 }
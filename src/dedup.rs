@@ -0,0 +1,246 @@
+//! Suppression of the duplicate packets of redundant feeds
+//!
+//! Radar data distributed on two LANs arrives twice, at slightly different times. `Deduplicator`
+//! passes on the first copy of each packet from any input, and suppresses the copies that arrive
+//! within the time window after it. Copies are recognised by the SAC/SIC and time of day of the
+//! first record that has them, and by the encoded content of the packet.
+//!
+//! The counts per input show a one-sided loss: when a packet leaves the window, every input
+//! that didn't carry it, and had pushed a packet by the time it was first received, has missed
+//! it.
+
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime};
+
+use deku::{DekuContainerWrite, DekuError};
+
+use crate::AsterixPacket;
+
+/// Packets carried by an input
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct InputStats {
+    /// First copies, passed on
+    pub unique: u64,
+    /// Copies of a packet already received, suppressed
+    pub duplicate: u64,
+    /// Packets received on other inputs only, counted when they leave the window
+    pub missed: u64,
+}
+
+impl InputStats {
+    /// Packets received
+    pub fn received(&self) -> u64 {
+        self.unique + self.duplicate
+    }
+}
+
+/// What identifies copies of a packet
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Signature {
+    source: Option<(u8, u8)>,
+    /// Raw I048/140 or I034/030
+    time_of_day: Option<u32>,
+    content: Vec<u8>,
+}
+
+impl Signature {
+    fn new(packet: &AsterixPacket) -> Result<Self, DekuError> {
+        let source =
+            packet.messages.iter().find_map(|m| m.data_source_identifier().map(|s| (s.sac, s.sic)));
+        let time_of_day = packet.messages.iter().find_map(|m| m.time_of_day().map(|t| t.time));
+        Ok(Self { source, time_of_day, content: packet.to_bytes()? })
+    }
+}
+
+/// Input that has pushed a packet
+#[derive(Debug, Clone)]
+struct Input {
+    /// Time of its first packet
+    since: SystemTime,
+    stats: InputStats,
+}
+
+#[derive(Debug, Clone)]
+struct Seen {
+    first: SystemTime,
+    /// Inputs that carried the packet
+    inputs: Vec<usize>,
+}
+
+/// Passes on the first copy of each packet, see the module documentation
+#[derive(Debug, Clone)]
+pub struct Deduplicator {
+    window: Duration,
+    seen: HashMap<Signature, Seen>,
+    /// By input number, `None` for the numbers of inputs that haven't pushed a packet
+    inputs: Vec<Option<Input>>,
+}
+
+impl Deduplicator {
+    /// Suppress copies received up to `window` after the first
+    pub fn new(window: Duration) -> Self {
+        Self { window, seen: HashMap::new(), inputs: vec![] }
+    }
+
+    /// Counts of input `input`, `None` before its first packet
+    pub fn stats(&self, input: usize) -> Option<InputStats> {
+        self.inputs.get(input)?.as_ref().map(|i| i.stats)
+    }
+
+    /// Input numbers and counts of the inputs that have pushed a packet
+    pub fn inputs(&self) -> impl Iterator<Item = (usize, InputStats)> + '_ {
+        self.inputs.iter().enumerate().filter_map(|(n, i)| Some((n, i.as_ref()?.stats)))
+    }
+
+    /// Take a packet from input `input` received at `received`, returning it if it is the
+    /// first copy
+    ///
+    /// Fails if the packet can't be encoded for comparison.
+    pub fn push(
+        &mut self,
+        input: usize,
+        packet: AsterixPacket,
+        received: SystemTime,
+    ) -> Result<Option<AsterixPacket>, DekuError> {
+        let signature = Signature::new(&packet)?;
+        self.expire(received);
+        if self.inputs.len() <= input {
+            self.inputs.resize(input + 1, None);
+        }
+        let stats = &mut self.inputs[input]
+            .get_or_insert(Input { since: received, stats: InputStats::default() })
+            .stats;
+
+        match self.seen.get_mut(&signature) {
+            Some(seen) => {
+                if !seen.inputs.contains(&input) {
+                    seen.inputs.push(input);
+                }
+                stats.duplicate += 1;
+                Ok(None)
+            }
+            None => {
+                self.seen.insert(signature, Seen { first: received, inputs: vec![input] });
+                stats.unique += 1;
+                Ok(Some(packet))
+            }
+        }
+    }
+
+    /// Forget the packets first received more than the window before `now`, counting them as
+    /// missed by the inputs that didn't carry them
+    pub fn expire(&mut self, now: SystemTime) {
+        let window = self.window;
+        let inputs = &mut self.inputs;
+        self.seen.retain(|_, seen| {
+            // a clock stepped back gives an age of zero
+            let age = now.duration_since(seen.first).unwrap_or_default();
+            if age <= window {
+                return true;
+            }
+            for (number, input) in inputs.iter_mut().enumerate() {
+                let Some(input) = input else {
+                    continue;
+                };
+                if input.since <= seen.first && !seen.inputs.contains(&number) {
+                    input.stats.missed += 1;
+                }
+            }
+            false
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_item::TimeOfDay;
+    use crate::test_util::cat48;
+    use crate::AsterixMessage;
+
+    fn packet(seconds: f64) -> AsterixPacket {
+        let mut packet = AsterixPacket {
+            category: 48,
+            messages: vec![AsterixMessage::Cat48(cat48().with_time(seconds))],
+            ..AsterixPacket::default()
+        };
+        packet.finalize().unwrap();
+        packet
+    }
+
+    fn at(millis: u64) -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn dual_lan() {
+        let (a, b) = (0, 1);
+        let mut dedup = Deduplicator::new(Duration::from_millis(500));
+
+        assert!(dedup.push(a, packet(1.0), at(0)).unwrap().is_some());
+        assert!(dedup.push(b, packet(1.0), at(20)).unwrap().is_none());
+        // B first this time
+        assert!(dedup.push(b, packet(2.0), at(1000)).unwrap().is_some());
+        assert!(dedup.push(a, packet(2.0), at(1010)).unwrap().is_none());
+        // lost on B
+        assert!(dedup.push(a, packet(3.0), at(2000)).unwrap().is_some());
+        // a copy after the window is passed on again
+        assert!(dedup.push(a, packet(3.0), at(2600)).unwrap().is_some());
+
+        dedup.expire(at(5000));
+        assert_eq!(dedup.stats(a), Some(InputStats { unique: 3, duplicate: 1, missed: 0 }));
+        assert_eq!(dedup.stats(b), Some(InputStats { unique: 1, duplicate: 1, missed: 2 }));
+        assert_eq!(
+            dedup.inputs().map(|(n, s)| (n, s.received())).collect::<Vec<_>>(),
+            [(a, 4), (b, 2)]
+        );
+        assert_eq!(dedup.stats(2), None);
+    }
+
+    #[test]
+    fn inputs_without_packets() {
+        let mut dedup = Deduplicator::new(Duration::from_millis(500));
+        // input 1 never pushes a packet, input 2 starts late
+        dedup.push(0, packet(1.0), at(0)).unwrap();
+        dedup.push(2, packet(2.0), at(100)).unwrap();
+        dedup.push(0, packet(2.0), at(110)).unwrap();
+        dedup.expire(at(1000));
+        assert_eq!(dedup.stats(1), None);
+        assert_eq!(dedup.inputs().map(|(n, _)| n).collect::<Vec<_>>(), [0, 2]);
+        assert_eq!(dedup.stats(0).unwrap().missed, 0);
+        assert_eq!(dedup.stats(2).unwrap().missed, 0);
+    }
+
+    #[test]
+    fn repeated_on_one_input() {
+        let mut dedup = Deduplicator::new(Duration::from_millis(500));
+        dedup.push(0, packet(5.0), at(0)).unwrap();
+        dedup.push(1, packet(6.0), at(0)).unwrap();
+        assert!(dedup.push(0, packet(1.0), at(10)).unwrap().is_some());
+        assert!(dedup.push(0, packet(1.0), at(20)).unwrap().is_none());
+        dedup.expire(at(1000));
+        assert_eq!(dedup.stats(0), Some(InputStats { unique: 2, duplicate: 1, missed: 1 }));
+        // missed once, not once per copy
+        assert_eq!(dedup.stats(1), Some(InputStats { unique: 1, duplicate: 0, missed: 2 }));
+    }
+
+    #[test]
+    fn clock_stepped_back() {
+        let mut dedup = Deduplicator::new(Duration::from_millis(500));
+        assert!(dedup.push(0, packet(1.0), at(1000)).unwrap().is_some());
+        // received before the first copy, an age of zero
+        assert!(dedup.push(1, packet(1.0), at(200)).unwrap().is_none());
+        dedup.expire(at(0));
+        assert_eq!(dedup.seen.len(), 1);
+    }
+
+    #[test]
+    fn not_encodable() {
+        let mut dedup = Deduplicator::new(Duration::from_millis(500));
+        let mut packet = packet(1.0);
+        let AsterixMessage::Cat48(record) = &mut packet.messages[0] else { unreachable!() };
+        record.time_of_day = Some(TimeOfDay { time: 0x100_0000 });
+        assert!(matches!(dedup.push(0, packet, at(0)), Err(DekuError::InvalidParam(_))));
+        assert_eq!(dedup.stats(0), None);
+    }
+}
@@ -0,0 +1,216 @@
+//! Editions of the ASTERIX categories, and the choice of edition per data source
+//!
+//! Every edition is decoded with the layout of the latest supported edition, which accepts every
+//! data item this crate knows about. `AsterixPacket::from_bytes` doesn't know which edition the
+//! sender uses, so it leaves the `edition` of each record unset and the record unchecked. Use
+//! `Editions` to set the edition that each radar is configured for, and validate the packet
+//! against it, rejecting data items that the edition doesn't define.
+
+use std::collections::HashMap;
+
+use deku::prelude::*;
+
+use crate::data_item::DataSourceIdentifier;
+use crate::types::CODE;
+use crate::{AsterixMessage, AsterixPacket};
+
+/// Edition of CAT048, Transmission of Monoradar Target Reports
+#[derive(Debug, Default, PartialEq, Eq, Hash, Clone, Copy, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub enum Cat48Edition {
+    #[cfg_attr(feature = "serde", serde(rename = "1.15"))]
+    V1_15,
+    #[cfg_attr(feature = "serde", serde(rename = "1.21"))]
+    V1_21,
+    #[default]
+    #[cfg_attr(feature = "serde", serde(rename = "1.31"))]
+    V1_31,
+}
+
+impl Cat48Edition {
+    /// I048/020 has the second extent with the ADSB, SCN and PAI subfields
+    pub fn has_target_report_second_extent(&self) -> bool {
+        *self >= Self::V1_31
+    }
+
+    /// The Reserved Expansion Field (FRN 28) is defined
+    pub fn has_reserved_expansion_field(&self) -> bool {
+        *self >= Self::V1_21
+    }
+
+    /// Last I048/030 code with a defined meaning, codes above this are reserved
+    pub fn last_warning_code(&self) -> u16 {
+        match self {
+            Self::V1_15 => 28,
+            Self::V1_21 => 30,
+            Self::V1_31 => 36,
+        }
+    }
+
+    /// `code` is defined by this edition, AMG and manufacturer codes are always accepted
+    pub fn defines(&self, code: &CODE) -> bool {
+        let number = match code {
+            CODE::TrackUpdateZenithalGap => 29,
+            CODE::ModeSTrackReAquired => 30,
+            CODE::DuplicatedMode5PairNoPinDetected => 31,
+            CODE::WrongDFReplyFormatDetected => 32,
+            CODE::TransponderAnomalyMs => 33,
+            CODE::TransponderAnomalySI => 34,
+            CODE::PotentialICConflict => 35,
+            CODE::ICConflictDetectionPossible => 36,
+            _ => 0,
+        };
+        number <= self.last_warning_code()
+    }
+}
+
+impl core::fmt::Display for Cat48Edition {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::V1_15 => write!(f, "1.15"),
+            Self::V1_21 => write!(f, "1.21"),
+            Self::V1_31 => write!(f, "1.31"),
+        }
+    }
+}
+
+/// Edition of CAT034, Transmission of Monoradar Service Messages
+#[derive(Debug, Default, PartialEq, Eq, Hash, Clone, Copy, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub enum Cat34Edition {
+    #[cfg_attr(feature = "serde", serde(rename = "1.26"))]
+    V1_26,
+    #[cfg_attr(feature = "serde", serde(rename = "1.27"))]
+    V1_27,
+    #[default]
+    #[cfg_attr(feature = "serde", serde(rename = "1.29"))]
+    V1_29,
+}
+
+impl Cat34Edition {
+    /// The Reserved Expansion Field (FRN 13) is defined
+    pub fn has_reserved_expansion_field(&self) -> bool {
+        *self >= Self::V1_27
+    }
+}
+
+impl core::fmt::Display for Cat34Edition {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::V1_26 => write!(f, "1.26"),
+            Self::V1_27 => write!(f, "1.27"),
+            Self::V1_29 => write!(f, "1.29"),
+        }
+    }
+}
+
+/// Edition of each category, with overrides per data source
+///
+/// ```rust
+/// use asterix::edition::{Cat34Edition, Cat48Edition, Editions};
+///
+/// // all radars use 1.21, except SAC/SIC 25/13
+/// let editions = Editions::new(Cat48Edition::V1_21, Cat34Edition::V1_27)
+///     .with_cat48_source(25, 13, Cat48Edition::V1_31);
+///
+/// let bytes = [0x22, 0x00, 0x0b, 0xf0, 0x19, 0x0d, 0x02, 0x35, 0x6d, 0xfa, 0x60];
+/// let packet = editions.decode_validated(&bytes).unwrap();
+/// ```
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Editions {
+    cat48: Cat48Edition,
+    cat34: Cat34Edition,
+    cat48_sources: HashMap<(u8, u8), Cat48Edition>,
+    cat34_sources: HashMap<(u8, u8), Cat34Edition>,
+}
+
+impl Editions {
+    /// Use `cat48` and `cat34` for every data source
+    pub fn new(cat48: Cat48Edition, cat34: Cat34Edition) -> Self {
+        Self { cat48, cat34, ..Self::default() }
+    }
+
+    /// Use `edition` for CAT048 records from SAC/SIC
+    pub fn with_cat48_source(mut self, sac: u8, sic: u8, edition: Cat48Edition) -> Self {
+        self.cat48_sources.insert((sac, sic), edition);
+        self
+    }
+
+    /// Use `edition` for CAT034 records from SAC/SIC
+    pub fn with_cat34_source(mut self, sac: u8, sic: u8, edition: Cat34Edition) -> Self {
+        self.cat34_sources.insert((sac, sic), edition);
+        self
+    }
+
+    /// CAT048 edition of records from `source`
+    pub fn cat48(&self, source: Option<&DataSourceIdentifier>) -> Cat48Edition {
+        source.and_then(|s| self.cat48_sources.get(&(s.sac, s.sic))).copied().unwrap_or(self.cat48)
+    }
+
+    /// CAT034 edition of records from `source`
+    pub fn cat34(&self, source: Option<&DataSourceIdentifier>) -> Cat34Edition {
+        source.and_then(|s| self.cat34_sources.get(&(s.sac, s.sic))).copied().unwrap_or(self.cat34)
+    }
+
+    /// Set the edition of every record in `packet`, and check the records against it
+    ///
+    /// The records keep their decoded layout, only the data items that the edition doesn't
+    /// define are rejected.
+    pub fn validate(&self, packet: &mut AsterixPacket) -> Result<(), DekuError> {
+        for message in &mut packet.messages {
+            match message {
+                AsterixMessage::Cat48(c) => {
+                    c.edition = Some(self.cat48(c.data_source_identifier.as_ref()))
+                }
+                AsterixMessage::Cat34(c) => {
+                    c.edition = Some(self.cat34(c.data_source_identifier.as_ref()))
+                }
+                AsterixMessage::Cat4(_) => {}
+            }
+            message.check_edition()?;
+        }
+        Ok(())
+    }
+
+    /// Decode a packet, then `validate()` it against the edition of each record's data source
+    pub fn decode_validated(&self, bytes: &[u8]) -> Result<AsterixPacket, DekuError> {
+        let (_, mut packet) = AsterixPacket::from_bytes((bytes, 0))?;
+        self.validate(&mut packet)?;
+        Ok(packet)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cat48_boundaries() {
+        let editions = [Cat48Edition::V1_15, Cat48Edition::V1_21, Cat48Edition::V1_31];
+        let second_extent = editions.map(|e| e.has_target_report_second_extent());
+        assert_eq!(second_extent, [false, false, true]);
+        let reserved_expansion = editions.map(|e| e.has_reserved_expansion_field());
+        assert_eq!(reserved_expansion, [false, true, true]);
+        assert_eq!(editions.map(|e| e.last_warning_code()), [28, 30, 36]);
+
+        // last code of each edition, and the first one after it
+        let codes = [
+            (CODE::PotentialBDSSwapDetected, [true, true, true]),
+            (CODE::TrackUpdateZenithalGap, [false, true, true]),
+            (CODE::ModeSTrackReAquired, [false, true, true]),
+            (CODE::DuplicatedMode5PairNoPinDetected, [false, false, true]),
+            (CODE::ICConflictDetectionPossible, [false, false, true]),
+            (CODE::AMGAllocated(37), [true, true, true]),
+            (CODE::Manufacturer(127), [true, true, true]),
+        ];
+        for (code, defined) in codes {
+            assert_eq!(editions.map(|e| e.defines(&code)), defined, "{code:?}");
+        }
+    }
+
+    #[test]
+    fn cat34_boundaries() {
+        let editions = [Cat34Edition::V1_26, Cat34Edition::V1_27, Cat34Edition::V1_29];
+        assert_eq!(editions.map(|e| e.has_reserved_expansion_field()), [false, true, true]);
+    }
+}
@@ -0,0 +1,49 @@
+//! Errors other than those of encoding and decoding, which fail with `DekuError`
+
+/// Error of this crate other than encoding and decoding
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    /// A slant range, or a range on the radar plane, in m that can't reach the height in m of the
+    /// target, seen from the radar
+    SlantRangeOutOfReach { slant_range: f64, height: f64 },
+    /// A year, month and day that isn't a calendar date
+    InvalidDate { year: i32, month: u8, day: u8 },
+    /// A parameter outside of its valid range, such as a configuration value
+    InvalidParam(String),
+}
+
+impl core::fmt::Display for Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::SlantRangeOutOfReach { slant_range, height } => {
+                write!(f, "slant range of {slant_range} m can't reach a height of {height} m")
+            }
+            Self::InvalidDate { year, month, day } => {
+                write!(f, "{year}-{month:02}-{day:02} is not a date")
+            }
+            Self::InvalidParam(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Each of the `params` of `module`, by name, is a finite number above zero
+pub(crate) fn positive(module: &str, params: &[(&str, f64)]) -> Result<(), Error> {
+    match params.iter().find(|(_, value)| !(*value > 0.0 && value.is_finite())) {
+        Some((name, value)) => Err(Error::InvalidParam(format!(
+            "{module}: {name} {value} is not a finite positive number"
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Each of the `params` of `module`, by name, is a finite number of at least zero
+pub(crate) fn non_negative(module: &str, params: &[(&str, f64)]) -> Result<(), Error> {
+    match params.iter().find(|(_, value)| !(*value >= 0.0 && value.is_finite())) {
+        Some((name, value)) => Err(Error::InvalidParam(format!(
+            "{module}: {name} {value} is not a finite non-negative number"
+        ))),
+        None => Ok(()),
+    }
+}
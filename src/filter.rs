@@ -0,0 +1,327 @@
+//! Filtering of CAT048 records by I034/100 Generic Polar Window and I034/110 Data Filter
+//!
+//! A radar can be asked to send only the plots inside a polar window, and to suppress some types
+//! of data in a window. `StreamFilter` applies the same rules to a CAT048 stream, so that the
+//! output holds only what the radar was asked to send, even if the input is a superset. Use one
+//! `StreamFilter` per radar, it ignores the service messages and drops the plots of other radars.
+//!
+//! ```rust
+//! use asterix::data_item::GenericPolarWindow;
+//! use asterix::filter::StreamFilter;
+//! use asterix::types::DataFilterTYP;
+//!
+//! // plots within 100 NM, without primary-only plots north of the radar
+//! let mut coverage = GenericPolarWindow { rho_start: 0, rho_end: 0, theta_start: 0, theta_end: 0 };
+//! coverage.set_rho_end_nm(100.0).unwrap();
+//! let mut north = GenericPolarWindow { rho_start: 0, rho_end: u16::MAX, theta_start: 0, theta_end: 0 };
+//! north.set_theta_start_deg(270.0).unwrap();
+//! north.set_theta_end_deg(90.0).unwrap();
+//! let filter = StreamFilter::new(25, 13)
+//!     .with_window(coverage)
+//!     .with_type_filter(DataFilterTYP::FilterPSRData, Some(north));
+//! ```
+
+use crate::data_item::{
+    DataSourceIdentifier, GenericPolarWindow, MeasuredPositionInPolarCoordinates,
+};
+use crate::types::{DataFilterTYP, MTYPE, TYP};
+use crate::{geodesy, AsterixMessage, AsterixPacket, Cat34, Cat48};
+
+/// Data type suppressed in a window, from I034/110 and I034/100
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeFilter {
+    pub typ: DataFilterTYP,
+    /// `None` to suppress everywhere
+    pub window: Option<GenericPolarWindow>,
+}
+
+/// Keeps the CAT048 records that a radar was asked to send, see the module documentation
+///
+/// Records without I0xx/010 are assumed to be from the radar.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreamFilter {
+    source: DataSourceIdentifier,
+    window: Option<GenericPolarWindow>,
+    type_filter: Option<TypeFilter>,
+}
+
+impl StreamFilter {
+    /// Keep every record of SAC/SIC
+    pub fn new(sac: u8, sic: u8) -> Self {
+        Self { source: DataSourceIdentifier { sac, sic }, window: None, type_filter: None }
+    }
+
+    /// The record is from another radar
+    fn is_foreign(&self, source: Option<&DataSourceIdentifier>) -> bool {
+        source.is_some_and(|s| *s != self.source)
+    }
+
+    /// Keep only plots inside `window`
+    pub fn with_window(mut self, window: GenericPolarWindow) -> Self {
+        self.window = Some(window);
+        self
+    }
+
+    /// Suppress data of type `typ` inside `window`, or everywhere
+    pub fn with_type_filter(
+        mut self,
+        typ: DataFilterTYP,
+        window: Option<GenericPolarWindow>,
+    ) -> Self {
+        self.type_filter = Some(TypeFilter { typ, window });
+        self
+    }
+
+    /// Window plots are kept in
+    pub fn window(&self) -> Option<&GenericPolarWindow> {
+        self.window.as_ref()
+    }
+
+    /// Data type suppressed
+    pub fn type_filter(&self) -> Option<&TypeFilter> {
+        self.type_filter.as_ref()
+    }
+
+    /// Configure from a service message of the radar
+    ///
+    /// I034/110, with the I034/100 of the same record as its window, replaces the type filter.
+    /// I034/100 without I034/110 replaces the window plots are kept in. An I034/110 of
+    /// `InvalidValue` removes the type filter. Messages of other radars are ignored.
+    pub fn update(&mut self, record: &Cat34) {
+        if self.is_foreign(record.data_source_identifier.as_ref()) {
+            return;
+        }
+        match (&record.data_filter, &record.generic_polar_window) {
+            (Some(filter), window) => {
+                self.type_filter = (filter.typ != DataFilterTYP::InvalidValue)
+                    .then(|| TypeFilter { typ: filter.typ, window: window.clone() });
+            }
+            (None, Some(window)) => {
+                // a geographical filtering message without a data filter only moves the window
+                let filtering = record.message_type.as_ref().map(|t| t.t);
+                if filtering.map_or(true, |t| t == MTYPE::GeographicaFiltering) {
+                    self.window = Some(window.clone());
+                }
+            }
+            (None, None) => {}
+        }
+    }
+
+    /// Keep `record`, removing I048/250 if enhanced surveillance data is suppressed
+    ///
+    /// Records without a position are kept, and records without I048/020 are only checked against
+    /// the windows. Call `Cat48::update_fspec` before encoding a record that was changed.
+    pub fn retain(&self, record: &mut Cat48) -> bool {
+        let position = position(record);
+        if let (Some(window), Some(position)) = (&self.window, &position) {
+            if !contains(window, position) {
+                return false;
+            }
+        }
+
+        let Some(filter) = &self.type_filter else {
+            return true;
+        };
+        let inside = match (&filter.window, &position) {
+            (Some(window), Some(position)) => contains(window, position),
+            (Some(_), None) => false,
+            (None, _) => true,
+        };
+        if !inside {
+            return true;
+        }
+
+        let typ = record.target_report_descriptor.as_ref().map(|d| d.typ);
+        let psr = typ == Some(TYP::SinglePSRDetection);
+        let ssr = matches!(
+            typ,
+            Some(TYP::SingleSSRDetection | TYP::SingleModeSAllCall | TYP::SingleModeSRollCall)
+        );
+        let (suppress, enhanced) = match filter.typ {
+            DataFilterTYP::FilterPSRData => (psr, false),
+            DataFilterTYP::FilterSSRModeSData => (ssr, false),
+            DataFilterTYP::FilterSSRModeSPSRData => {
+                (typ.is_some_and(|t| t != TYP::NoDetection), false)
+            }
+            DataFilterTYP::EnhancedSurveillanceData => (false, true),
+            DataFilterTYP::FilterPSREnhancedSurveillanceData => (psr, true),
+            DataFilterTYP::FilterPSREnhancedSurveillanceSSRModeSDataNotInAreaOfPrimeInterest
+            | DataFilterTYP::FilterPSREnhancedSurveillanceAllSSRModeSData => (psr || ssr, true),
+            // weather and jamming strobes aren't sent in CAT048
+            DataFilterTYP::InvalidValue
+            | DataFilterTYP::FilterWeatherData
+            | DataFilterTYP::FilterJammingStrobe => (false, false),
+        };
+        if suppress {
+            return false;
+        }
+        if enhanced {
+            record.mode_smb_data = None;
+        }
+        true
+    }
+
+    /// Filter the records of `packet` in order, configuring from its CAT034 records
+    ///
+    /// CAT048 records of other radars are dropped. Only the FSPEC of records that lost I048/250 is
+    /// updated, the other records are encoded as they were decoded.
+    pub fn apply(&mut self, packet: &mut AsterixPacket) -> Result<(), deku::DekuError> {
+        let mut messages = core::mem::take(&mut packet.messages);
+        messages.retain_mut(|message| match message {
+            AsterixMessage::Cat34(record) => {
+                self.update(record);
+                true
+            }
+            AsterixMessage::Cat48(record) => {
+                if self.is_foreign(record.data_source_identifier.as_ref()) {
+                    return false;
+                }
+                let enhanced = record.mode_smb_data.is_some();
+                let retain = self.retain(record);
+                if enhanced && record.mode_smb_data.is_none() {
+                    record.update_fspec();
+                }
+                retain
+            }
+            AsterixMessage::Cat4(_) => true,
+        });
+        packet.messages = messages;
+        packet.finalize_lossless()
+    }
+}
+
+/// I048/040, or I048/042 if it's missing
+fn position(record: &Cat48) -> Option<MeasuredPositionInPolarCoordinates> {
+    if let Some(polar) = &record.measured_position_in_polar_coordinates {
+        return Some(polar.clone());
+    }
+    let cartesian = record.calculated_position_cartesian_coor.as_ref()?;
+    let (rho, theta) = geodesy::cartesian_to_polar(cartesian.x_nm(), cartesian.y_nm());
+    let mut polar = MeasuredPositionInPolarCoordinates { rho: 0, theta: 0 };
+    polar.set_rho_nm(rho).ok()?;
+    polar.set_theta_deg_wrapped(theta).ok()?;
+    Some(polar)
+}
+
+/// `position` is inside `window`, which crosses north when theta end is before theta start
+///
+/// I048/040 and I034/100 have the same LSB, so the raw values are compared.
+fn contains(window: &GenericPolarWindow, position: &MeasuredPositionInPolarCoordinates) -> bool {
+    if position.rho < window.rho_start || position.rho > window.rho_end {
+        return false;
+    }
+    let (start, end, theta) = (window.theta_start, window.theta_end, position.theta);
+    match start.cmp(&end) {
+        core::cmp::Ordering::Less => (start..=end).contains(&theta),
+        core::cmp::Ordering::Greater => theta >= start || theta <= end,
+        core::cmp::Ordering::Equal => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_item::{DataFilter, MessageType, ModeSMBData};
+    use crate::test_util::cat48;
+    use deku::{DekuContainerRead, DekuContainerWrite};
+
+    fn window(rho: (f64, f64), theta: (f64, f64)) -> GenericPolarWindow {
+        let mut window =
+            GenericPolarWindow { rho_start: 0, rho_end: 0, theta_start: 0, theta_end: 0 };
+        window.set_rho_start_nm(rho.0).unwrap();
+        window.set_rho_end_nm(rho.1).unwrap();
+        window.set_theta_start_deg(theta.0).unwrap();
+        window.set_theta_end_deg(theta.1).unwrap();
+        window
+    }
+
+    fn plot(typ: TYP, rho: f64, theta: f64) -> Cat48 {
+        cat48().with_type(typ).with_polar(rho, theta)
+    }
+
+    #[test]
+    fn window_across_north() {
+        let filter = StreamFilter::new(25, 13).with_window(window((10.0, 100.0), (350.0, 10.0)));
+        assert!(filter.retain(&mut plot(TYP::SinglePSRDetection, 50.0, 355.0)));
+        assert!(filter.retain(&mut plot(TYP::SinglePSRDetection, 50.0, 5.0)));
+        assert!(!filter.retain(&mut plot(TYP::SinglePSRDetection, 50.0, 180.0)));
+        assert!(!filter.retain(&mut plot(TYP::SinglePSRDetection, 5.0, 0.0)));
+
+        // from I048/042, 50 NM north
+        assert!(filter.retain(&mut cat48().with_cartesian(0.0, 50.0)));
+    }
+
+    #[test]
+    fn data_filter() {
+        let east = window((0.0, 255.0), (45.0, 135.0));
+        let filter =
+            StreamFilter::new(25, 13).with_type_filter(DataFilterTYP::FilterPSRData, Some(east));
+        assert!(!filter.retain(&mut plot(TYP::SinglePSRDetection, 50.0, 90.0)));
+        assert!(filter.retain(&mut plot(TYP::SinglePSRDetection, 50.0, 270.0)));
+        assert!(filter.retain(&mut plot(TYP::SSRPlusPSRDetection, 50.0, 90.0)));
+
+        let filter = StreamFilter::new(25, 13)
+            .with_type_filter(DataFilterTYP::FilterPSREnhancedSurveillanceData, None);
+        let mut record = plot(TYP::SingleModeSRollCall, 50.0, 90.0);
+        record.mode_smb_data = Some(ModeSMBData { count: 0, mb_data: vec![] });
+        assert!(filter.retain(&mut record));
+        assert!(record.mode_smb_data.is_none());
+        assert!(!filter.retain(&mut plot(TYP::SinglePSRDetection, 50.0, 90.0)));
+    }
+
+    #[test]
+    fn from_cat34() {
+        let mut filter = StreamFilter::new(25, 13);
+        let mut record = Cat34 {
+            message_type: Some(MessageType { t: MTYPE::GeographicaFiltering }),
+            generic_polar_window: Some(window((0.0, 100.0), (0.0, 180.0))),
+            ..Cat34::default()
+        };
+        filter.update(&record);
+        assert!(filter.window().is_some());
+        assert!(filter.type_filter().is_none());
+
+        record.data_filter = Some(DataFilter { typ: DataFilterTYP::FilterSSRModeSData });
+        filter.update(&record);
+        assert_eq!(filter.type_filter().unwrap().typ, DataFilterTYP::FilterSSRModeSData);
+
+        let mut packet = AsterixPacket {
+            category: 48,
+            messages: vec![
+                AsterixMessage::Cat48(plot(TYP::SinglePSRDetection, 50.0, 90.0)),
+                AsterixMessage::Cat48(plot(TYP::SingleSSRDetection, 50.0, 90.0)),
+                AsterixMessage::Cat48(plot(TYP::SinglePSRDetection, 50.0, 270.0)),
+                AsterixMessage::Cat48(plot(TYP::SinglePSRDetection, 150.0, 90.0)),
+            ],
+            ..AsterixPacket::default()
+        };
+        filter.apply(&mut packet).unwrap();
+        assert_eq!(packet.messages.len(), 1);
+
+        // not from this radar
+        record.data_filter = Some(DataFilter { typ: DataFilterTYP::InvalidValue });
+        let other = DataSourceIdentifier { sac: 25, sic: 14 };
+        filter.update(&Cat34 { data_source_identifier: Some(other), ..record.clone() });
+        assert!(filter.type_filter().is_some());
+
+        filter.update(&record);
+        assert!(filter.type_filter().is_none());
+    }
+
+    #[test]
+    fn apply_lossless() {
+        let mut filter = StreamFilter::new(25, 13)
+            .with_type_filter(DataFilterTYP::EnhancedSurveillanceData, None);
+        // I048/010 25/13 with a trailing empty FSPEC octet
+        let mut bytes = vec![0x30, 0x00, 0x17, 0b1000_0001, 0x00, 0x19, 0x0d];
+        // I048/010 25/14
+        bytes.extend([0b1000_0000, 0x19, 0x0e]);
+        // I048/010 25/13, I048/250 with BDS 4,0
+        bytes.extend([0b1000_0001, 0b0010_0000, 0x19, 0x0d, 0x01]);
+        bytes.extend([0xc0, 0x78, 0x00, 0x31, 0xbc, 0x00, 0x00, 0x40]);
+        let (_, mut packet) = AsterixPacket::from_bytes((&bytes, 0)).unwrap();
+        filter.apply(&mut packet).unwrap();
+        let expected = [0x30, 0x00, 0x0a, 0b1000_0001, 0x00, 0x19, 0x0d, 0b1000_0000, 0x19, 0x0d];
+        assert_eq!(packet.to_bytes().unwrap(), expected);
+    }
+}
@@ -0,0 +1,81 @@
+use crate::data_item::{
+    AircraftIdentificationAndCharacteristics, AlertIdentifier, AlertStatus,
+    ConflictTimingAndSeparation, DataSourceIdentifier, ReservedExpansionField,
+    SafetyNetMessageType, SdpsIdentifier, SpecialPurposeField, TimeOfDay,
+};
+use crate::fspec::{add_fx, is_fspec, trim_fspec};
+use crate::FSPEC_IDENT;
+use asterix_derive::UpdateFspec;
+use deku::prelude::*;
+
+/// FSPEC bits of the data items `Cat4` defines, per octet
+const DEFINED: [u8; 3] = [0b1111_1101, 0b0101_0001, 0b0010_0110];
+
+/// Every data item of `fspec` is defined by `Cat4`
+fn is_defined(fspec: &[u8]) -> bool {
+    fspec.len() <= DEFINED.len() && fspec.iter().zip(DEFINED).all(|(f, d)| f & !d == 0)
+}
+
+/// Safety Net Messages
+///
+/// Only the data items of alerts between two aircraft, such as STCA, are defined: records with
+/// I004/060, I004/030, I004/120, I004/076, I004/074, I004/075, I004/100, I004/035 or I004/110
+/// fail to decode.
+#[derive(Debug, Default, PartialEq, Clone, DekuRead, DekuWrite, UpdateFspec)]
+#[deku(endian = "big")]
+pub struct Cat4 {
+    #[deku(until = "|b: &u8| *b & FSPEC_IDENT == 0", assert = "is_defined(fspec)")]
+    pub fspec: Vec<u8>,
+    /// FRN 1
+    #[deku(skip, cond = "is_fspec(DataSourceIdentifier::FRN_4, fspec, 0)")]
+    pub data_source_identifier: Option<DataSourceIdentifier>,
+    /// FRN 2
+    #[deku(skip, cond = "is_fspec(SafetyNetMessageType::FRN_4, fspec, 0)")]
+    pub message_type: Option<SafetyNetMessageType>,
+    /// FRN 3
+    #[deku(skip, cond = "is_fspec(SdpsIdentifier::FRN_4, fspec, 0)")]
+    pub sdps_identifier: Option<SdpsIdentifier>,
+    /// FRN 4
+    #[deku(skip, cond = "is_fspec(TimeOfDay::FRN_4, fspec, 0)")]
+    #[deku(ctx = "4")]
+    pub time_of_message: Option<TimeOfDay>,
+    /// FRN 5
+    #[deku(skip, cond = "is_fspec(AlertIdentifier::FRN_4, fspec, 0)")]
+    pub alert_identifier: Option<AlertIdentifier>,
+    /// FRN 6
+    #[deku(skip, cond = "is_fspec(AlertStatus::FRN_4, fspec, 0)")]
+    pub alert_status: Option<AlertStatus>,
+    /// FRN 9
+    #[deku(skip, cond = "is_fspec(AircraftIdentificationAndCharacteristics::FRN_4, fspec, 1)")]
+    pub aircraft_1: Option<AircraftIdentificationAndCharacteristics>,
+    /// FRN 11
+    #[deku(skip, cond = "is_fspec(ConflictTimingAndSeparation::FRN_4, fspec, 1)")]
+    pub conflict_timing_and_separation: Option<ConflictTimingAndSeparation>,
+    /// FRN 17
+    #[deku(
+        skip,
+        cond = "is_fspec(AircraftIdentificationAndCharacteristics::FRN_4_SECOND, fspec, 2)"
+    )]
+    pub aircraft_2: Option<AircraftIdentificationAndCharacteristics>,
+    /// FRN 20
+    #[deku(skip, cond = "is_fspec(ReservedExpansionField::FRN_4, fspec, 2)")]
+    pub reserved_expansion_field: Option<ReservedExpansionField>,
+    /// FRN 21
+    #[deku(skip, cond = "is_fspec(SpecialPurposeField::FRN_4, fspec, 2)")]
+    pub special_purpose_field: Option<SpecialPurposeField>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn undefined_items() {
+        assert!(is_defined(&[0b1111_1101, 0b0101_0001, 0b0010_0110]));
+        // I004/060
+        assert!(!is_defined(&[0b0000_0010]));
+        // I004/030
+        assert!(!is_defined(&[0b0000_0001, 0b1000_0000]));
+        assert!(Cat4::from_bytes((&[0b0000_0011, 0b1000_0000, 0x00, 0x01], 0)).is_err());
+    }
+}
@@ -5,16 +5,20 @@ use crate::data_item::{
     MeasuredPositionInPolarCoordinates, Mode1CodeConfidenceIndicator, Mode1CodeOctalRepresentation,
     Mode2CodeConfidenceIndicator, Mode2CodeOctalRepresentation, Mode3ACodeConfidenceIndicator,
     Mode3ACodeInOctalRepresentation, ModeCCodeAndConfidenceIndicator, ModeSMBData,
-    RadarPlotCharacteristics, RadialDopplerSpeed, TargetReportDescriptor, TimeOfDay, TrackNumber,
-    TrackQuality, TrackStatus, WarningErrorConditionsTargetClass,
+    RadarPlotCharacteristics, RadialDopplerSpeed, ReservedExpansionField, SpecialPurposeField,
+    TargetReportDescriptor, TimeOfDay, TrackNumber, TrackQuality, TrackStatus,
+    WarningErrorConditionsTargetClass,
 };
+use crate::edition::Cat48Edition;
 use crate::fspec::{add_fx, is_fspec, trim_fspec};
+use crate::geodesy;
+use crate::types::{EmergencyCodeChange, Squawk, FX};
 use crate::FSPEC_IDENT;
 use asterix_derive::UpdateFspec;
 use deku::prelude::*;
 
 /// Transmission of Monoradar Target Reports
-#[derive(Debug, Default, PartialEq, DekuRead, DekuWrite, UpdateFspec)]
+#[derive(Debug, Default, PartialEq, Clone, DekuRead, DekuWrite, UpdateFspec)]
 #[deku(endian = "big")]
 pub struct Cat48 {
     #[deku(until = "|b: &u8| *b & FSPEC_IDENT == 0")]
@@ -24,6 +28,7 @@ pub struct Cat48 {
     pub data_source_identifier: Option<DataSourceIdentifier>,
     /// FRN 2
     #[deku(skip, cond = "is_fspec(TimeOfDay::FRN_48, fspec, 0)")]
+    #[deku(ctx = "48")]
     pub time_of_day: Option<TimeOfDay>,
     /// FRN 3
     #[deku(skip, cond = "is_fspec(TargetReportDescriptor::FRN_48, fspec, 0)")]
@@ -97,6 +102,81 @@ pub struct Cat48 {
     /// FRN 26
     #[deku(skip, cond = "is_fspec(Mode2CodeConfidenceIndicator::FRN_48, fspec, 3)")]
     pub mode_2_code_confidence: Option<Mode2CodeConfidenceIndicator>,
-    // FRN 27: Special Purpose Field
-    // FRN 28: Reserved Expansion Field
+    /// FRN 27
+    #[deku(skip, cond = "is_fspec(SpecialPurposeField::FRN_48, fspec, 3)")]
+    pub special_purpose_field: Option<SpecialPurposeField>,
+    /// FRN 28
+    #[deku(skip, cond = "is_fspec(ReservedExpansionField::FRN_48, fspec, 3)")]
+    pub reserved_expansion_field: Option<ReservedExpansionField>,
+    /// Edition the record is checked against, see `Editions`
+    ///
+    /// `None` after `AsterixPacket::from_bytes`, which can't tell the edition of the sender, and
+    /// then the record isn't checked.
+    #[deku(skip)]
+    pub edition: Option<Cat48Edition>,
+}
+
+impl Cat48 {
+    /// Distance in NM between the measured position of I048/040 and the calculated position of
+    /// I048/042, with I048/040 laid flat onto the radar plane by `geodesy::polar_to_cartesian`
+    pub fn polar_cartesian_difference_nm(&self) -> Option<f64> {
+        let polar = self.measured_position_in_polar_coordinates.as_ref()?;
+        let cartesian = self.calculated_position_cartesian_coor.as_ref()?;
+        let (x, y) = geodesy::polar_to_cartesian(polar.rho_nm(), polar.theta_deg());
+        Some((x - cartesian.x_nm()).hypot(y - cartesian.y_nm()))
+    }
+
+    /// The measured polar and calculated Cartesian positions are within `tolerance_nm` of each
+    /// other, `None` if either is missing
+    pub fn is_position_consistent(&self, tolerance_nm: f64) -> Option<bool> {
+        self.polar_cartesian_difference_nm().map(|d| d <= tolerance_nm)
+    }
+
+    /// Check that every data item present is defined by `edition`, if it is set
+    pub fn check_edition(&self) -> Result<(), DekuError> {
+        let Some(edition) = self.edition else {
+            return Ok(());
+        };
+        if !edition.has_target_report_second_extent()
+            && self
+                .target_report_descriptor
+                .as_ref()
+                .is_some_and(|t| t.fx2 == Some(FX::ExtensionIntoFirstExtent))
+        {
+            return Err(DekuError::Assertion(format!(
+                "I048/020 second extent is not defined by CAT048 edition {edition}"
+            )));
+        }
+        if let Some(warnings) = &self.warning_error_con_target_class {
+            if let Some(codefx) = warnings.codefxs.iter().find(|c| !edition.defines(&c.code)) {
+                return Err(DekuError::Assertion(format!(
+                    "I048/030 {:?} is not defined by CAT048 edition {edition}",
+                    codefx.code
+                )));
+            }
+        }
+        if !edition.has_reserved_expansion_field() && self.reserved_expansion_field.is_some() {
+            return Err(DekuError::Assertion(format!(
+                "I048/RE is not defined by CAT048 edition {edition}"
+            )));
+        }
+        Ok(())
+    }
+
+    /// Mode-3/A code of the target, from I048/070
+    pub fn squawk(&self) -> Option<Squawk> {
+        self.mode_3_a_code_in_octal_representation.as_ref().map(|m| m.reply)
+    }
+
+    /// Compare the Mode-3/A code against an earlier report of the same target, returning the
+    /// change if either code is an emergency code (7500, 7600, 7700) and the codes differ
+    pub fn emergency_code_change(&self, previous: &Self) -> Option<EmergencyCodeChange> {
+        let (previous, current) = (previous.squawk(), self.squawk());
+        let emergency = |s: Option<Squawk>| s.is_some_and(|s| s.is_emergency());
+        if previous != current && (emergency(previous) || emergency(current)) {
+            Some(EmergencyCodeChange { previous, current })
+        } else {
+            None
+        }
+    }
 }
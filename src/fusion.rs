@@ -0,0 +1,591 @@
+//! Multi-radar fusion of CAT048 monoradar tracks into system tracks
+//!
+//! `Fusion` converts the tracks of each radar to WGS-84 with the position of the radar from its
+//! `SensorRegistry`, which learns I034/120, and projects them onto a `SystemPlane`. A radar track
+//! is correlated with the system track of the same Mode S address, else with the system track it
+//! updated before, else with the nearest system track in the gate that the radar doesn't already
+//! contribute to. System tracks are smoothed with an alpha-beta filter on the plane.
+//!
+//! `SystemTrack` follows the CAT062 System Track Data items, with the contributing radar tracks
+//! in the manner of I062/340 and I062/290. It is a reference for lab testing and a fallback, not
+//! a replacement for a certified tracker: there is no covariance, and each radar track is
+//! trusted as given. Records need I048/010, I048/161, I048/140 and I048/042 or I048/040, and
+//! must be given in time order per radar.
+
+use deku::DekuError;
+
+use crate::data_item::TimeOfDay;
+use crate::error::{self, Error};
+use crate::geodesy::{self, Enu, SystemPlane, Wgs84, METERS_PER_FT, METERS_PER_NM};
+use crate::modifier::{self, elapsed};
+use crate::sensor::SensorRegistry;
+use crate::types::{Callsign, Squawk};
+use crate::{AsterixMessage, Cat34, Cat48};
+
+/// LSB of I062/070 in seconds
+const TIME_LSB: f64 = 1.0 / 128.0;
+/// LSB of I062/105 in degrees
+const WGS84_LSB: f64 = 180.0 / 33_554_432.0;
+/// LSB of I062/100 in m
+const CARTESIAN_LSB: f64 = 0.5;
+/// LSB of I062/185 in m/s
+const VELOCITY_LSB: f64 = 0.25;
+/// LSB of I062/136 in FL
+const FLIGHT_LEVEL_LSB: f64 = 0.25;
+
+/// Parameters of `Fusion`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FusionConfig {
+    /// Position gain of the alpha-beta filter, and velocity gain for radar tracks with I048/200
+    pub alpha: f64,
+    /// Velocity gain of the alpha-beta filter for radar tracks without I048/200
+    pub beta: f64,
+    /// Largest distance in m between a radar track and the predicted system track position
+    pub gate_m: f64,
+    /// Radar tracks without I048/200 less than this many seconds after the last update of a
+    /// system track don't update its velocity
+    pub min_interval: f64,
+    /// Radar tracks not updated for this many seconds stop contributing, and system tracks
+    /// without contributions are dropped
+    pub max_age: f64,
+}
+
+impl Default for FusionConfig {
+    fn default() -> Self {
+        Self {
+            alpha: 0.5,
+            beta: 0.2,
+            gate_m: 2.0 * METERS_PER_NM,
+            min_interval: 1.0,
+            max_age: 20.0,
+        }
+    }
+}
+
+impl FusionConfig {
+    /// Gains in 0..=1, `min_interval` positive and the other parameters not negative
+    fn validate(&self) -> Result<(), Error> {
+        let gains = [("alpha", self.alpha), ("beta", self.beta)];
+        if let Some((name, value)) = gains.iter().find(|(_, v)| !(0.0..=1.0).contains(v)) {
+            return Err(Error::InvalidParam(format!("fusion: {name} {value} is outside of 0..=1")));
+        }
+        error::positive("fusion", &[("min_interval", self.min_interval)])?;
+        error::non_negative("fusion", &[("gate_m", self.gate_m), ("max_age", self.max_age)])
+    }
+}
+
+/// Radar track contributing to a system track
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SensorTrack {
+    /// System Area Code of the radar
+    pub sac: u8,
+    /// System Identification Code of the radar
+    pub sic: u8,
+    /// I048/161
+    pub track_number: u16,
+    /// I048/140 of the last update, in seconds since midnight
+    pub time: f64,
+}
+
+/// Fused track, following the CAT062 data items
+#[derive(Debug, Clone, PartialEq)]
+pub struct SystemTrack {
+    /// I062/040
+    pub number: u16,
+    /// I062/070, time of the track in seconds since midnight
+    pub time: f64,
+    /// I062/105, with the height of the flight level, or 0
+    pub position: Wgs84,
+    /// I062/100 on the system plane, east in m
+    pub x: f64,
+    /// I062/100 on the system plane, north in m
+    pub y: f64,
+    /// I062/185 east in m/s
+    pub vx: f64,
+    /// I062/185 north in m/s
+    pub vy: f64,
+    /// I062/136
+    pub flight_level: Option<f64>,
+    /// I062/060
+    pub squawk: Option<Squawk>,
+    /// I062/380 Target Address
+    pub address: Option<u32>,
+    /// I062/380 Target Identification
+    pub callsign: Option<Callsign>,
+    /// Radar tracks correlated with the system track
+    pub updates: u32,
+    /// Radar tracks contributing to the system track, one per radar
+    pub sensors: Vec<SensorTrack>,
+    /// Radar of the last update, as in I062/340 SID
+    pub last_sensor: (u8, u8),
+}
+
+impl SystemTrack {
+    /// Position on the plane predicted at `time`
+    pub fn predict(&self, time: f64) -> (f64, f64) {
+        let dt = elapsed(self.time, time);
+        (self.x + self.vx * dt, self.y + self.vy * dt)
+    }
+
+    /// I062/070 as a data item
+    pub fn time_of_day(&self) -> Option<TimeOfDay> {
+        TimeOfDay::from_seconds(self.time).ok()
+    }
+
+    /// I062/070 raw, LSB of 1/128 s
+    pub fn i062_070(&self) -> Result<u32, DekuError> {
+        Ok(modifier::unsigned(self.time, TIME_LSB, 24, "I062/070 time of track")? as u32)
+    }
+
+    /// I062/105 raw latitude and longitude, LSB of 180/2^25 degrees
+    pub fn i062_105(&self) -> Result<(i32, i32), DekuError> {
+        let latitude =
+            modifier::signed(self.position.latitude, WGS84_LSB, 32, "I062/105 latitude")?;
+        let longitude =
+            modifier::signed(self.position.longitude, WGS84_LSB, 32, "I062/105 longitude")?;
+        Ok((latitude as i32, longitude as i32))
+    }
+
+    /// I062/100 raw x and y, LSB of 0.5 m
+    pub fn i062_100(&self) -> Result<(i32, i32), DekuError> {
+        let x = modifier::signed(self.x, CARTESIAN_LSB, 24, "I062/100 x")?;
+        let y = modifier::signed(self.y, CARTESIAN_LSB, 24, "I062/100 y")?;
+        Ok((x as i32, y as i32))
+    }
+
+    /// I062/185 raw vx and vy, LSB of 0.25 m/s
+    pub fn i062_185(&self) -> Result<(i16, i16), DekuError> {
+        let vx = modifier::signed(self.vx, VELOCITY_LSB, 16, "I062/185 vx")?;
+        let vy = modifier::signed(self.vy, VELOCITY_LSB, 16, "I062/185 vy")?;
+        Ok((vx as i16, vy as i16))
+    }
+
+    /// I062/136 raw, LSB of 1/4 FL
+    pub fn i062_136(&self) -> Option<Result<i16, DekuError>> {
+        self.flight_level.map(|fl| {
+            Ok(modifier::signed(fl, FLIGHT_LEVEL_LSB, 16, "I062/136 flight level")? as i16)
+        })
+    }
+
+    /// I062/080 MON, the track is updated by a single radar
+    pub fn is_mono_sensor(&self) -> bool {
+        self.sensors.len() == 1
+    }
+
+    /// Seconds since the last update by radar `sac`, `sic` at `time`, as in I062/290
+    pub fn age(&self, sac: u8, sic: u8, time: f64) -> Option<f64> {
+        self.sensor(sac, sic).map(|s| elapsed(s.time, time))
+    }
+
+    fn sensor(&self, sac: u8, sic: u8) -> Option<&SensorTrack> {
+        self.sensors.iter().find(|s| (s.sac, s.sic) == (sac, sic))
+    }
+}
+
+/// Radar track of a CAT048 record on the system plane
+struct Report {
+    sensor: SensorTrack,
+    x: f64,
+    y: f64,
+    /// Velocity from I048/200
+    velocity: Option<(f64, f64)>,
+    fl: Option<f64>,
+    squawk: Option<Squawk>,
+    address: Option<u32>,
+    callsign: Option<Callsign>,
+}
+
+/// System tracks of several radars, see the module documentation
+#[derive(Debug, Clone)]
+pub struct Fusion {
+    config: FusionConfig,
+    plane: SystemPlane,
+    sensors: SensorRegistry,
+    tracks: Vec<SystemTrack>,
+    next_number: u16,
+}
+
+impl Fusion {
+    /// Errors on a `config` out of the ranges of `FusionConfig`, such as `min_interval` zero,
+    /// which would divide by a zero interval
+    pub fn new(plane: SystemPlane, config: FusionConfig) -> Result<Self, Error> {
+        config.validate()?;
+        Ok(Self { config, plane, sensors: SensorRegistry::new(), tracks: vec![], next_number: 0 })
+    }
+
+    /// Use the positions of the sensors of `sensors`, updated by I034/120
+    pub fn with_sensors(mut self, sensors: SensorRegistry) -> Self {
+        self.sensors = sensors;
+        self
+    }
+
+    /// Sensors with the radar positions
+    pub fn sensors(&self) -> &SensorRegistry {
+        &self.sensors
+    }
+
+    /// System tracks being followed
+    pub fn tracks(&self) -> &[SystemTrack] {
+        &self.tracks
+    }
+
+    /// System track with I062/040 `number`
+    pub fn track(&self, number: u16) -> Option<&SystemTrack> {
+        self.tracks.iter().find(|t| t.number == number)
+    }
+
+    /// Learn a radar position, or correlate a radar track, returning the updated system track
+    pub fn update(&mut self, message: &AsterixMessage) -> Option<SystemTrack> {
+        match message {
+            AsterixMessage::Cat48(record) => self.update_cat48(record),
+            AsterixMessage::Cat34(record) => {
+                self.update_cat34(record);
+                None
+            }
+            AsterixMessage::Cat4(_) => None,
+        }
+    }
+
+    /// Learn the position of the radar from I034/120, see `SensorRegistry::update_cat34`
+    pub fn update_cat34(&mut self, record: &Cat34) {
+        self.sensors.update_cat34(record);
+    }
+
+    /// Correlate a radar track, returning the updated system track
+    ///
+    /// `None` for records of radars without a known position, or without the data items listed
+    /// in the module documentation.
+    pub fn update_cat48(&mut self, record: &Cat48) -> Option<SystemTrack> {
+        let report = self.report(record)?;
+        self.expire(report.sensor.time);
+
+        let index = match self.correlate(&report) {
+            Some(index) => {
+                self.filter(index, &report);
+                index
+            }
+            None => {
+                let number = self.allocate_number();
+                let (vx, vy) = report.velocity.unwrap_or_default();
+                let mut track = SystemTrack {
+                    number,
+                    time: report.sensor.time,
+                    position: Wgs84::default(),
+                    x: report.x,
+                    y: report.y,
+                    vx,
+                    vy,
+                    flight_level: report.fl,
+                    squawk: report.squawk,
+                    address: report.address,
+                    callsign: report.callsign,
+                    updates: 1,
+                    sensors: vec![report.sensor],
+                    last_sensor: (report.sensor.sac, report.sensor.sic),
+                };
+                track.position = self.unproject(&track);
+                self.tracks.push(track);
+                self.tracks.len() - 1
+            }
+        };
+        Some(self.tracks[index].clone())
+    }
+
+    fn report(&self, record: &Cat48) -> Option<Report> {
+        let source = record.data_source_identifier.as_ref()?;
+        let radar = self.sensors.position(source)?;
+        let sensor = SensorTrack {
+            sac: source.sac,
+            sic: source.sic,
+            track_number: record.track_number.as_ref()?.number,
+            time: record.time_of_day.as_ref()?.seconds(),
+        };
+        let height = geodesy::plot_height(record).unwrap_or_default();
+        let position = match (
+            &record.calculated_position_cartesian_coor,
+            &record.measured_position_in_polar_coordinates,
+        ) {
+            (Some(cartesian), _) => {
+                geodesy::cartesian_to_wgs84(&radar, cartesian.x_nm(), cartesian.y_nm(), height)
+            }
+            (None, Some(polar)) => {
+                geodesy::polar_to_wgs84(&radar, polar.rho_nm(), polar.theta_deg(), height)
+            }
+            (None, None) => return None,
+        }
+        .ok()?;
+        let (x, y) = self.plane.project(&position);
+        // the plane velocity of I048/200, from the displacement it gives in one second
+        let velocity = record.calculated_track_velocity.as_ref().map(|velocity| {
+            let speed = velocity.groundspeed_nm_s() * METERS_PER_NM;
+            let (sin, cos) = velocity.heading_deg().to_radians().sin_cos();
+            let ahead = position.offset(&Enu { east: speed * sin, north: speed * cos, up: 0.0 });
+            let (ax, ay) = self.plane.project(&ahead);
+            (ax - x, ay - y)
+        });
+        Some(Report {
+            sensor,
+            x,
+            y,
+            velocity,
+            fl: record.flight_level_in_binary_repre.as_ref().map(|f| f.fl()),
+            squawk: record.squawk(),
+            address: record.aircraft_address.as_ref().map(|a| a.address),
+            callsign: record.aircraft_identification.as_ref().map(|a| a.identification),
+        })
+    }
+
+    /// Drop radar tracks older than `max_age` at `time`, and system tracks left without any
+    fn expire(&mut self, time: f64) {
+        let max_age = self.config.max_age;
+        for track in &mut self.tracks {
+            track.sensors.retain(|s| elapsed(s.time, time) <= max_age);
+        }
+        self.tracks.retain(|t| !t.sensors.is_empty());
+    }
+
+    /// System track for `report`, by Mode S address, its radar track, else the nearest in the gate
+    fn correlate(&mut self, report: &Report) -> Option<usize> {
+        let SensorTrack { sac, sic, track_number, time } = report.sensor;
+        let by_track = self
+            .tracks
+            .iter()
+            .position(|t| t.sensor(sac, sic).is_some_and(|s| s.track_number == track_number));
+
+        if let Some(address) = report.address {
+            if let Some(index) = self.tracks.iter().position(|t| t.address == Some(address)) {
+                // the radar track moves to the system track of its address
+                if let Some(other) = by_track.filter(|other| *other != index) {
+                    self.tracks[other].sensors.retain(|s| (s.sac, s.sic) != (sac, sic));
+                }
+                return Some(index);
+            }
+        }
+
+        if let Some(index) = by_track {
+            let track = &mut self.tracks[index];
+            if report.address.is_none() || track.address.is_none() {
+                return Some(index);
+            }
+            // the radar track now has another aircraft
+            track.sensors.retain(|s| (s.sac, s.sic) != (sac, sic));
+        }
+
+        self.tracks
+            .iter()
+            .enumerate()
+            .filter(|(_, t)| t.sensor(sac, sic).is_none())
+            .filter(|(_, t)| t.address.is_none() || report.address.is_none())
+            .filter(|(_, t)| {
+                t.squawk.is_none() || report.squawk.is_none() || t.squawk == report.squawk
+            })
+            .filter_map(|(index, t)| {
+                let (x, y) = t.predict(time);
+                let distance = (report.x - x).hypot(report.y - y);
+                (distance <= self.config.gate_m).then_some((distance, index))
+            })
+            .min_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(core::cmp::Ordering::Equal))
+            .map(|(_, index)| index)
+    }
+
+    /// Alpha-beta update of a system track with `report`
+    fn filter(&mut self, index: usize, report: &Report) {
+        let FusionConfig { alpha, beta, min_interval, .. } = self.config;
+        let track = &mut self.tracks[index];
+        let dt = elapsed(track.time, report.sensor.time);
+
+        // update at the time of the report, then keep the track at the later time
+        let (x, y) = track.predict(report.sensor.time);
+        let (rx, ry) = (report.x - x, report.y - y);
+        let (x, y) = (x + alpha * rx, y + alpha * ry);
+        match report.velocity {
+            Some((vx, vy)) => {
+                track.vx += alpha * (vx - track.vx);
+                track.vy += alpha * (vy - track.vy);
+            }
+            None if dt >= min_interval => {
+                track.vx += beta * rx / dt;
+                track.vy += beta * ry / dt;
+            }
+            None => {}
+        }
+        let time = if dt > 0.0 { report.sensor.time } else { track.time };
+        let ahead = elapsed(report.sensor.time, time);
+        track.x = x + track.vx * ahead;
+        track.y = y + track.vy * ahead;
+        track.time = time;
+
+        track.flight_level = report.fl.or(track.flight_level);
+        track.squawk = report.squawk.or(track.squawk);
+        track.address = report.address.or(track.address);
+        track.callsign = report.callsign.or(track.callsign);
+        track.updates += 1;
+        track.last_sensor = (report.sensor.sac, report.sensor.sic);
+        match track.sensors.iter_mut().find(|s| (s.sac, s.sic) == track.last_sensor) {
+            Some(sensor) => *sensor = report.sensor,
+            None => track.sensors.push(report.sensor),
+        }
+
+        let position = self.unproject(&self.tracks[index]);
+        self.tracks[index].position = position;
+    }
+
+    fn unproject(&self, track: &SystemTrack) -> Wgs84 {
+        let height = track.flight_level.map_or(0.0, |fl| fl * 100.0 * METERS_PER_FT);
+        self.plane.unproject(track.x, track.y, height)
+    }
+
+    /// Next system track number not in use
+    fn allocate_number(&mut self) -> u16 {
+        loop {
+            let number = self.next_number;
+            self.next_number = number.wrapping_add(1);
+            if self.track(number).is_none() {
+                return number;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_item::{DataSourceIdentifier, ThreeDPositionOfDataSource};
+    use crate::test_util::cat48;
+
+    const NORTH: Wgs84 = Wgs84 { latitude: 50.5, longitude: 8.5, height: 100.0 };
+    const SOUTH: Wgs84 = Wgs84 { latitude: 49.5, longitude: 8.7, height: 300.0 };
+
+    fn fusion() -> Fusion {
+        let mut fusion =
+            Fusion::new(SystemPlane::new(Wgs84::new(50.0, 8.6, 0.0)), FusionConfig::default())
+                .unwrap();
+        for (sic, radar) in [(1, NORTH), (2, SOUTH)] {
+            let mut position = ThreeDPositionOfDataSource {
+                height_of_wgs_84: radar.height as i16,
+                latitude_in_wgs_84: 0,
+                longitude_in_wgs_84: 0,
+            };
+            position.set_latitude_deg(radar.latitude).unwrap();
+            position.set_longitude_deg(radar.longitude).unwrap();
+            fusion.update_cat34(&Cat34 {
+                data_source_identifier: Some(DataSourceIdentifier { sac: 25, sic }),
+                three_d_position_of_data_source: Some(position),
+                ..Cat34::default()
+            });
+        }
+        fusion
+    }
+
+    fn track(sic: u8, number: u16, target: &Wgs84, time: f64) -> Cat48 {
+        let radar = if sic == 2 { SOUTH } else { NORTH };
+        let polar = geodesy::measured_position(&radar, target).unwrap();
+        Cat48 {
+            measured_position_in_polar_coordinates: Some(polar),
+            ..cat48()
+                .with_source(25, sic)
+                .with_time(time)
+                .with_track_number(number)
+                .with_flight_level(target.height / METERS_PER_FT / 100.0)
+        }
+    }
+
+    #[test]
+    fn correlate() {
+        let mut fusion = fusion();
+        let target = Wgs84::new(50.0, 8.6, 3048.0);
+        let other = Wgs84::new(50.3, 8.6, 3048.0);
+        assert!(fusion.update_cat48(&track(3, 1, &target, 10.0)).is_none());
+
+        let first = fusion.update_cat48(&track(1, 10, &target, 10.0)).unwrap();
+        assert!(first.is_mono_sensor());
+        assert!(first.position.enu(&target).north.abs() < 50.0);
+        // in the gate, from another radar
+        let second = fusion.update_cat48(&track(2, 20, &target, 11.0)).unwrap();
+        assert_eq!(second.number, first.number);
+        assert_eq!(second.sensors.len(), 2);
+        assert_eq!(second.last_sensor, (25, 2));
+        assert_eq!(second.age(25, 1, 11.0), Some(1.0));
+        assert!((second.flight_level.unwrap() - 100.0).abs() < 0.25);
+
+        let third = fusion.update_cat48(&track(1, 11, &other, 12.0)).unwrap();
+        assert_ne!(third.number, first.number);
+        assert_eq!(fusion.tracks().len(), 2);
+
+        // by Mode S address, whatever the distance
+        fusion.update_cat48(&track(1, 10, &target, 14.0).with_address(0xabcdef));
+        let far = fusion.update_cat48(&track(2, 21, &other, 15.0).with_address(0xabcdef)).unwrap();
+        assert_eq!(far.number, first.number);
+        assert_eq!(far.sensors.iter().find(|s| s.sic == 2).unwrap().track_number, 21);
+
+        // radar tracks expire, then their system tracks
+        let late = fusion.update_cat48(&track(2, 22, &other, 34.5)).unwrap();
+        assert_ne!(late.number, third.number);
+        assert!(fusion.track(third.number).is_none());
+        assert_eq!(fusion.track(first.number).unwrap().sensors.len(), 1);
+        fusion.update_cat48(&track(2, 22, &other, 36.0));
+        assert_eq!(fusion.tracks().len(), 1);
+    }
+
+    #[test]
+    fn address_first() {
+        let mut fusion = fusion();
+        let target = Wgs84::new(50.0, 8.6, 3048.0);
+        let other = Wgs84::new(50.3, 8.6, 3048.0);
+        let first = fusion.update_cat48(&track(1, 10, &target, 10.0)).unwrap();
+        let second =
+            fusion.update_cat48(&track(2, 20, &other, 10.5).with_address(0xabcdef)).unwrap();
+        assert_ne!(first.number, second.number);
+
+        // the radar track moves to the system track of its address
+        let moved =
+            fusion.update_cat48(&track(1, 10, &target, 12.0).with_address(0xabcdef)).unwrap();
+        assert_eq!(moved.number, second.number);
+        assert_eq!(moved.sensors.len(), 2);
+        assert!(fusion.track(first.number).unwrap().sensors.is_empty());
+    }
+
+    #[test]
+    fn i062() {
+        let mut fusion = fusion();
+        let target = Wgs84::new(50.1, 8.7, 3048.0);
+        let mut track = fusion.update_cat48(&track(1, 10, &target, 10.0)).unwrap();
+        assert_eq!(track.i062_070().unwrap(), 1280);
+        let (latitude, longitude) = track.i062_105().unwrap();
+        assert!((f64::from(latitude) * WGS84_LSB - track.position.latitude).abs() < 1e-5);
+        assert!((f64::from(longitude) * WGS84_LSB - track.position.longitude).abs() < 1e-5);
+        let (x, y) = track.i062_100().unwrap();
+        assert!((f64::from(x) * 0.5 - track.x).abs() <= 0.25);
+        assert!((f64::from(y) * 0.5 - track.y).abs() <= 0.25);
+        assert_eq!(track.i062_185().unwrap(), (0, 0));
+        assert_eq!(track.i062_136().unwrap().unwrap(), 400);
+
+        track.x = 5e6;
+        track.vy = -1e4;
+        track.flight_level = None;
+        assert!(matches!(track.i062_100(), Err(DekuError::InvalidParam(_))));
+        assert!(matches!(track.i062_185(), Err(DekuError::InvalidParam(_))));
+        assert!(track.i062_136().is_none());
+    }
+
+    #[test]
+    fn config() {
+        let plane = SystemPlane::new(Wgs84::new(50.0, 8.6, 0.0));
+        let config = FusionConfig::default();
+        for bad in [
+            FusionConfig { min_interval: 0.0, ..config },
+            FusionConfig { alpha: -0.5, ..config },
+            FusionConfig { beta: f64::NAN, ..config },
+            FusionConfig { gate_m: -1.0, ..config },
+            FusionConfig { max_age: f64::INFINITY, ..config },
+        ] {
+            assert!(matches!(Fusion::new(plane, bad), Err(Error::InvalidParam(_))));
+        }
+        assert_eq!(
+            Fusion::new(plane, FusionConfig { min_interval: -1.0, ..config }).unwrap_err(),
+            Error::InvalidParam(
+                "fusion: min_interval -1 is not a finite positive number".to_string()
+            )
+        );
+    }
+}
@@ -0,0 +1,456 @@
+//! Conversion between radar polar positions and WGS-84
+//!
+//! A plot is measured as slant range and azimuth from the radar. With the height of the target,
+//! from its flight level or 3D height, the elevation is found on a sphere of the local earth
+//! radius at the radar, which corrects for both the slant range and the earth curvature. The
+//! resulting local east/north/up vector is then converted through earth-centred coordinates
+//! onto the WGS-84 ellipsoid.
+//!
+//! Radar Cartesian positions, as in I048/042, are the projection of the slant range onto the
+//! radar plane, the plane tangent to the earth at the radar, so converting them to WGS-84 also
+//! needs the height of the target. `polar_to_cartesian()` and `cartesian_to_polar()` don't know
+//! the height and lay the slant range flat onto the plane, which is close to the projection only
+//! for targets at low elevation. `SystemPlane` is a stereographic projection for a whole system of
+//! radars.
+
+use deku::DekuError;
+
+use crate::data_item::{MeasuredPositionInPolarCoordinates, ThreeDPositionOfDataSource};
+use crate::error::Error;
+use crate::Cat48;
+
+/// Semi-major axis of the WGS-84 ellipsoid in m
+pub const WGS84_A: f64 = 6_378_137.0;
+/// Flattening of the WGS-84 ellipsoid
+pub const WGS84_F: f64 = 1.0 / 298.257_223_563;
+/// First eccentricity squared of the WGS-84 ellipsoid
+pub const WGS84_E2: f64 = WGS84_F * (2.0 - WGS84_F);
+
+/// m per NM
+pub const METERS_PER_NM: f64 = 1852.0;
+/// m per ft
+pub const METERS_PER_FT: f64 = 0.3048;
+
+/// Position on the WGS-84 ellipsoid
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub struct Wgs84 {
+    /// Degrees, positive north
+    pub latitude: f64,
+    /// Degrees, positive east
+    pub longitude: f64,
+    /// Height above the ellipsoid in m
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub height: f64,
+}
+
+impl From<&ThreeDPositionOfDataSource> for Wgs84 {
+    fn from(position: &ThreeDPositionOfDataSource) -> Self {
+        Self {
+            latitude: position.latitude_deg(),
+            longitude: position.longitude_deg(),
+            height: f64::from(position.height_of_wgs_84),
+        }
+    }
+}
+
+/// Earth-centred, earth-fixed position in m
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Ecef {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+/// Local east/north/up offset in m
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Enu {
+    pub east: f64,
+    pub north: f64,
+    pub up: f64,
+}
+
+impl Wgs84 {
+    pub fn new(latitude: f64, longitude: f64, height: f64) -> Self {
+        Self { latitude, longitude, height }
+    }
+
+    /// Radius of curvature in the prime vertical
+    fn prime_vertical_radius(latitude: f64) -> f64 {
+        WGS84_A / (1.0 - WGS84_E2 * latitude.sin().powi(2)).sqrt()
+    }
+
+    /// Gaussian mean radius of curvature, the radius of the sphere that best fits the
+    /// ellipsoid around this position
+    pub fn earth_radius(&self) -> f64 {
+        let latitude = self.latitude.to_radians();
+        let w2 = 1.0 - WGS84_E2 * latitude.sin().powi(2);
+        WGS84_A * (1.0 - WGS84_E2).sqrt() / w2
+    }
+
+    pub fn to_ecef(&self) -> Ecef {
+        let (latitude, longitude) = (self.latitude.to_radians(), self.longitude.to_radians());
+        let n = Self::prime_vertical_radius(latitude);
+        Ecef {
+            x: (n + self.height) * latitude.cos() * longitude.cos(),
+            y: (n + self.height) * latitude.cos() * longitude.sin(),
+            z: (n * (1.0 - WGS84_E2) + self.height) * latitude.sin(),
+        }
+    }
+
+    pub fn from_ecef(ecef: &Ecef) -> Self {
+        let p = ecef.x.hypot(ecef.y);
+        let longitude = ecef.y.atan2(ecef.x);
+        // converges to well below a mm within a few iterations for heights of aircraft
+        let mut latitude = ecef.z.atan2(p * (1.0 - WGS84_E2));
+        let mut height = 0.0;
+        for _ in 0..5 {
+            let n = Self::prime_vertical_radius(latitude);
+            height = p / latitude.cos() - n;
+            latitude = ecef.z.atan2(p * (1.0 - WGS84_E2 * n / (n + height)));
+        }
+        Self { latitude: latitude.to_degrees(), longitude: longitude.to_degrees(), height }
+    }
+
+    /// Position at `enu` from this position
+    pub fn offset(&self, enu: &Enu) -> Self {
+        let (latitude, longitude) = (self.latitude.to_radians(), self.longitude.to_radians());
+        let (sin_lat, cos_lat) = latitude.sin_cos();
+        let (sin_lon, cos_lon) = longitude.sin_cos();
+        let origin = self.to_ecef();
+        let ecef = Ecef {
+            x: origin.x - sin_lon * enu.east - sin_lat * cos_lon * enu.north
+                + cos_lat * cos_lon * enu.up,
+            y: origin.y + cos_lon * enu.east - sin_lat * sin_lon * enu.north
+                + cos_lat * sin_lon * enu.up,
+            z: origin.z + cos_lat * enu.north + sin_lat * enu.up,
+        };
+        Self::from_ecef(&ecef)
+    }
+
+    /// Offset of `other` from this position
+    pub fn enu(&self, other: &Self) -> Enu {
+        let (latitude, longitude) = (self.latitude.to_radians(), self.longitude.to_radians());
+        let (sin_lat, cos_lat) = latitude.sin_cos();
+        let (sin_lon, cos_lon) = longitude.sin_cos();
+        let (origin, target) = (self.to_ecef(), other.to_ecef());
+        let (dx, dy, dz) = (target.x - origin.x, target.y - origin.y, target.z - origin.z);
+        Enu {
+            east: -sin_lon * dx + cos_lon * dy,
+            north: -sin_lat * cos_lon * dx - sin_lat * sin_lon * dy + cos_lat * dz,
+            up: cos_lat * cos_lon * dx + cos_lat * sin_lon * dy + sin_lat * dz,
+        }
+    }
+}
+
+/// Elevation angle in radians of a target at `slant_range` m and `height` m, seen from a radar at
+/// `radar_height` m on a sphere of radius `earth_radius` m
+fn elevation(
+    slant_range: f64,
+    height: f64,
+    radar_height: f64,
+    earth_radius: f64,
+) -> Result<f64, Error> {
+    let (radar, target) = (earth_radius + radar_height, earth_radius + height);
+    let sin = (target.powi(2) - radar.powi(2) - slant_range.powi(2)) / (2.0 * radar * slant_range);
+    if !(-1.0..=1.0).contains(&sin) {
+        return Err(Error::SlantRangeOutOfReach { slant_range, height });
+    }
+    Ok(sin.asin())
+}
+
+/// Position of a target at slant range `rho_nm` and azimuth `theta_deg` from `radar`, at
+/// `height` m above the ellipsoid
+pub fn polar_to_wgs84(
+    radar: &Wgs84,
+    rho_nm: f64,
+    theta_deg: f64,
+    height: f64,
+) -> Result<Wgs84, Error> {
+    let slant_range = rho_nm * METERS_PER_NM;
+    let earth_radius = radar.earth_radius();
+    let (sin_theta, cos_theta) = theta_deg.to_radians().sin_cos();
+    // the sphere drifts from the ellipsoid with range, correct the height on the sphere until
+    // the target is at `height` above the ellipsoid
+    let mut sphere_height = height;
+    let mut target = *radar;
+    for _ in 0..4 {
+        let elevation = elevation(slant_range, sphere_height, radar.height, earth_radius)?;
+        let ground = slant_range * elevation.cos();
+        let enu = Enu {
+            east: ground * sin_theta,
+            north: ground * cos_theta,
+            up: slant_range * elevation.sin(),
+        };
+        target = radar.offset(&enu);
+        let error = height - target.height;
+        if error.abs() < 1e-4 {
+            break;
+        }
+        sphere_height += error;
+    }
+    Ok(target)
+}
+
+/// Slant range in NM and azimuth in degrees of `target` from `radar`
+pub fn wgs84_to_polar(radar: &Wgs84, target: &Wgs84) -> (f64, f64) {
+    let enu = radar.enu(target);
+    let rho = (enu.east.powi(2) + enu.north.powi(2) + enu.up.powi(2)).sqrt();
+    let theta = enu.east.atan2(enu.north).to_degrees().rem_euclid(360.0);
+    (rho / METERS_PER_NM, if theta >= 360.0 { 0.0 } else { theta })
+}
+
+/// I048/040 of a plot of `target`, as measured by `radar`
+pub fn measured_position(
+    radar: &Wgs84,
+    target: &Wgs84,
+) -> Result<MeasuredPositionInPolarCoordinates, DekuError> {
+    let (rho, theta) = wgs84_to_polar(radar, target);
+    let mut position = MeasuredPositionInPolarCoordinates { rho: 0, theta: 0 };
+    position.set_rho_nm(rho)?;
+    position.set_theta_deg_wrapped(theta)?;
+    Ok(position)
+}
+
+/// Height of the target of `record` in m, from I048/110 if present, otherwise from I048/090
+///
+/// The flight level is a pressure altitude, and is used as the height above the ellipsoid
+/// without correcting for the local pressure or geoid
+pub fn plot_height(record: &Cat48) -> Option<f64> {
+    if let Some(height) = &record.height_measured_by_3d_radar {
+        return Some(height.height_ft() * METERS_PER_FT);
+    }
+    record.flight_level_in_binary_repre.as_ref().map(|fl| fl.fl() * 100.0 * METERS_PER_FT)
+}
+
+/// Position of the plot of `record` measured by `radar`, `None` without I048/040 or a height
+pub fn plot_position(record: &Cat48, radar: &Wgs84) -> Option<Result<Wgs84, Error>> {
+    let polar = record.measured_position_in_polar_coordinates.as_ref()?;
+    let height = plot_height(record)?;
+    Some(polar_to_wgs84(radar, polar.rho_nm(), polar.theta_deg(), height))
+}
+
+/// Radar Cartesian position in NM of slant range `rho_nm` and azimuth `theta_deg`, x is east and
+/// y is north, laying the slant range flat onto the radar plane
+pub fn polar_to_cartesian(rho_nm: f64, theta_deg: f64) -> (f64, f64) {
+    let (sin, cos) = theta_deg.to_radians().sin_cos();
+    (rho_nm * sin, rho_nm * cos)
+}
+
+/// Slant range in NM and azimuth in degrees of radar Cartesian position `x_nm`, `y_nm`, taking
+/// the range on the radar plane as the slant range
+pub fn cartesian_to_polar(x_nm: f64, y_nm: f64) -> (f64, f64) {
+    let theta = x_nm.atan2(y_nm).to_degrees().rem_euclid(360.0);
+    (x_nm.hypot(y_nm), if theta >= 360.0 { 0.0 } else { theta })
+}
+
+/// Position of radar Cartesian `x_nm`, `y_nm` from `radar`, at `height` m above the ellipsoid
+///
+/// `x_nm`, `y_nm` is the projection onto the radar plane, the target is above or below it on a
+/// sphere of the local earth radius at the radar.
+pub fn cartesian_to_wgs84(
+    radar: &Wgs84,
+    x_nm: f64,
+    y_nm: f64,
+    height: f64,
+) -> Result<Wgs84, Error> {
+    let (east, north) = (x_nm * METERS_PER_NM, y_nm * METERS_PER_NM);
+    let range = east.hypot(north);
+    let earth_radius = radar.earth_radius();
+    // as in `polar_to_wgs84`, correct the height on the sphere for the ellipsoid
+    let mut sphere_height = height;
+    let mut target = *radar;
+    for _ in 0..4 {
+        let target_radius = earth_radius + sphere_height;
+        if range > target_radius {
+            return Err(Error::SlantRangeOutOfReach { slant_range: range, height: sphere_height });
+        }
+        let up = (target_radius.powi(2) - range.powi(2)).sqrt() - earth_radius - radar.height;
+        target = radar.offset(&Enu { east, north, up });
+        let error = height - target.height;
+        if error.abs() < 1e-4 {
+            break;
+        }
+        sphere_height += error;
+    }
+    Ok(target)
+}
+
+/// Radar Cartesian position in NM of `target` from `radar`, its projection onto the radar plane
+pub fn wgs84_to_cartesian(radar: &Wgs84, target: &Wgs84) -> (f64, f64) {
+    let enu = radar.enu(target);
+    (enu.east / METERS_PER_NM, enu.north / METERS_PER_NM)
+}
+
+/// Stereographic projection onto the plane tangent to the earth at `origin`
+///
+/// The projection is made from the sphere of the Gaussian radius at `origin`, which is conformal
+/// and accurate to a few metres over the area covered by a system of radars. x is east and y is
+/// north, in m.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub struct SystemPlane {
+    origin: Wgs84,
+    radius: f64,
+}
+
+impl SystemPlane {
+    pub fn new(origin: Wgs84) -> Self {
+        Self { origin, radius: origin.earth_radius() }
+    }
+
+    pub fn origin(&self) -> Wgs84 {
+        self.origin
+    }
+
+    /// Position of `position` on the plane in m, the height is not projected
+    pub fn project(&self, position: &Wgs84) -> (f64, f64) {
+        let (sin_lat0, cos_lat0) = self.origin.latitude.to_radians().sin_cos();
+        let (sin_lat, cos_lat) = position.latitude.to_radians().sin_cos();
+        let (sin_dlon, cos_dlon) =
+            (position.longitude - self.origin.longitude).to_radians().sin_cos();
+        let k = 2.0 * self.radius / (1.0 + sin_lat0 * sin_lat + cos_lat0 * cos_lat * cos_dlon);
+        (k * cos_lat * sin_dlon, k * (cos_lat0 * sin_lat - sin_lat0 * cos_lat * cos_dlon))
+    }
+
+    /// Position of `x`, `y` m on the plane, at `height` m above the ellipsoid
+    pub fn unproject(&self, x: f64, y: f64, height: f64) -> Wgs84 {
+        let (sin_lat0, cos_lat0) = self.origin.latitude.to_radians().sin_cos();
+        let rho = x.hypot(y);
+        if rho == 0.0 {
+            return Wgs84 { height, ..self.origin };
+        }
+        let (sin_c, cos_c) = (2.0 * (rho / (2.0 * self.radius)).atan()).sin_cos();
+        let latitude = (cos_c * sin_lat0 + y * sin_c * cos_lat0 / rho).asin();
+        let dlon = (x * sin_c).atan2(rho * cos_lat0 * cos_c - y * sin_lat0 * sin_c);
+        Wgs84 {
+            latitude: latitude.to_degrees(),
+            longitude: (self.origin.longitude + dlon.to_degrees() + 180.0).rem_euclid(360.0)
+                - 180.0,
+            height,
+        }
+    }
+
+    /// Position on the plane in m of a plot at `rho_nm`, `theta_deg` from `radar`, at `height` m
+    /// above the ellipsoid
+    pub fn project_polar(
+        &self,
+        radar: &Wgs84,
+        rho_nm: f64,
+        theta_deg: f64,
+        height: f64,
+    ) -> Result<(f64, f64), Error> {
+        Ok(self.project(&polar_to_wgs84(radar, rho_nm, theta_deg, height)?))
+    }
+
+    /// Slant range in NM and azimuth in degrees from `radar` of `x`, `y` m on the plane, at
+    /// `height` m above the ellipsoid
+    pub fn polar(&self, radar: &Wgs84, x: f64, y: f64, height: f64) -> (f64, f64) {
+        wgs84_to_polar(radar, &self.unproject(x, y, height))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RADAR: Wgs84 = Wgs84 { latitude: 50.0379, longitude: 8.5622, height: 120.0 };
+
+    #[test]
+    fn ecef_round_trip() {
+        for position in [RADAR, Wgs84::new(-33.9, 151.2, 11_000.0), Wgs84::new(89.9, -179.9, -50.0)]
+        {
+            let back = Wgs84::from_ecef(&position.to_ecef());
+            assert!((back.latitude - position.latitude).abs() < 1e-9);
+            assert!((back.longitude - position.longitude).abs() < 1e-9);
+            assert!((back.height - position.height).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn polar_round_trip() {
+        for (rho, theta, height) in
+            [(1.0, 0.0, 300.0), (100.0, 90.0, 10_668.0), (250.0, 225.5, 12_000.0)]
+        {
+            let target = polar_to_wgs84(&RADAR, rho, theta, height).unwrap();
+            assert!((target.height - height).abs() < 1e-3);
+            let (back_rho, back_theta) = wgs84_to_polar(&RADAR, &target);
+            assert!((back_rho - rho).abs() < 1e-6);
+            let theta_error = (back_theta - theta + 180.0).rem_euclid(360.0) - 180.0;
+            assert!(theta_error.abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn earth_curvature() {
+        // at 200 NM the earth drops away, so the target at radar height is below the horizon
+        // and its ground distance is shorter than the slant range
+        let target = polar_to_wgs84(&RADAR, 200.0, 0.0, RADAR.height).unwrap();
+        let north = RADAR.enu(&target).north;
+        assert!(north < 200.0 * METERS_PER_NM);
+        assert!(north > 199.0 * METERS_PER_NM);
+        // about 111.2 km per degree of latitude
+        assert!((target.latitude - RADAR.latitude - 3.33).abs() < 0.01);
+
+        let err = polar_to_wgs84(&RADAR, 1.0, 0.0, 10_000.0).unwrap_err();
+        assert!(matches!(err, Error::SlantRangeOutOfReach { height, .. } if height > 9_000.0));
+    }
+
+    #[test]
+    fn cartesian() {
+        let (x, y) = polar_to_cartesian(10.0, 225.0);
+        assert!((x + 7.071_067_811_865).abs() < 1e-9 && (y + 7.071_067_811_865).abs() < 1e-9);
+        let (rho, theta) = cartesian_to_polar(x, y);
+        assert!((rho - 10.0).abs() < 1e-12 && (theta - 225.0).abs() < 1e-9);
+
+        let target = cartesian_to_wgs84(&RADAR, x, y, 3_000.0).unwrap();
+        assert!((target.height - 3_000.0).abs() < 1e-3);
+        let (back_x, back_y) = wgs84_to_cartesian(&RADAR, &target);
+        assert!((back_x - x).abs() < 1e-6 && (back_y - y).abs() < 1e-6);
+    }
+
+    #[test]
+    fn high_elevation() {
+        // 1 NM east at FL350, about 80° above the radar
+        let target = cartesian_to_wgs84(&RADAR, 1.0, 0.0, 10_668.0).unwrap();
+        let enu = RADAR.enu(&target);
+        assert!((enu.east - METERS_PER_NM).abs() < 1e-3 && enu.north.abs() < 1e-3);
+        let (x, y) = wgs84_to_cartesian(&RADAR, &target);
+        assert!((x - 1.0).abs() < 1e-9 && y.abs() < 1e-9);
+
+        // the slant range is much longer than the range on the radar plane
+        let (rho, theta) = wgs84_to_polar(&RADAR, &target);
+        assert!((rho - 5.78).abs() < 0.01 && (theta - 90.0).abs() < 1e-6);
+        let polar = polar_to_wgs84(&RADAR, rho, theta, 10_668.0).unwrap();
+        assert!((RADAR.enu(&polar).east - enu.east).abs() < 1e-3);
+        // laid flat, the slant range lands far off
+        assert!((polar_to_cartesian(rho, theta).0 - x) > 4.5);
+    }
+
+    #[test]
+    fn system_plane() {
+        let plane = SystemPlane::new(Wgs84::new(50.0, 8.0, 0.0));
+        assert_eq!(plane.project(&plane.origin()), (0.0, 0.0));
+        for position in [RADAR, Wgs84::new(52.5, 13.4, 34.0), Wgs84::new(47.4, 5.1, 0.0)] {
+            let (x, y) = plane.project(&position);
+            let back = plane.unproject(x, y, position.height);
+            assert!((back.latitude - position.latitude).abs() < 1e-9);
+            assert!((back.longitude - position.longitude).abs() < 1e-9);
+        }
+        // one degree north of the origin is about 111.2 km
+        let (x, y) = plane.project(&Wgs84::new(51.0, 8.0, 0.0));
+        assert!(x.abs() < 1e-6 && (y - 111_200.0).abs() < 300.0);
+
+        let (x, y) = plane.project_polar(&RADAR, 80.0, 45.0, 10_000.0).unwrap();
+        let (rho, theta) = plane.polar(&RADAR, x, y, 10_000.0);
+        assert!((rho - 80.0).abs() < 1e-6 && (theta - 45.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn synthesise_plot() {
+        let theta = f64::from(54_660) * 360.0 / 65536.0;
+        let target = polar_to_wgs84(&RADAR, 120.5, theta, 9_000.0).unwrap();
+        let position = measured_position(&RADAR, &target).unwrap();
+        assert_eq!((position.rho, position.theta), (30_848, 54_660));
+    }
+}
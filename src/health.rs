@@ -0,0 +1,533 @@
+//! Radar health from I034/050 System Configuration and Status and I034/060 System Processing Mode
+//!
+//! `HealthMonitor` keeps the last known status of each radar and reports what changed as
+//! `HealthEvent`s. The first status of a radar is taken as the baseline and doesn't raise events.
+//!
+//! I034/050 doesn't report test target failures, so these are detected from CAT048 test target
+//! and field monitor reports: once a radar has sent one, a revolution without one, counted
+//! between North Marker messages, is a failure.
+
+use std::collections::HashMap;
+
+use crate::data_item::{
+    ComSubField, DataSourceIdentifier, MdsSubField, Sensor, SystemProcessingMode, TimeOfDay,
+};
+use crate::types::{ANT, CHAB, MSC, MTYPE, NOGO, OVL, RAB, RDPC, TST, TSV};
+use crate::{AsterixMessage, Cat34, Cat48};
+
+/// Sensor or processing part of the radar, the subfields of I034/050 and I034/060
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Subsystem {
+    /// Common part
+    Com,
+    /// Primary surveillance radar
+    Psr,
+    /// Secondary surveillance radar
+    Ssr,
+    /// Mode S
+    Mds,
+}
+
+/// Function reporting an overload in I034/050
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OverloadSource {
+    /// COM OVL RDP, radar data processor
+    Rdp,
+    /// COM OVL XMT, transmission subsystem
+    Transmission,
+    /// PSR OVL
+    Psr,
+    /// SSR OVL
+    Ssr,
+    /// MDS OVL SUR, Mode S surveillance
+    ModeSSurveillance,
+    /// MDS OVL SCF, Surveillance Co-ordination Function
+    ModeSScf,
+    /// MDS OVL DLF, Data Link Function
+    ModeSDlf,
+}
+
+impl OverloadSource {
+    /// Subfield of I034/050 reporting the overload
+    pub fn subsystem(&self) -> Subsystem {
+        match self {
+            Self::Rdp | Self::Transmission => Subsystem::Com,
+            Self::Psr => Subsystem::Psr,
+            Self::Ssr => Subsystem::Ssr,
+            Self::ModeSSurveillance | Self::ModeSScf | Self::ModeSDlf => Subsystem::Mds,
+        }
+    }
+}
+
+/// Change of the health of a radar
+#[derive(Debug, Clone, PartialEq)]
+pub enum HealthChange {
+    /// A sensor switched between channel A, channel B and diversity
+    ChannelSwitchover { subsystem: Subsystem, from: CHAB, to: CHAB },
+    /// A sensor switched antenna
+    AntennaSwitchover { subsystem: Subsystem, from: ANT, to: ANT },
+    /// The radar data processor chain in use changed
+    ProcessorSwitchover { from: RDPC, to: RDPC },
+    /// The system was released for or inhibited from operational use, COM NOGO
+    OperationalStatusChange { from: NOGO, to: NOGO },
+    /// The monitoring system of a subsystem was connected or disconnected
+    MonitoringStatusChange { subsystem: Subsystem, from: MSC, to: MSC },
+    /// The time source became valid or invalid, COM TSV
+    TimeSourceChange { from: TSV, to: TSV },
+    /// An overload started or ended
+    Overload { source: OverloadSource, overloaded: bool },
+    /// No channel of a sensor is selected
+    SensorChainLost { subsystem: Subsystem },
+    /// A channel of a sensor is selected again
+    SensorChainRestored { subsystem: Subsystem },
+    /// A revolution ended without a test target or field monitor report
+    TestTargetFailure,
+    /// A test target or field monitor report was received after a failure
+    TestTargetRestored,
+    /// I034/060 changed
+    ProcessingModeChange { from: SystemProcessingMode, to: SystemProcessingMode },
+}
+
+/// Change of the health of a radar, with the record that reported it
+#[derive(Debug, Clone, PartialEq)]
+pub struct HealthEvent {
+    pub source: DataSourceIdentifier,
+    /// I034/030 of the record, I048/140 for test target events
+    pub time_of_day: Option<TimeOfDay>,
+    pub change: HealthChange,
+}
+
+/// Last known status of a radar
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RadarHealth {
+    pub com: Option<ComSubField>,
+    pub psr: Option<Sensor>,
+    pub ssr: Option<Sensor>,
+    pub mds: Option<MdsSubField>,
+    pub processing_mode: Option<SystemProcessingMode>,
+    /// A test target or field monitor report has been received
+    pub has_test_target: bool,
+    pub test_target_failed: bool,
+    test_target_in_scan: bool,
+}
+
+impl RadarHealth {
+    /// Overloads currently reported
+    pub fn overloads(&self) -> Vec<OverloadSource> {
+        let overload = |ovl: &OVL| *ovl == OVL::Overload;
+        let mut overloads = vec![];
+        if let Some(com) = &self.com {
+            if overload(&com.ovl_rdp) {
+                overloads.push(OverloadSource::Rdp);
+            }
+            if overload(&com.olv_xmt) {
+                overloads.push(OverloadSource::Transmission);
+            }
+        }
+        if self.psr.as_ref().is_some_and(|s| overload(&s.ovl)) {
+            overloads.push(OverloadSource::Psr);
+        }
+        if self.ssr.as_ref().is_some_and(|s| overload(&s.ovl)) {
+            overloads.push(OverloadSource::Ssr);
+        }
+        if let Some(mds) = &self.mds {
+            for (ovl, source) in [
+                (&mds.ovl_sur, OverloadSource::ModeSSurveillance),
+                (&mds.ovl_scf, OverloadSource::ModeSScf),
+                (&mds.ovl_dlf, OverloadSource::ModeSDlf),
+            ] {
+                if overload(ovl) {
+                    overloads.push(source);
+                }
+            }
+        }
+        overloads
+    }
+
+    /// Sensors with no channel selected
+    pub fn lost_chains(&self) -> Vec<Subsystem> {
+        let lost = |chab: Option<&CHAB>| chab == Some(&CHAB::NoChannelSelected);
+        let mut chains = vec![];
+        if lost(self.psr.as_ref().map(|s| &s.chab)) {
+            chains.push(Subsystem::Psr);
+        }
+        if lost(self.ssr.as_ref().map(|s| &s.chab)) {
+            chains.push(Subsystem::Ssr);
+        }
+        if lost(self.mds.as_ref().map(|s| &s.chab)) {
+            chains.push(Subsystem::Mds);
+        }
+        chains
+    }
+
+    fn knows(&self, subsystem: Subsystem) -> bool {
+        match subsystem {
+            Subsystem::Com => self.com.is_some(),
+            Subsystem::Psr => self.psr.is_some(),
+            Subsystem::Ssr => self.ssr.is_some(),
+            Subsystem::Mds => self.mds.is_some(),
+        }
+    }
+
+    fn update(&mut self, record: &Cat34) -> Vec<HealthChange> {
+        let mut changes = vec![];
+        let before = self.overloads();
+
+        if let Some(status) = &record.system_configuration_and_status {
+            if let (Some(from), Some(to)) = (&self.com, &status.com) {
+                if from.nogo != to.nogo {
+                    changes.push(HealthChange::OperationalStatusChange {
+                        from: from.nogo,
+                        to: to.nogo,
+                    });
+                }
+                if from.rdpc != to.rdpc {
+                    changes
+                        .push(HealthChange::ProcessorSwitchover { from: from.rdpc, to: to.rdpc });
+                }
+                monitoring(Subsystem::Com, from.msc, to.msc, &mut changes);
+                if from.tsv != to.tsv {
+                    changes.push(HealthChange::TimeSourceChange { from: from.tsv, to: to.tsv });
+                }
+            }
+            if let (Some(from), Some(to)) = (&self.psr, &status.psr) {
+                chain(Subsystem::Psr, (from.ant, from.chab), (to.ant, to.chab), &mut changes);
+                monitoring(Subsystem::Psr, from.msc, to.msc, &mut changes);
+            }
+            if let (Some(from), Some(to)) = (&self.ssr, &status.ssr) {
+                chain(Subsystem::Ssr, (from.ant, from.chab), (to.ant, to.chab), &mut changes);
+                monitoring(Subsystem::Ssr, from.msc, to.msc, &mut changes);
+            }
+            if let (Some(from), Some(to)) = (&self.mds, &status.mds) {
+                chain(Subsystem::Mds, (from.ant, from.chab), (to.ant, to.chab), &mut changes);
+                monitoring(Subsystem::Mds, from.msc, to.msc, &mut changes);
+            }
+
+            // an overload already present in the first status of a subsystem is the baseline
+            let known: Vec<Subsystem> =
+                [Subsystem::Com, Subsystem::Psr, Subsystem::Ssr, Subsystem::Mds]
+                    .into_iter()
+                    .filter(|s| self.knows(*s))
+                    .collect();
+            self.com = status.com.clone().or(self.com.take());
+            self.psr = status.psr.clone().or(self.psr.take());
+            self.ssr = status.ssr.clone().or(self.ssr.take());
+            self.mds = status.mds.clone().or(self.mds.take());
+            let after = self.overloads();
+            for source in after.iter().filter(|s| !before.contains(s)) {
+                if known.contains(&source.subsystem()) {
+                    changes.push(HealthChange::Overload { source: *source, overloaded: true });
+                }
+            }
+            for source in before.iter().filter(|s| !after.contains(s)) {
+                changes.push(HealthChange::Overload { source: *source, overloaded: false });
+            }
+        }
+
+        if let Some(mode) = &record.system_processing_mode {
+            if let Some(from) = &self.processing_mode {
+                if from != mode {
+                    changes.push(HealthChange::ProcessingModeChange {
+                        from: from.clone(),
+                        to: mode.clone(),
+                    });
+                }
+            }
+            self.processing_mode = Some(mode.clone());
+        }
+
+        if record.message_type.as_ref().map(|t| t.t) == Some(MTYPE::NorthMarker) {
+            if self.has_test_target && !self.test_target_in_scan && !self.test_target_failed {
+                self.test_target_failed = true;
+                changes.push(HealthChange::TestTargetFailure);
+            }
+            self.test_target_in_scan = false;
+        }
+        changes
+    }
+
+    fn update_plot(&mut self, record: &Cat48) -> Option<HealthChange> {
+        let descriptor = record.target_report_descriptor.as_ref()?;
+        let test = descriptor.tst == Some(TST::TestTargetReport)
+            || descriptor.rab == RAB::ReportFromFieldMonitor;
+        if !test {
+            return None;
+        }
+        self.has_test_target = true;
+        self.test_target_in_scan = true;
+        if self.test_target_failed {
+            self.test_target_failed = false;
+            return Some(HealthChange::TestTargetRestored);
+        }
+        None
+    }
+}
+
+/// Antenna and channel changes of a sensor
+fn chain(
+    subsystem: Subsystem,
+    (from_ant, from_chab): (ANT, CHAB),
+    (to_ant, to_chab): (ANT, CHAB),
+    changes: &mut Vec<HealthChange>,
+) {
+    if from_ant != to_ant {
+        changes.push(HealthChange::AntennaSwitchover { subsystem, from: from_ant, to: to_ant });
+    }
+    match (from_chab, to_chab) {
+        (from, to) if from == to => {}
+        (_, CHAB::NoChannelSelected) => changes.push(HealthChange::SensorChainLost { subsystem }),
+        (CHAB::NoChannelSelected, _) => {
+            changes.push(HealthChange::SensorChainRestored { subsystem })
+        }
+        (from, to) => changes.push(HealthChange::ChannelSwitchover { subsystem, from, to }),
+    }
+}
+
+/// Monitoring system change of a subsystem
+fn monitoring(subsystem: Subsystem, from: MSC, to: MSC, changes: &mut Vec<HealthChange>) {
+    if from != to {
+        changes.push(HealthChange::MonitoringStatusChange { subsystem, from, to });
+    }
+}
+
+/// Health of every radar, keyed by SAC/SIC
+#[derive(Debug, Clone, Default)]
+pub struct HealthMonitor {
+    radars: HashMap<(u8, u8), RadarHealth>,
+}
+
+impl HealthMonitor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Last known status of `source`
+    pub fn get(&self, source: &DataSourceIdentifier) -> Option<&RadarHealth> {
+        self.radars.get(&(source.sac, source.sic))
+    }
+
+    /// Update with a record, returning the changes it reports
+    ///
+    /// Records without I0xx/010 are ignored.
+    pub fn update(&mut self, message: &AsterixMessage) -> Vec<HealthEvent> {
+        match message {
+            AsterixMessage::Cat34(record) => self.update_cat34(record),
+            AsterixMessage::Cat48(record) => self.update_cat48(record).into_iter().collect(),
+            AsterixMessage::Cat4(_) => vec![],
+        }
+    }
+
+    /// Update with a CAT034 record, returning the changes it reports
+    pub fn update_cat34(&mut self, record: &Cat34) -> Vec<HealthEvent> {
+        let Some(source) = &record.data_source_identifier else {
+            return vec![];
+        };
+        let radar = self.radars.entry((source.sac, source.sic)).or_default();
+        radar
+            .update(record)
+            .into_iter()
+            .map(|change| HealthEvent {
+                source: source.clone(),
+                time_of_day: record.time_of_day.clone(),
+                change,
+            })
+            .collect()
+    }
+
+    /// Update with a CAT048 record, returning a test target event
+    pub fn update_cat48(&mut self, record: &Cat48) -> Option<HealthEvent> {
+        let source = record.data_source_identifier.as_ref()?;
+        let radar = self.radars.entry((source.sac, source.sic)).or_default();
+        let change = radar.update_plot(record)?;
+        Some(HealthEvent {
+            source: source.clone(),
+            time_of_day: record.time_of_day.clone(),
+            change,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_item::{SsrSubField, SystemConfigurationAndStatus};
+    use crate::test_util::{cat34, SOURCE};
+    use crate::types::{FX, MSC, NOGO, RDPR, RED, TSV};
+    use crate::AsterixPacket;
+    use deku::DekuContainerRead;
+
+    fn status(rdpc: RDPC, ssr: Option<(CHAB, OVL)>) -> Cat34 {
+        Cat34 {
+            data_source_identifier: Some(SOURCE),
+            system_configuration_and_status: Some(SystemConfigurationAndStatus {
+                com_bit: 1,
+                spare_bit0: 0,
+                psr_bit: 0,
+                ssr_bit: u8::from(ssr.is_some()),
+                mds_bit: 0,
+                spare_bit1: 0,
+                fx_bit: FX::EndOfDataItem,
+                com: Some(ComSubField {
+                    nogo: NOGO::SystemIsReleasedForOperationalUse,
+                    rdpc,
+                    rdpr: RDPR::DefaultSituation,
+                    ovl_rdp: OVL::NoOverload,
+                    olv_xmt: OVL::NoOverload,
+                    msc: MSC::MonitoringSystemConnected,
+                    tsv: TSV::Valid,
+                    spare: 0,
+                }),
+                psr: None,
+                ssr: ssr.map(|(chab, ovl)| Sensor {
+                    ant: ANT::Antenna1,
+                    chab,
+                    ovl,
+                    msc: MSC::MonitoringSystemConnected,
+                    spare: 0,
+                }),
+                mds: None,
+            }),
+            ..Cat34::default()
+        }
+    }
+
+    fn changes(monitor: &mut HealthMonitor, record: Cat34) -> Vec<HealthChange> {
+        monitor.update_cat34(&record).into_iter().map(|e| e.change).collect()
+    }
+
+    #[test]
+    fn status_changes() {
+        let mut monitor = HealthMonitor::new();
+        let a = Some((CHAB::ChannelAOnlySelected, OVL::NoOverload));
+        assert!(changes(&mut monitor, status(RDPC::RDPC1Selected, a)).is_empty());
+        // absent subfields keep their last known state
+        assert!(changes(&mut monitor, status(RDPC::RDPC1Selected, None)).is_empty());
+
+        let b = Some((CHAB::ChannelBOnlySelected, OVL::Overload));
+        assert_eq!(
+            changes(&mut monitor, status(RDPC::RDPC2Selected, b)),
+            [
+                HealthChange::ProcessorSwitchover {
+                    from: RDPC::RDPC1Selected,
+                    to: RDPC::RDPC2Selected
+                },
+                HealthChange::ChannelSwitchover {
+                    subsystem: Subsystem::Ssr,
+                    from: CHAB::ChannelAOnlySelected,
+                    to: CHAB::ChannelBOnlySelected
+                },
+                HealthChange::Overload { source: OverloadSource::Ssr, overloaded: true },
+            ]
+        );
+        assert_eq!(monitor.get(&SOURCE).unwrap().overloads(), [OverloadSource::Ssr]);
+
+        let lost = Some((CHAB::NoChannelSelected, OVL::NoOverload));
+        assert_eq!(
+            changes(&mut monitor, status(RDPC::RDPC2Selected, lost)),
+            [
+                HealthChange::SensorChainLost { subsystem: Subsystem::Ssr },
+                HealthChange::Overload { source: OverloadSource::Ssr, overloaded: false },
+            ]
+        );
+        assert_eq!(monitor.get(&SOURCE).unwrap().lost_chains(), [Subsystem::Ssr]);
+        assert_eq!(
+            changes(
+                &mut monitor,
+                status(RDPC::RDPC2Selected, b.map(|(c, _)| (c, OVL::NoOverload)))
+            ),
+            [HealthChange::SensorChainRestored { subsystem: Subsystem::Ssr }]
+        );
+    }
+
+    #[test]
+    fn com_status_changes() {
+        let mut monitor = HealthMonitor::new();
+        let a = Some((CHAB::ChannelAOnlySelected, OVL::NoOverload));
+        assert!(changes(&mut monitor, status(RDPC::RDPC1Selected, a)).is_empty());
+
+        let mut record = status(RDPC::RDPC1Selected, a);
+        let config = record.system_configuration_and_status.as_mut().unwrap();
+        let com = config.com.as_mut().unwrap();
+        com.nogo = NOGO::OperationalUseOfSystemIsInhibited;
+        com.msc = MSC::MonitoringSystemDisconnected;
+        com.tsv = TSV::Invalid;
+        config.ssr.as_mut().unwrap().msc = MSC::MonitoringSystemDisconnected;
+        assert_eq!(
+            changes(&mut monitor, record),
+            [
+                HealthChange::OperationalStatusChange {
+                    from: NOGO::SystemIsReleasedForOperationalUse,
+                    to: NOGO::OperationalUseOfSystemIsInhibited
+                },
+                HealthChange::MonitoringStatusChange {
+                    subsystem: Subsystem::Com,
+                    from: MSC::MonitoringSystemConnected,
+                    to: MSC::MonitoringSystemDisconnected
+                },
+                HealthChange::TimeSourceChange { from: TSV::Valid, to: TSV::Invalid },
+                HealthChange::MonitoringStatusChange {
+                    subsystem: Subsystem::Ssr,
+                    from: MSC::MonitoringSystemConnected,
+                    to: MSC::MonitoringSystemDisconnected
+                },
+            ]
+        );
+        assert_eq!(changes(&mut monitor, status(RDPC::RDPC1Selected, a)).len(), 4);
+    }
+
+    #[test]
+    fn processing_mode() {
+        let mode = |red_rad| SystemProcessingMode {
+            com_bit: 0,
+            spare_bit0: 0,
+            psr_bit: 0,
+            ssr_bit: 1,
+            mds_bit: 0,
+            spare_bit1: 0,
+            fx_bit: FX::EndOfDataItem,
+            com: None,
+            psr: None,
+            ssr: Some(SsrSubField { red_rad, spare: 0 }),
+            mds: None,
+        };
+        let record = |red_rad| Cat34 {
+            data_source_identifier: Some(SOURCE),
+            system_processing_mode: Some(mode(red_rad)),
+            ..Cat34::default()
+        };
+        let mut monitor = HealthMonitor::new();
+        assert!(changes(&mut monitor, record(RED::NoReductionActive)).is_empty());
+        assert!(changes(&mut monitor, record(RED::NoReductionActive)).is_empty());
+        assert_eq!(
+            changes(&mut monitor, record(RED::ReductionStep1Active)),
+            [HealthChange::ProcessingModeChange {
+                from: mode(RED::NoReductionActive),
+                to: mode(RED::ReductionStep1Active)
+            }]
+        );
+    }
+
+    #[test]
+    fn test_target() {
+        let north = cat34(MTYPE::NorthMarker);
+        // I048/010 25/13, I048/020 SSR field monitor
+        let bytes = [0x30, 0x00, 0x07, 0b1010_0000, 0x19, 0x0d, 0b0100_0010];
+        let (_, packet) = AsterixPacket::from_bytes((&bytes, 0)).unwrap();
+        let AsterixMessage::Cat48(test_target) = &packet.messages[0] else {
+            panic!("not CAT048");
+        };
+        let mut monitor = HealthMonitor::new();
+        // no test target seen yet
+        assert!(changes(&mut monitor, north.clone()).is_empty());
+        assert!(changes(&mut monitor, north.clone()).is_empty());
+
+        assert!(monitor.update_cat48(test_target).is_none());
+        assert!(changes(&mut monitor, north.clone()).is_empty());
+        assert_eq!(changes(&mut monitor, north.clone()), [HealthChange::TestTargetFailure]);
+        assert!(changes(&mut monitor, north.clone()).is_empty());
+        assert_eq!(
+            monitor.update_cat48(test_target).map(|e| e.change),
+            Some(HealthChange::TestTargetRestored)
+        );
+    }
+}
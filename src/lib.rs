@@ -27,8 +27,8 @@
 //! thirty_eight.message_type = Some(MessageType {
 //!     t: MTYPE::SectorCrossing,
 //! });
-//! thirty_eight.time_of_day = Some(TimeOfDay { time: 27355.953 });
-//! thirty_eight.sector_number = Some(SectorNumber { num: 135 });
+//! thirty_eight.time_of_day = Some(TimeOfDay::from_seconds(27355.953).unwrap());
+//! thirty_eight.sector_number = Some(SectorNumber::from_deg(135.0).unwrap());
 //!
 //! let mut packet = AsterixPacket::default();
 //! packet.category = 34;
@@ -51,6 +51,21 @@
 //! // serialize
 //! packet.to_bytes().unwrap();
 //! ```
+//!
+//! # Lossless Re-encoding
+//! Every data item keeps its spare and reserved bits, so a decoded packet that is serialized
+//! again reproduces the received bytes. `finalize()` recomputes each fspec from the data items
+//! that are present, use `finalize_lossless()` to only update the length and keep each fspec
+//! exactly as it was received.
+//! ```rust
+//! use deku::prelude::*;
+//! use asterix::*;
+//!
+//! let bytes = [0x22, 0x00, 0x0b, 0xf0, 0x19, 0x0d, 0x02, 0x35, 0x6d, 0xfa, 0x60];
+//! let (_, mut packet) = AsterixPacket::from_bytes((&bytes, 0)).unwrap();
+//! packet.finalize_lossless().unwrap();
+//! assert_eq!(packet.to_bytes().unwrap(), bytes);
+//! ```
 
 use deku::bitvec::{BitVec, Msb0};
 use deku::prelude::*;
@@ -66,21 +81,45 @@ pub use fourty_eight::Cat48;
 mod thirty_four;
 pub use thirty_four::Cat34;
 
+mod four;
+pub use four::Cat4;
+
+pub mod alert;
+pub mod bias;
+pub mod clock;
 pub mod data_item;
+pub mod dedup;
+pub mod edition;
+pub mod error;
+pub mod filter;
 mod fspec;
+pub mod fusion;
+pub mod geodesy;
+pub mod health;
+pub mod picture;
+pub mod plausibility;
+pub mod plot_count;
+pub mod rotation;
+pub mod scan;
+pub mod sensor;
+pub mod stca;
+pub mod tracker;
+
+#[cfg(test)]
+mod test_util;
 
 /// Size of category + length in bytes
 const ASTERIX_HEADER_SIZE: u16 = 3;
 
 const FSPEC_IDENT: u8 = 0b0000_0001;
 
-#[derive(Debug, Default, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, Default, PartialEq, Clone, DekuRead, DekuWrite)]
 #[deku(endian = "big")]
 pub struct AsterixPacket {
     /// Category of all `messages`
     pub category: u8,
     /// Total length of `AsterixPacket`
-    #[deku(update = "Self::update_len(&mut self.messages)")]
+    #[deku(update = "Self::update_len(&mut self.messages)?")]
     pub length: u16,
     /// Asterix Messages
     #[deku(bytes_read = "length - ASTERIX_HEADER_SIZE", ctx = "*category")]
@@ -88,30 +127,41 @@ pub struct AsterixPacket {
 }
 
 impl AsterixPacket {
-    /// Update fspec and len
+    /// Update fspec and len, after checking each message against its edition
     pub fn finalize(&mut self) -> Result<(), DekuError> {
         for message in &mut self.messages {
+            message.check_edition()?;
             message.update_fspec();
         }
         self.update()
     }
 
+    /// Update len only, keeping every fspec as decoded
+    pub fn finalize_lossless(&mut self) -> Result<(), DekuError> {
+        for message in &self.messages {
+            message.check_edition()?;
+        }
+        self.update()
+    }
+
     /// Read all messages and return byte len
-    fn update_len(messages: &mut [AsterixMessage]) -> u16 {
-        let mut len: u16 = 0;
+    fn update_len(messages: &mut [AsterixMessage]) -> Result<u16, DekuError> {
+        let mut len: u16 = ASTERIX_HEADER_SIZE;
         for message in messages.iter_mut() {
             let mut bits: BitVec<u8, Msb0> = BitVec::new();
-            message.write(&mut bits, (deku::ctx::Endian::Big, 0)).unwrap();
-            len += (bits.len() / 8) as u16 + ASTERIX_HEADER_SIZE
+            message.write(&mut bits, (deku::ctx::Endian::Big, 0))?;
+            len += (bits.len() / 8) as u16
         }
-        len
+        Ok(len)
     }
 }
 
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, Clone, DekuRead, DekuWrite)]
 #[deku(id = "category", ctx = "_: deku::ctx::Endian, category: u8")]
 /// Union of Asterix categories
 pub enum AsterixMessage {
+    #[deku(id = "4")]
+    Cat4(Cat4),
     #[deku(id = "34")]
     Cat34(Cat34),
     #[deku(id = "48")]
@@ -122,8 +172,37 @@ impl AsterixMessage {
     /// Call `update_fpsec` of internal type
     pub fn update_fspec(&mut self) {
         match self {
+            Self::Cat4(c) => c.update_fspec(),
             Self::Cat34(c) => c.update_fspec(),
             Self::Cat48(c) => c.update_fspec(),
         }
     }
+
+    /// Call `check_edition` of internal type
+    pub fn check_edition(&self) -> Result<(), DekuError> {
+        match self {
+            // only one edition of CAT004 is supported
+            Self::Cat4(_) => Ok(()),
+            Self::Cat34(c) => c.check_edition(),
+            Self::Cat48(c) => c.check_edition(),
+        }
+    }
+
+    /// I048/010, I034/010 or I004/010
+    pub fn data_source_identifier(&self) -> Option<&data_item::DataSourceIdentifier> {
+        match self {
+            Self::Cat4(c) => c.data_source_identifier.as_ref(),
+            Self::Cat34(c) => c.data_source_identifier.as_ref(),
+            Self::Cat48(c) => c.data_source_identifier.as_ref(),
+        }
+    }
+
+    /// I048/140, I034/030 or I004/020
+    pub fn time_of_day(&self) -> Option<&data_item::TimeOfDay> {
+        match self {
+            Self::Cat4(c) => c.time_of_message.as_ref(),
+            Self::Cat34(c) => c.time_of_day.as_ref(),
+            Self::Cat48(c) => c.time_of_day.as_ref(),
+        }
+    }
 }
@@ -1,11 +1,79 @@
-pub fn groundspeed() -> f32 {
-    2_f32.powi(-14)
+use deku::DekuError;
+
+/// Round a physical value to the nearest count of `lsb`
+fn to_lsb(value: f64, lsb: f64) -> f64 {
+    (value / lsb).round()
+}
+
+fn out_of_range(name: &str, value: f64, min: f64, max: f64) -> DekuError {
+    DekuError::InvalidParam(format!("{name}: {value} is outside of {min}..={max}"))
+}
+
+/// Nearest count of `lsb` that fits in `bits` unsigned bits
+pub fn unsigned(value: f64, lsb: f64, bits: u32, name: &str) -> Result<u64, DekuError> {
+    let max = (1_u64 << bits) - 1;
+    let raw = to_lsb(value, lsb);
+    // NaN fails both comparisons
+    if (0.0..=max as f64).contains(&raw) {
+        Ok(raw as u64)
+    } else {
+        Err(out_of_range(name, value, 0.0, max as f64 * lsb))
+    }
+}
+
+/// Nearest count of `lsb` that fits in `bits` two's complement bits
+pub fn signed(value: f64, lsb: f64, bits: u32, name: &str) -> Result<i64, DekuError> {
+    let max = (1_i64 << (bits - 1)) - 1;
+    let min = -max - 1;
+    let raw = to_lsb(value, lsb);
+    if (min as f64..=max as f64).contains(&raw) {
+        Ok(raw as i64)
+    } else {
+        Err(out_of_range(name, value, min as f64 * lsb, max as f64 * lsb))
+    }
 }
 
-pub fn heading1() -> f32 {
-    360.0 / 2_f32.powi(16)
+/// Nearest count of 360/2^`bits` degrees for an angle in 0..360, values that round up to 360
+/// are encoded as 0
+pub fn angle(value: f64, bits: u32, name: &str) -> Result<u64, DekuError> {
+    if !(0.0..360.0).contains(&value) {
+        return Err(DekuError::InvalidParam(format!("{name}: {value} is outside of 0..360")));
+    }
+    let lsb = 360.0 / (1_u64 << bits) as f64;
+    Ok(to_lsb(value, lsb) as u64 % (1 << bits))
 }
 
-pub fn heading2() -> f32 {
-    360.0 / 2_f32.powi(12)
+/// Wrap an angle in degrees into 0..360
+pub fn wrap_deg(value: f64) -> f64 {
+    let wrapped = value.rem_euclid(360.0);
+    // rem_euclid can return 360.0 for tiny negative values
+    if wrapped >= 360.0 {
+        0.0
+    } else {
+        wrapped
+    }
+}
+
+/// 2^-14 NM/s
+pub const GROUNDSPEED: f64 = 1.0 / 16384.0;
+
+/// 360/2^16 degrees
+pub const HEADING_16: f64 = 360.0 / 65536.0;
+
+/// 360/2^12 degrees
+pub const HEADING_12: f64 = 360.0 / 4096.0;
+
+pub const SECONDS_PER_HOUR: f64 = 3600.0;
+
+pub const SECONDS_PER_DAY: f64 = 86400.0;
+
+/// Seconds from time of day `earlier` to `later`, in -12..12 hours, so negative if `later` is
+/// before `earlier` and across midnight if it is shortly after it
+pub fn elapsed(earlier: f64, later: f64) -> f64 {
+    let elapsed = (later - earlier).rem_euclid(SECONDS_PER_DAY);
+    if elapsed > SECONDS_PER_DAY / 2.0 {
+        elapsed - SECONDS_PER_DAY
+    } else {
+        elapsed
+    }
 }
@@ -0,0 +1,346 @@
+//! Air picture of the latest state of each target
+//!
+//! `AirPicture` merges each CAT048 record into the state of its target, identified by Mode S
+//! address, else by the track number of its radar. A target first seen by track number moves to
+//! its Mode S address when a record with both arrives.
+//!
+//! Scans are counted per radar from North Marker messages, or with `AirPicture::end_scan` for
+//! streams without CAT034. Targets not updated for `max_missed_scans` scans of their radar are
+//! dropped. Records without I048/010 count as coming from SAC/SIC 0/0.
+
+use std::collections::HashMap;
+
+use crate::data_item::{
+    CalculatedPositionCartesianCorr, CalculatedTrackVelocity, DataSourceIdentifier,
+    MeasuredPositionInPolarCoordinates, TimeOfDay,
+};
+use crate::types::{Callsign, Squawk, MTYPE};
+use crate::{AsterixMessage, Cat34, Cat48};
+
+/// Identity of a target in the air picture
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TargetKey {
+    /// I048/220
+    ModeS(u32),
+    /// I048/161 of SAC/SIC
+    Track { sac: u8, sic: u8, number: u16 },
+}
+
+/// Latest state of a target, each item from the last record that had it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Target {
+    pub key: TargetKey,
+    /// Radar of the last update
+    pub source: Option<DataSourceIdentifier>,
+    pub track_number: Option<u16>,
+    pub polar: Option<MeasuredPositionInPolarCoordinates>,
+    pub cartesian: Option<CalculatedPositionCartesianCorr>,
+    /// Flight level from I048/090
+    pub flight_level: Option<f64>,
+    pub callsign: Option<Callsign>,
+    pub squawk: Option<Squawk>,
+    pub velocity: Option<CalculatedTrackVelocity>,
+    /// I048/140 of the last update
+    pub time_of_day: Option<TimeOfDay>,
+    /// Records merged into the target
+    pub updates: u32,
+    /// SAC/SIC of the last update, 0/0 without I048/010
+    radar: (u8, u8),
+    /// Scan of the radar of the last update
+    scan: u64,
+}
+
+impl Target {
+    fn new(key: TargetKey) -> Self {
+        Self {
+            key,
+            source: None,
+            track_number: None,
+            polar: None,
+            cartesian: None,
+            flight_level: None,
+            callsign: None,
+            squawk: None,
+            velocity: None,
+            time_of_day: None,
+            updates: 0,
+            radar: (0, 0),
+            scan: 0,
+        }
+    }
+
+    /// Mode S address
+    pub fn address(&self) -> Option<u32> {
+        match self.key {
+            TargetKey::ModeS(address) => Some(address),
+            TargetKey::Track { .. } => None,
+        }
+    }
+
+    fn merge(&mut self, record: &Cat48, radar: (u8, u8), scan: u64) {
+        fn take<T: Clone>(into: &mut Option<T>, from: &Option<T>) {
+            if from.is_some() {
+                into.clone_from(from);
+            }
+        }
+        take(&mut self.source, &record.data_source_identifier);
+        take(&mut self.track_number, &record.track_number.as_ref().map(|t| t.number));
+        take(&mut self.polar, &record.measured_position_in_polar_coordinates);
+        take(&mut self.cartesian, &record.calculated_position_cartesian_coor);
+        take(&mut self.flight_level, &record.flight_level_in_binary_repre.as_ref().map(|f| f.fl()));
+        take(
+            &mut self.callsign,
+            &record.aircraft_identification.as_ref().map(|a| a.identification),
+        );
+        take(&mut self.squawk, &record.squawk());
+        take(&mut self.velocity, &record.calculated_track_velocity);
+        take(&mut self.time_of_day, &record.time_of_day);
+        self.updates += 1;
+        self.radar = radar;
+        self.scan = scan;
+    }
+
+    /// Fill the items this target doesn't have from `other`, a target of the same aircraft
+    fn absorb(&mut self, other: Target) {
+        self.source = self.source.take().or(other.source);
+        self.track_number = self.track_number.or(other.track_number);
+        self.polar = self.polar.take().or(other.polar);
+        self.cartesian = self.cartesian.take().or(other.cartesian);
+        self.flight_level = self.flight_level.or(other.flight_level);
+        self.callsign = self.callsign.or(other.callsign);
+        self.squawk = self.squawk.or(other.squawk);
+        self.velocity = self.velocity.take().or(other.velocity);
+        self.time_of_day = self.time_of_day.take().or(other.time_of_day);
+        self.updates += other.updates;
+    }
+}
+
+/// Change of the air picture
+#[derive(Debug, Clone, PartialEq)]
+pub enum PictureEvent {
+    /// First record of a target
+    New(TargetKey),
+    /// A target first seen by track number now has a Mode S address, its state is merged into the
+    /// Mode S target if there is one
+    Rekeyed { from: TargetKey, to: TargetKey },
+    /// A target wasn't updated for too many scans, with its last state
+    Lost(Target),
+    /// The aircraft identification of a target changed
+    CallsignChange { key: TargetKey, from: Callsign, to: Callsign },
+}
+
+/// Latest state of every target, see the module documentation
+#[derive(Debug, Clone)]
+pub struct AirPicture {
+    max_missed_scans: u64,
+    targets: HashMap<TargetKey, Target>,
+    /// Mode S targets by the track number their radar gave them
+    tracks: HashMap<(u8, u8, u16), TargetKey>,
+    scans: HashMap<(u8, u8), u64>,
+}
+
+impl AirPicture {
+    /// Drop targets not updated for `max_missed_scans` scans of their radar
+    pub fn new(max_missed_scans: u64) -> Self {
+        Self {
+            max_missed_scans,
+            targets: HashMap::new(),
+            tracks: HashMap::new(),
+            scans: HashMap::new(),
+        }
+    }
+
+    pub fn get(&self, key: &TargetKey) -> Option<&Target> {
+        self.targets.get(key)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Target> {
+        self.targets.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.targets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.targets.is_empty()
+    }
+
+    /// Merge a record, or count a scan on a North Marker
+    pub fn update(&mut self, message: &AsterixMessage) -> Vec<PictureEvent> {
+        match message {
+            AsterixMessage::Cat48(record) => self.update_cat48(record),
+            AsterixMessage::Cat34(record) => self.update_cat34(record),
+            AsterixMessage::Cat4(_) => vec![],
+        }
+    }
+
+    /// Count a scan of the radar on a North Marker
+    pub fn update_cat34(&mut self, record: &Cat34) -> Vec<PictureEvent> {
+        match (&record.data_source_identifier, record.message_type.as_ref().map(|t| t.t)) {
+            (Some(source), Some(MTYPE::NorthMarker)) => self.end_scan(source),
+            _ => vec![],
+        }
+    }
+
+    /// Count a scan of `source`, dropping its targets that missed too many scans
+    pub fn end_scan(&mut self, source: &DataSourceIdentifier) -> Vec<PictureEvent> {
+        let id = (source.sac, source.sic);
+        let scan = self.scans.entry(id).or_default();
+        *scan += 1;
+        let scan = *scan;
+
+        let lost: Vec<TargetKey> = self
+            .targets
+            .values()
+            .filter(|t| t.radar == id)
+            .filter(|t| scan - t.scan > self.max_missed_scans)
+            .map(|t| t.key)
+            .collect();
+        lost.into_iter()
+            .filter_map(|key| self.targets.remove(&key))
+            .map(|target| {
+                self.tracks.retain(|_, key| *key != target.key);
+                PictureEvent::Lost(target)
+            })
+            .collect()
+    }
+
+    /// Merge a record into the state of its target
+    ///
+    /// Records without I048/220 or I048/161 are ignored.
+    pub fn update_cat48(&mut self, record: &Cat48) -> Vec<PictureEvent> {
+        let mut events = vec![];
+        let source = record.data_source_identifier.as_ref().map_or((0, 0), |s| (s.sac, s.sic));
+        let track = record.track_number.as_ref().map(|t| (source.0, source.1, t.number));
+
+        let key = match (record.aircraft_address.as_ref(), track) {
+            (Some(address), track) => {
+                let key = TargetKey::ModeS(address.address);
+                if let Some(track) = track {
+                    self.tracks.insert(track, key);
+                }
+                // a target seen by track number before its address
+                let track_key =
+                    track.map(|(sac, sic, number)| TargetKey::Track { sac, sic, number });
+                if let Some(target) = track_key.and_then(|k| self.targets.remove(&k)) {
+                    events.push(PictureEvent::Rekeyed { from: target.key, to: key });
+                    match self.targets.get_mut(&key) {
+                        Some(existing) => existing.absorb(target),
+                        None => {
+                            self.targets.insert(key, Target { key, ..target });
+                        }
+                    }
+                }
+                key
+            }
+            (None, Some((sac, sic, number))) => self
+                .tracks
+                .get(&(sac, sic, number))
+                .copied()
+                .unwrap_or(TargetKey::Track { sac, sic, number }),
+            (None, None) => return events,
+        };
+
+        let scan = self.scans.get(&source).copied().unwrap_or_default();
+        let target = self.targets.entry(key).or_insert_with(|| {
+            events.push(PictureEvent::New(key));
+            Target::new(key)
+        });
+        let previous = target.callsign;
+        target.merge(record, source, scan);
+        if let (Some(from), Some(to)) = (previous, target.callsign) {
+            if from != to {
+                events.push(PictureEvent::CallsignChange { key, from, to });
+            }
+        }
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{cat34, cat48};
+
+    fn north() -> AsterixMessage {
+        AsterixMessage::Cat34(cat34(MTYPE::NorthMarker))
+    }
+
+    #[test]
+    fn merge() {
+        let mut picture = AirPicture::new(2);
+        let track = TargetKey::Track { sac: 25, sic: 13, number: 7 };
+        let mode_s = TargetKey::ModeS(0xabcdef);
+        assert_eq!(picture.update_cat48(&cat48().with_track_number(7)), [PictureEvent::New(track)]);
+        assert_eq!(
+            picture.update_cat48(
+                &cat48().with_address(0xabcdef).with_track_number(7).with_callsign("DLH123")
+            ),
+            [PictureEvent::Rekeyed { from: track, to: mode_s }]
+        );
+        // later records without the address are merged by track number
+        assert!(picture.update_cat48(&cat48().with_track_number(7)).is_empty());
+        assert_eq!(picture.len(), 1);
+
+        let target = picture.get(&mode_s).unwrap();
+        assert_eq!(target.updates, 3);
+        assert_eq!(target.track_number, Some(7));
+        assert_eq!(target.address(), Some(0xabcdef));
+        assert_eq!(target.callsign.unwrap().trimmed(), "DLH123");
+
+        assert_eq!(
+            picture.update_cat48(&cat48().with_address(0xabcdef).with_callsign("DLH124")),
+            [PictureEvent::CallsignChange {
+                key: mode_s,
+                from: Callsign::new("DLH123").unwrap(),
+                to: Callsign::new("DLH124").unwrap()
+            }]
+        );
+        assert!(picture.update_cat48(&cat48()).is_empty());
+    }
+
+    #[test]
+    fn rekey_into_existing() {
+        let mut picture = AirPicture::new(2);
+        let mode_s = TargetKey::ModeS(0xabcdef);
+        picture.update_cat48(&cat48().with_address(0xabcdef).with_flight_level(350.0));
+        picture.update_cat48(&cat48().with_source(25, 14).with_track_number(7).with_squawk(0o1234));
+        assert_eq!(
+            picture.update_cat48(
+                &cat48().with_source(25, 14).with_address(0xabcdef).with_track_number(7)
+            ),
+            [PictureEvent::Rekeyed {
+                from: TargetKey::Track { sac: 25, sic: 14, number: 7 },
+                to: mode_s
+            }]
+        );
+        assert_eq!(picture.len(), 1);
+        let target = picture.get(&mode_s).unwrap();
+        assert_eq!(target.updates, 3);
+        assert_eq!(target.flight_level, Some(350.0));
+        assert_eq!(target.squawk, Some(Squawk::new(0o1234).unwrap()));
+        assert_eq!(target.source, Some(DataSourceIdentifier { sac: 25, sic: 14 }));
+    }
+
+    #[test]
+    fn lost() {
+        let mut picture = AirPicture::new(2);
+        picture.update_cat48(&cat48().with_address(1));
+        picture.update(&north());
+        picture.update_cat48(&cat48().with_address(2));
+        assert!(picture.update(&north()).is_empty());
+        let events = picture.update(&north());
+        assert!(matches!(&events[..], [PictureEvent::Lost(t)] if t.key == TargetKey::ModeS(1)));
+        assert!(picture.update(&north()).len() == 1);
+        assert!(picture.is_empty());
+
+        // records without I048/010 belong to the scans of 0/0
+        let unknown = DataSourceIdentifier { sac: 0, sic: 0 };
+        picture.update_cat48(&Cat48 { data_source_identifier: None, ..cat48().with_address(3) });
+        assert!(picture.end_scan(&unknown).is_empty());
+        assert!(picture.end_scan(&unknown).is_empty());
+        assert_eq!(picture.end_scan(&unknown).len(), 1);
+        assert!(picture.is_empty());
+    }
+}
@@ -0,0 +1,485 @@
+//! Plausibility checks of CAT048 target reports
+//!
+//! `PlausibilityChecker` compares each record with the previous record of the same target from
+//! the same radar, by Mode S address, else by track number, and flags position jumps faster than
+//! an aircraft can fly and flight level changes faster than it can climb. Records of a Mode S
+//! address at the same time but far apart are flagged as duplicate addresses, within one radar
+//! from the radar Cartesian positions, and across radars from WGS-84 positions when the
+//! `SensorRegistry`, which learns I034/120, gives the radar positions.
+//!
+//! I048/140 and I034/030 are checked to be within 0..86400 s, and not earlier than the latest
+//! record of the radar, allowing for the order of plots within a scan. After `resync_after`
+//! records in a row earlier than that, the radar clock is taken to have been reset, or the latest
+//! time to have been corrupt, and the last of them becomes the latest time.
+
+use std::collections::HashMap;
+
+use crate::data_item::{DataSourceIdentifier, TimeOfDay};
+use crate::geodesy::{self, Wgs84};
+use crate::modifier::{elapsed, SECONDS_PER_DAY, SECONDS_PER_HOUR};
+use crate::picture::TargetKey;
+use crate::sensor::SensorRegistry;
+use crate::types::MTYPE;
+use crate::{AsterixMessage, Cat34, Cat48};
+
+/// Seconds between prunings of the targets of a radar on CAT048 time
+const PRUNE_INTERVAL: f64 = 1.0;
+
+/// Parameters of `PlausibilityChecker`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlausibilityConfig {
+    /// Largest plausible groundspeed in kt
+    pub max_groundspeed_kt: f64,
+    /// Largest plausible rate of climb or descent in FL per second
+    pub max_climb_fl_s: f64,
+    /// Records of a target less than this many seconds apart are from the same scan, and are
+    /// checked as duplicates instead of for speed and climb
+    pub min_interval: f64,
+    /// Records of a Mode S address from two radars less than this many seconds apart are at the
+    /// same time
+    pub duplicate_window: f64,
+    /// Largest distance in NM between records of a Mode S address at the same time
+    pub duplicate_distance_nm: f64,
+    /// Seconds a record may be earlier than the latest record of its radar
+    pub time_tolerance: f64,
+    /// Records in a row earlier than the latest record of their radar before its time is
+    /// resynchronised
+    pub resync_after: u32,
+    /// Targets not updated for this many seconds are forgotten
+    pub max_age: f64,
+}
+
+impl Default for PlausibilityConfig {
+    fn default() -> Self {
+        Self {
+            max_groundspeed_kt: 1000.0,
+            max_climb_fl_s: 1.5,
+            min_interval: 1.0,
+            duplicate_window: 4.0,
+            duplicate_distance_nm: 5.0,
+            time_tolerance: 1.0,
+            resync_after: 3,
+            max_age: 60.0,
+        }
+    }
+}
+
+/// Failed check
+#[derive(Debug, Clone, PartialEq)]
+pub enum Implausibility {
+    /// I048/140 or I034/030 is not within 0..86400 s
+    TimeOfDayOutOfRange { seconds: f64 },
+    /// The record is earlier than the latest record of the radar
+    TimeBackwards { previous: f64, seconds: f64 },
+    /// The target moved faster than `max_groundspeed_kt` since its last record
+    PositionJump { key: TargetKey, distance_nm: f64, groundspeed_kt: f64 },
+    /// The flight level changed faster than `max_climb_fl_s` since the last record of the target
+    ClimbRate { key: TargetKey, from: f64, to: f64, fl_s: f64 },
+    /// The Mode S address was reported far away at the same time, by the radar `other`, which
+    /// is the radar of the record for two records in one scan
+    DuplicateAddress { address: u32, other: DataSourceIdentifier, distance_nm: f64 },
+}
+
+/// Failed check of a record
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlausibilityEvent {
+    pub source: DataSourceIdentifier,
+    /// I048/140 or I034/030 of the record
+    pub time_of_day: Option<TimeOfDay>,
+    pub issue: Implausibility,
+}
+
+/// Last record of a target from a radar
+#[derive(Debug, Clone, Copy)]
+struct Sighting {
+    time: f64,
+    /// Radar Cartesian position in NM
+    x: f64,
+    y: f64,
+    position: Option<Wgs84>,
+    fl: Option<f64>,
+}
+
+/// Time of the records of a radar
+#[derive(Debug, Clone, Copy)]
+struct RadarTime {
+    /// Time of the latest record
+    latest: f64,
+    /// Records in a row earlier than `latest` beyond the tolerance
+    backwards: u32,
+    /// Time the targets of the radar were last pruned
+    pruned: f64,
+}
+
+/// Checks the records of every radar, see the module documentation
+#[derive(Debug, Clone, Default)]
+pub struct PlausibilityChecker {
+    config: PlausibilityConfig,
+    sensors: SensorRegistry,
+    times: HashMap<(u8, u8), RadarTime>,
+    targets: HashMap<((u8, u8), TargetKey), Sighting>,
+}
+
+impl PlausibilityChecker {
+    pub fn new(config: PlausibilityConfig) -> Self {
+        Self { config, ..Self::default() }
+    }
+
+    /// Use the positions of the sensors of `sensors`, updated by I034/120
+    pub fn with_sensors(mut self, sensors: SensorRegistry) -> Self {
+        self.sensors = sensors;
+        self
+    }
+
+    /// Sensors with the radar positions
+    pub fn sensors(&self) -> &SensorRegistry {
+        &self.sensors
+    }
+
+    /// Check a record
+    ///
+    /// Records without I0xx/010 are ignored.
+    pub fn update(&mut self, message: &AsterixMessage) -> Vec<PlausibilityEvent> {
+        match message {
+            AsterixMessage::Cat48(record) => self.update_cat48(record),
+            AsterixMessage::Cat34(record) => self.update_cat34(record),
+            AsterixMessage::Cat4(_) => vec![],
+        }
+    }
+
+    /// Check I034/030, learn the radar position from I034/120, and forget old targets of the
+    /// radar on a North Marker
+    pub fn update_cat34(&mut self, record: &Cat34) -> Vec<PlausibilityEvent> {
+        let Some(source) = &record.data_source_identifier else {
+            return vec![];
+        };
+        let id = (source.sac, source.sic);
+        self.sensors.update_cat34(record);
+        let mut issues = vec![];
+        let time = record.time_of_day.as_ref().and_then(|t| self.check_time(id, t, &mut issues));
+        if let (Some(time), Some(MTYPE::NorthMarker)) =
+            (time, record.message_type.as_ref().map(|t| t.t))
+        {
+            self.prune(id, time);
+        }
+        self.events(source, &record.time_of_day, issues)
+    }
+
+    /// Check a record against the last record of its target
+    pub fn update_cat48(&mut self, record: &Cat48) -> Vec<PlausibilityEvent> {
+        let Some(source) = &record.data_source_identifier else {
+            return vec![];
+        };
+        let id = (source.sac, source.sic);
+        let mut issues = vec![];
+        let Some(time) =
+            record.time_of_day.as_ref().and_then(|t| self.check_time(id, t, &mut issues))
+        else {
+            return self.events(source, &record.time_of_day, issues);
+        };
+        // between North Markers, or without CAT034
+        if self.times.get(&id).is_some_and(|t| elapsed(t.pruned, time) >= PRUNE_INTERVAL) {
+            self.prune(id, time);
+        }
+
+        let address = record.aircraft_address.as_ref().map(|a| a.address);
+        let key = match (address, &record.track_number) {
+            (Some(address), _) => TargetKey::ModeS(address),
+            (None, Some(track)) => TargetKey::Track { sac: id.0, sic: id.1, number: track.number },
+            (None, None) => return self.events(source, &record.time_of_day, issues),
+        };
+        let Some(sighting) = self.sighting(id, record, time) else {
+            return self.events(source, &record.time_of_day, issues);
+        };
+
+        if let Some(previous) = self.targets.get(&(id, key)) {
+            self.check_target(id, key, previous, &sighting, &mut issues);
+        }
+        if let Some(address) = address {
+            self.check_duplicates(address, id, &sighting, &mut issues);
+        }
+        self.targets.insert((id, key), sighting);
+        self.events(source, &record.time_of_day, issues)
+    }
+
+    /// Seconds of `time_of_day` if in range and not going backwards
+    fn check_time(
+        &mut self,
+        id: (u8, u8),
+        time_of_day: &TimeOfDay,
+        issues: &mut Vec<Implausibility>,
+    ) -> Option<f64> {
+        let seconds = time_of_day.seconds();
+        if !(0.0..SECONDS_PER_DAY).contains(&seconds) {
+            issues.push(Implausibility::TimeOfDayOutOfRange { seconds });
+            return None;
+        }
+        let Some(time) = self.times.get_mut(&id) else {
+            self.times.insert(id, RadarTime { latest: seconds, backwards: 0, pruned: seconds });
+            return Some(seconds);
+        };
+        let dt = elapsed(time.latest, seconds);
+        if dt < -self.config.time_tolerance {
+            time.backwards += 1;
+            if time.backwards < self.config.resync_after {
+                issues.push(Implausibility::TimeBackwards { previous: time.latest, seconds });
+                return None;
+            }
+            // resynchronise, forgetting the targets seen after the new time
+            *time = RadarTime { latest: seconds, backwards: 0, pruned: seconds };
+            self.targets.retain(|(radar, _), s| *radar != id || elapsed(s.time, seconds) >= 0.0);
+            return Some(seconds);
+        }
+        time.backwards = 0;
+        if dt > 0.0 {
+            time.latest = seconds;
+        }
+        Some(seconds)
+    }
+
+    /// Forget the targets of radar `id` not updated for `max_age` at `time`
+    fn prune(&mut self, id: (u8, u8), time: f64) {
+        let max_age = self.config.max_age;
+        self.targets.retain(|(radar, _), s| *radar != id || elapsed(s.time, time) <= max_age);
+        if let Some(radar) = self.times.get_mut(&id) {
+            radar.pruned = time;
+        }
+    }
+
+    fn sighting(&self, id: (u8, u8), record: &Cat48, time: f64) -> Option<Sighting> {
+        let polar = record.measured_position_in_polar_coordinates.as_ref();
+        let height = geodesy::plot_height(record).unwrap_or_default();
+        let radar = self.sensors.position(&DataSourceIdentifier { sac: id.0, sic: id.1 });
+        let (x, y, position) = match (&record.calculated_position_cartesian_coor, polar) {
+            (Some(cartesian), _) => {
+                let (x, y) = (cartesian.x_nm(), cartesian.y_nm());
+                let position =
+                    radar.and_then(|r| geodesy::cartesian_to_wgs84(&r, x, y, height).ok());
+                (x, y, position)
+            }
+            (None, Some(polar)) => {
+                let (rho, theta) = (polar.rho_nm(), polar.theta_deg());
+                let (x, y) = geodesy::polar_to_cartesian(rho, theta);
+                let position =
+                    radar.and_then(|r| geodesy::polar_to_wgs84(&r, rho, theta, height).ok());
+                (x, y, position)
+            }
+            (None, None) => return None,
+        };
+        let fl = record.flight_level_in_binary_repre.as_ref().map(|f| f.fl());
+        Some(Sighting { time, x, y, position, fl })
+    }
+
+    fn check_target(
+        &self,
+        id: (u8, u8),
+        key: TargetKey,
+        previous: &Sighting,
+        sighting: &Sighting,
+        issues: &mut Vec<Implausibility>,
+    ) {
+        let dt = elapsed(previous.time, sighting.time);
+        let distance_nm = (sighting.x - previous.x).hypot(sighting.y - previous.y);
+        if dt < self.config.min_interval {
+            if let TargetKey::ModeS(address) = key {
+                if distance_nm > self.config.duplicate_distance_nm {
+                    // twice in the same scan
+                    let other = DataSourceIdentifier { sac: id.0, sic: id.1 };
+                    issues.push(Implausibility::DuplicateAddress { address, other, distance_nm });
+                }
+            }
+            return;
+        }
+        let groundspeed_kt = distance_nm / dt * SECONDS_PER_HOUR;
+        if groundspeed_kt > self.config.max_groundspeed_kt {
+            issues.push(Implausibility::PositionJump { key, distance_nm, groundspeed_kt });
+        }
+        if let (Some(from), Some(to)) = (previous.fl, sighting.fl) {
+            let fl_s = (to - from) / dt;
+            if fl_s.abs() > self.config.max_climb_fl_s {
+                issues.push(Implausibility::ClimbRate { key, from, to, fl_s });
+            }
+        }
+    }
+
+    /// Compare with the last records of `address` from the other radars
+    fn check_duplicates(
+        &self,
+        address: u32,
+        id: (u8, u8),
+        sighting: &Sighting,
+        issues: &mut Vec<Implausibility>,
+    ) {
+        let Some(position) = sighting.position else {
+            return;
+        };
+        for ((radar, key), other) in &self.targets {
+            if *radar == id || *key != TargetKey::ModeS(address) {
+                continue;
+            }
+            let Some(other_position) = other.position else {
+                continue;
+            };
+            if elapsed(other.time, sighting.time).abs() >= self.config.duplicate_window {
+                continue;
+            }
+            let enu = other_position.enu(&position);
+            let distance_nm = enu.east.hypot(enu.north) / geodesy::METERS_PER_NM;
+            if distance_nm > self.config.duplicate_distance_nm {
+                issues.push(Implausibility::DuplicateAddress {
+                    address,
+                    other: DataSourceIdentifier { sac: radar.0, sic: radar.1 },
+                    distance_nm,
+                });
+            }
+        }
+    }
+
+    fn events(
+        &self,
+        source: &DataSourceIdentifier,
+        time_of_day: &Option<TimeOfDay>,
+        issues: Vec<Implausibility>,
+    ) -> Vec<PlausibilityEvent> {
+        issues
+            .into_iter()
+            .map(|issue| PlausibilityEvent {
+                source: source.clone(),
+                time_of_day: time_of_day.clone(),
+                issue,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sensor::SensorConfig;
+    use crate::test_util::{cat34, cat48, SOURCE};
+
+    fn record(time: f64, rho: f64, theta: f64, fl: f64) -> Cat48 {
+        cat48().with_time(time).with_polar(rho, theta).with_flight_level(fl).with_address(0xabcdef)
+    }
+
+    fn issues(events: Vec<PlausibilityEvent>) -> Vec<Implausibility> {
+        events.into_iter().map(|e| e.issue).collect()
+    }
+
+    #[test]
+    fn target() {
+        let key = TargetKey::ModeS(0xabcdef);
+        let mut checker = PlausibilityChecker::new(PlausibilityConfig::default());
+        assert!(checker.update_cat48(&record(100.0, 50.0, 0.0, 300.0)).is_empty());
+        assert!(checker.update_cat48(&record(104.0, 50.5, 0.0, 302.0)).is_empty());
+        // 5 NM in 4 s
+        match &issues(checker.update_cat48(&record(108.0, 55.5, 0.0, 302.0)))[..] {
+            [Implausibility::PositionJump { key: k, groundspeed_kt, .. }] => {
+                assert_eq!(*k, key);
+                assert!((groundspeed_kt - 4500.0).abs() < 1.0);
+            }
+            issues => panic!("{issues:?}"),
+        }
+        assert_eq!(
+            issues(checker.update_cat48(&record(112.0, 56.0, 0.0, 310.0))),
+            [Implausibility::ClimbRate { key, from: 302.0, to: 310.0, fl_s: 2.0 }]
+        );
+        // twice in one scan
+        match &issues(checker.update_cat48(&record(112.5, 56.0, 90.0, 310.0)))[..] {
+            [Implausibility::DuplicateAddress { address: 0xabcdef, other, .. }] => {
+                assert_eq!(*other, SOURCE)
+            }
+            issues => panic!("{issues:?}"),
+        }
+    }
+
+    #[test]
+    fn time() {
+        let mut checker = PlausibilityChecker::new(PlausibilityConfig::default());
+        let mut late = record(86_399.0, 50.0, 0.0, 300.0);
+        assert!(checker.update_cat48(&late).is_empty());
+        late.time_of_day = Some(TimeOfDay { time: 86_400 * 128 });
+        assert_eq!(
+            issues(checker.update_cat48(&late)),
+            [Implausibility::TimeOfDayOutOfRange { seconds: 86_400.0 }]
+        );
+        // across midnight, then within the tolerance, then backwards
+        assert!(checker.update_cat48(&record(3.0, 50.1, 0.0, 300.0)).is_empty());
+        assert!(checker.update_cat48(&record(2.5, 50.1, 0.0, 300.0)).is_empty());
+        let north = cat34(MTYPE::NorthMarker).with_time(1.0);
+        assert_eq!(
+            issues(checker.update_cat34(&north)),
+            [Implausibility::TimeBackwards { previous: 3.0, seconds: 1.0 }]
+        );
+    }
+
+    #[test]
+    fn resync() {
+        let mut checker = PlausibilityChecker::new(PlausibilityConfig::default());
+        assert!(checker.update_cat48(&record(100.0, 50.0, 0.0, 300.0)).is_empty());
+        // a corrupt time hours ahead, then the radar time again
+        assert!(checker.update_cat48(&record(20_000.0, 50.0, 0.0, 300.0)).is_empty());
+        for seconds in [104.0, 108.0] {
+            assert_eq!(
+                issues(checker.update_cat48(&record(seconds, 50.5, 0.0, 300.0))),
+                [Implausibility::TimeBackwards { previous: 20_000.0, seconds }]
+            );
+        }
+        // the third record in a row is the new time, without the target of the corrupt time
+        assert!(checker.update_cat48(&record(112.0, 51.0, 0.0, 300.0)).is_empty());
+        assert!(checker.update_cat48(&record(116.0, 51.5, 0.0, 300.0)).is_empty());
+        assert_eq!(
+            issues(checker.update_cat48(&record(120.0, 60.0, 0.0, 300.0)))[..1],
+            [Implausibility::PositionJump {
+                key: TargetKey::ModeS(0xabcdef),
+                distance_nm: 8.5,
+                groundspeed_kt: 7650.0
+            }]
+        );
+    }
+
+    #[test]
+    fn prune() {
+        let config = PlausibilityConfig { max_age: 10.0, ..PlausibilityConfig::default() };
+        let mut checker = PlausibilityChecker::new(config);
+        checker.update_cat48(&record(100.0, 50.0, 0.0, 300.0));
+        for seconds in [104.0, 108.0, 112.0] {
+            checker.update_cat48(&record(seconds, 20.0, 0.0, 300.0).with_address(0x123456));
+        }
+        // without North Markers
+        assert_eq!(checker.targets.len(), 1);
+        assert!(checker.update_cat48(&record(116.0, 60.0, 0.0, 300.0)).is_empty());
+    }
+
+    #[test]
+    fn duplicate_address() {
+        let mut registry = SensorRegistry::new();
+        for (sic, longitude) in [(13, 8.0), (14, 9.0), (15, 8.5)] {
+            let position = Wgs84::new(50.0, longitude, 0.0);
+            registry
+                .insert(SensorConfig { position: Some(position), ..SensorConfig::new(25, sic) });
+        }
+        let mut checker =
+            PlausibilityChecker::new(PlausibilityConfig::default()).with_sensors(registry);
+        checker.update_cat48(&record(100.0, 20.0, 90.0, 300.0));
+
+        // about the same place, seen from the radar 1° east
+        let seen = record(101.0, 19.2, 270.0, 300.0).with_source(25, 14);
+        assert!(checker.update_cat48(&seen).is_empty());
+
+        // 20 NM north of a third radar between them
+        let far = record(102.0, 20.0, 0.0, 300.0).with_source(25, 15);
+        let mut others: Vec<_> = issues(checker.update_cat48(&far))
+            .into_iter()
+            .map(|issue| match issue {
+                Implausibility::DuplicateAddress { other, distance_nm, .. } => {
+                    assert!(distance_nm > 15.0);
+                    other.sic
+                }
+                issue => panic!("{issue:?}"),
+            })
+            .collect();
+        others.sort_unstable();
+        assert_eq!(others, [13, 14]);
+    }
+}
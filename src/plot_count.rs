@@ -0,0 +1,255 @@
+//! Comparison of received CAT048 plots with the I034/070 Message Count Values of the radar
+//!
+//! A radar reports in I034/070 how many target reports of each type it sent during the last
+//! antenna revolution, counted between two North crossings. `PlotCountChecker` counts the plots
+//! received from each radar between North Marker messages, and compares them with the next
+//! I034/070 from the same radar, to detect records lost between the radar and the receiver.
+
+use std::collections::HashMap;
+
+use crate::data_item::{DataSourceIdentifier, TimeOfDay};
+use crate::types::{MessageCounterTYP, MTYPE};
+use crate::{AsterixMessage, Cat34, Cat48};
+
+/// Plots of one type reported by the radar and received during a revolution
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlotCount {
+    pub typ: MessageCounterTYP,
+    /// I034/070 counter
+    pub reported: u16,
+    pub received: u32,
+}
+
+impl PlotCount {
+    /// Plots reported by the radar that weren't received
+    pub fn lost(&self) -> u32 {
+        u32::from(self.reported).saturating_sub(self.received)
+    }
+}
+
+/// Comparison of one revolution of a radar
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlotCountReport {
+    pub source: DataSourceIdentifier,
+    /// I034/030 of the record with I034/070
+    pub time_of_day: Option<TimeOfDay>,
+    /// Every CAT048 counter type in I034/070
+    pub counts: Vec<PlotCount>,
+}
+
+impl PlotCountReport {
+    /// Plots lost of every type
+    pub fn lost(&self) -> u32 {
+        self.counts.iter().map(PlotCount::lost).sum()
+    }
+
+    /// Some plots weren't received
+    pub fn has_loss(&self) -> bool {
+        self.lost() > 0
+    }
+}
+
+/// Plots per I034/070 counter type, the CAT048 target report types are the first eight
+#[derive(Debug, Clone, Default)]
+struct Counts {
+    /// A North Marker has been received, so `current` counts a whole revolution
+    synchronised: bool,
+    current: [u32; 8],
+    /// Last whole revolution, waiting for I034/070
+    completed: Option<[u32; 8]>,
+}
+
+/// Counts plots per radar, revolution and type, see the module documentation
+#[derive(Debug, Clone, Default)]
+pub struct PlotCountChecker {
+    radars: HashMap<(u8, u8), Counts>,
+}
+
+impl PlotCountChecker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Update with a record, returning the comparison when it has I034/070
+    ///
+    /// Records without I0xx/010 are ignored.
+    pub fn update(&mut self, message: &AsterixMessage) -> Option<PlotCountReport> {
+        match message {
+            AsterixMessage::Cat34(record) => self.update_cat34(record),
+            AsterixMessage::Cat48(record) => {
+                self.update_cat48(record);
+                None
+            }
+            AsterixMessage::Cat4(_) => None,
+        }
+    }
+
+    /// Count a plot
+    pub fn update_cat48(&mut self, record: &Cat48) {
+        let (Some(source), Some(descriptor)) =
+            (&record.data_source_identifier, &record.target_report_descriptor)
+        else {
+            return;
+        };
+        let counts = self.radars.entry((source.sac, source.sic)).or_default();
+        if counts.synchronised {
+            counts.current[MessageCounterTYP::from(descriptor.typ) as usize] += 1;
+        }
+    }
+
+    /// End the revolution on a North Marker, and compare it with I034/070
+    pub fn update_cat34(&mut self, record: &Cat34) -> Option<PlotCountReport> {
+        let source = record.data_source_identifier.as_ref()?;
+        let counts = self.radars.entry((source.sac, source.sic)).or_default();
+        if record.message_type.as_ref().map(|t| t.t) == Some(MTYPE::NorthMarker) {
+            if counts.synchronised {
+                counts.completed = Some(counts.current);
+            }
+            counts.synchronised = true;
+            counts.current = [0; 8];
+        }
+
+        let values = record.message_count_values.as_ref()?;
+        let received = counts.completed.take()?;
+        let counts = values
+            .counters
+            .iter()
+            .filter_map(|counter| {
+                Some(PlotCount {
+                    typ: counter.typ,
+                    reported: counter.counter,
+                    received: *received.get(counter.typ as usize)?,
+                })
+            })
+            .collect();
+        Some(PlotCountReport {
+            source: source.clone(),
+            time_of_day: record.time_of_day.clone(),
+            counts,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_item::{MessageCountValues, MessageCounter};
+    use crate::test_util::cat34;
+    use crate::AsterixPacket;
+    use deku::DekuContainerRead;
+
+    fn plot(typ: u8) -> Cat48 {
+        // I048/010 25/13, I048/020
+        let bytes = [0x30, 0x00, 0x07, 0b1010_0000, 0x19, 0x0d, typ << 5];
+        let (_, mut packet) = AsterixPacket::from_bytes((&bytes, 0)).unwrap();
+        match packet.messages.remove(0) {
+            AsterixMessage::Cat48(record) => record,
+            _ => unreachable!(),
+        }
+    }
+
+    fn north(counters: &[(MessageCounterTYP, u16)]) -> Cat34 {
+        Cat34 {
+            message_count_values: (!counters.is_empty()).then(|| MessageCountValues {
+                count: counters.len() as u8,
+                counters: counters
+                    .iter()
+                    .map(|(typ, counter)| MessageCounter { typ: *typ, counter: *counter })
+                    .collect(),
+            }),
+            ..cat34(MTYPE::NorthMarker)
+        }
+    }
+
+    #[test]
+    fn plot_count() {
+        let ssr = MessageCounterTYP::SingleSSRTargetReports;
+        let combined = MessageCounterTYP::SSRPSRTargetReports;
+        let weather = MessageCounterTYP::FilterForWeatherData;
+        let mut checker = PlotCountChecker::new();
+
+        // before the first North Marker
+        checker.update_cat48(&plot(2));
+        assert!(checker.update_cat34(&north(&[(ssr, 1)])).is_none());
+
+        for typ in [2, 2, 3, 1] {
+            checker.update_cat48(&plot(typ));
+        }
+        let report =
+            checker.update_cat34(&north(&[(ssr, 3), (combined, 1), (weather, 7)])).unwrap();
+        assert_eq!(
+            report.counts,
+            [
+                PlotCount { typ: ssr, reported: 3, received: 2 },
+                PlotCount { typ: combined, reported: 1, received: 1 },
+            ]
+        );
+        assert_eq!(report.lost(), 1);
+
+        // I034/070 in a later message
+        checker.update_cat48(&plot(2));
+        assert!(checker.update_cat34(&north(&[])).is_none());
+        checker.update_cat48(&plot(2));
+        let mut counters = north(&[(ssr, 1)]);
+        counters.message_type = None;
+        let report = checker.update_cat34(&counters).unwrap();
+        assert!(!report.has_loss());
+        assert!(checker.update_cat34(&counters).is_none());
+
+        for typ in 0..8 {
+            let counter = MessageCounterTYP::from(plot(typ).target_report_descriptor.unwrap().typ);
+            assert_eq!(counter as u8, typ);
+        }
+    }
+
+    #[test]
+    fn scan_boundary() {
+        let ssr = MessageCounterTYP::SingleSSRTargetReports;
+        let mut checker = PlotCountChecker::new();
+        checker.update_cat34(&north(&[]));
+        checker.update_cat48(&plot(2));
+        checker.update_cat48(&plot(2));
+        // plots after the North Marker belong to the next revolution, even before its I034/070
+        assert!(checker.update_cat34(&north(&[])).is_none());
+        checker.update_cat48(&plot(2));
+        let mut counters = north(&[(ssr, 2)]);
+        counters.message_type = None;
+        let report = checker.update_cat34(&counters).unwrap();
+        assert_eq!(report.counts, [PlotCount { typ: ssr, reported: 2, received: 2 }]);
+
+        let report = checker.update_cat34(&north(&[(ssr, 1)])).unwrap();
+        assert_eq!(report.counts, [PlotCount { typ: ssr, reported: 1, received: 1 }]);
+    }
+
+    #[test]
+    fn without_source() {
+        let ssr = MessageCounterTYP::SingleSSRTargetReports;
+        let mut checker = PlotCountChecker::new();
+        checker.update_cat34(&north(&[]));
+        checker.update_cat48(&plot(2));
+        checker.update_cat48(&Cat48 { data_source_identifier: None, ..plot(2) });
+        let anonymous = Cat34 { data_source_identifier: None, ..north(&[(ssr, 0)]) };
+        assert!(checker.update_cat34(&anonymous).is_none());
+
+        let report = checker.update_cat34(&north(&[(ssr, 1)])).unwrap();
+        assert_eq!(report.counts[0].received, 1);
+        assert_eq!(checker.radars.len(), 1);
+    }
+
+    #[test]
+    fn counter_reset() {
+        let ssr = MessageCounterTYP::SingleSSRTargetReports;
+        let mut checker = PlotCountChecker::new();
+        checker.update_cat34(&north(&[]));
+        for _ in 0..3 {
+            checker.update_cat48(&plot(2));
+        }
+        // a revolution without I034/070 isn't added to the next one
+        assert!(checker.update_cat34(&north(&[])).is_none());
+        checker.update_cat48(&plot(2));
+        // the radar restarted its counters, more plots were received than reported
+        let report = checker.update_cat34(&north(&[(ssr, 0)])).unwrap();
+        assert_eq!(report.counts, [PlotCount { typ: ssr, reported: 0, received: 1 }]);
+        assert!(!report.has_loss());
+    }
+}
@@ -0,0 +1,245 @@
+//! Antenna rotation timing from I034/041 Antenna Rotation Speed and North Marker messages
+//!
+//! The antenna of a rotating radar points north at the I034/030 of each North Marker message and
+//! turns at a constant rate, so the time it pointed at any azimuth can be estimated. This gives a
+//! time for plots without I048/140, and a check of the I048/140 that plots do have.
+//!
+//! Times are seconds since midnight, as in `TimeOfDay::seconds()`. The estimate is for the
+//! revolution that started at the last North Marker.
+//!
+//! A North Marker that is lost gives a period of about twice the rotation period. Periods near a
+//! multiple of the known period are split into that many revolutions, so they don't count as
+//! jitter.
+
+use deku::DekuError;
+
+use crate::data_item::TimeOfDay;
+use crate::modifier::{elapsed, SECONDS_PER_DAY};
+use crate::types::MTYPE;
+use crate::{Cat34, Cat48};
+
+/// Revolution measured between two North Marker messages
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Revolution {
+    /// Time of the North Marker that ended the revolution
+    pub north: f64,
+    /// Measured period in seconds, divided by the revolutions it spans
+    pub period: f64,
+    /// North Markers that were missed since the previous one
+    pub missed: u32,
+    /// Change of the period from the previous revolution, `None` for the first
+    pub jitter: Option<f64>,
+}
+
+/// Rotation period jitter over the measured revolutions
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Jitter {
+    /// Revolutions compared with the one before
+    pub revolutions: u32,
+    /// Root mean square change of the period, in seconds
+    pub rms: f64,
+    /// Largest change of the period, in seconds
+    pub max: f64,
+}
+
+/// Timing of one radar's antenna, see the module documentation
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RotationModel {
+    /// From I034/041 or configuration
+    nominal: Option<f64>,
+    /// I034/030 of the last North Marker
+    north: Option<f64>,
+    last: Option<Revolution>,
+    jitter_count: u32,
+    jitter_squares: f64,
+    jitter_max: f64,
+}
+
+impl RotationModel {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Use `period` seconds until I034/041 is received, such as `SensorRegistry::rotation_period`
+    pub fn with_period(mut self, period: f64) -> Self {
+        self.nominal = Some(period);
+        self
+    }
+
+    /// Rotation period in seconds, from I034/041 or configuration, else the last measured
+    pub fn period(&self) -> Option<f64> {
+        self.nominal.or(self.last.map(|r| r.period))
+    }
+
+    /// Time of the last North Marker
+    pub fn north(&self) -> Option<f64> {
+        self.north
+    }
+
+    /// Last measured revolution
+    pub fn last_revolution(&self) -> Option<Revolution> {
+        self.last
+    }
+
+    /// Period jitter so far, `None` until two revolutions have been measured
+    pub fn jitter(&self) -> Option<Jitter> {
+        (self.jitter_count > 0).then(|| Jitter {
+            revolutions: self.jitter_count,
+            rms: (self.jitter_squares / f64::from(self.jitter_count)).sqrt(),
+            max: self.jitter_max,
+        })
+    }
+
+    /// Update with a service message of the radar, returning the revolution a North Marker
+    /// completes
+    pub fn update(&mut self, record: &Cat34) -> Option<Revolution> {
+        if let Some(speed) = &record.antenna_rotation_speed {
+            self.nominal = Some(speed.seconds());
+        }
+        if record.message_type.as_ref().map(|t| t.t) != Some(MTYPE::NorthMarker) {
+            return None;
+        }
+        let north = record.time_of_day.as_ref()?.seconds();
+        let previous = self.north.replace(north)?;
+
+        let mut period = elapsed(previous, north);
+        // a North Marker at or before the previous one doesn't measure a revolution
+        if period <= 0.0 {
+            return None;
+        }
+        let revolutions = self.period().map_or(1.0, |estimate| revolutions(period, estimate));
+        period /= revolutions;
+        let jitter = self.last.map(|r| period - r.period);
+        if let Some(jitter) = jitter {
+            self.jitter_count += 1;
+            self.jitter_squares += jitter * jitter;
+            self.jitter_max = self.jitter_max.max(jitter.abs());
+        }
+        let revolution = Revolution { north, period, missed: revolutions as u32 - 1, jitter };
+        self.last = Some(revolution);
+        Some(revolution)
+    }
+
+    /// Estimated time the antenna pointed at `azimuth` degrees
+    pub fn time_at(&self, azimuth: f64) -> Option<f64> {
+        let north = self.north?;
+        let period = self.period()?;
+        Some((north + azimuth.rem_euclid(360.0) / 360.0 * period).rem_euclid(SECONDS_PER_DAY))
+    }
+
+    /// Estimated time of a plot from the azimuth of I048/040
+    pub fn estimate(&self, record: &Cat48) -> Option<f64> {
+        let polar = record.measured_position_in_polar_coordinates.as_ref()?;
+        self.time_at(polar.theta_deg())
+    }
+
+    /// Set I048/140 of a plot without one to the estimated time, returning if it was set
+    pub fn fill_time_of_day(&self, record: &mut Cat48) -> Result<bool, DekuError> {
+        if record.time_of_day.is_some() {
+            return Ok(false);
+        }
+        let Some(estimate) = self.estimate(record) else {
+            return Ok(false);
+        };
+        record.time_of_day = Some(TimeOfDay::from_seconds(estimate)?);
+        Ok(true)
+    }
+
+    /// Seconds between the I048/140 of a plot and the estimated time, `None` if either is unknown
+    pub fn time_of_day_error(&self, record: &Cat48) -> Option<f64> {
+        let time_of_day = record.time_of_day.as_ref()?.seconds();
+        Some(elapsed(self.estimate(record)?, time_of_day))
+    }
+
+    /// The I048/140 of a plot is within `tolerance` seconds of the estimated time, `None` if
+    /// either is unknown
+    pub fn is_time_of_day_consistent(&self, record: &Cat48, tolerance: f64) -> Option<bool> {
+        Some(self.time_of_day_error(record)?.abs() <= tolerance)
+    }
+}
+
+/// Revolutions in `period`, more than one if it's within a quarter revolution of a multiple of
+/// `estimate`
+fn revolutions(period: f64, estimate: f64) -> f64 {
+    let revolutions = (period / estimate).round();
+    if revolutions >= 2.0 && (period / estimate - revolutions).abs() <= 0.25 {
+        revolutions
+    } else {
+        1.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_item::AntennaRotationSpeed;
+    use crate::test_util::{cat34, cat48, tod};
+
+    fn north(seconds: f64) -> Cat34 {
+        cat34(MTYPE::NorthMarker).with_time(seconds)
+    }
+
+    #[test]
+    fn estimate() {
+        let mut model = RotationModel::new();
+        assert!(model.update(&north(86_398.0)).is_none());
+        assert_eq!(model.time_at(90.0), None);
+
+        // across midnight
+        let revolution = model.update(&north(2.0)).unwrap();
+        assert_eq!(revolution.period, 4.0);
+        assert_eq!(model.time_at(90.0), Some(3.0));
+
+        let record = Cat34 {
+            antenna_rotation_speed: Some(AntennaRotationSpeed::from_seconds(8.0).unwrap()),
+            ..Cat34::default()
+        };
+        model.update(&record);
+        assert_eq!(model.time_at(180.0), Some(6.0));
+
+        let mut record = cat48().with_polar(0.0, 270.0);
+        assert!(model.fill_time_of_day(&mut record).unwrap());
+        assert_eq!(record.time_of_day.as_ref().unwrap().seconds(), 8.0);
+        assert!(!model.fill_time_of_day(&mut record).unwrap());
+
+        record.time_of_day = Some(tod(8.5));
+        assert_eq!(model.time_of_day_error(&record), Some(0.5));
+        assert_eq!(model.is_time_of_day_consistent(&record, 0.25), Some(false));
+    }
+
+    #[test]
+    fn jitter() {
+        let mut model = RotationModel::new();
+        for seconds in [0.0, 4.0, 8.0, 12.5, 16.5] {
+            model.update(&north(seconds));
+        }
+        assert_eq!(model.last_revolution().unwrap().jitter, Some(-0.5));
+        let jitter = model.jitter().unwrap();
+        assert_eq!(jitter.revolutions, 3);
+        assert_eq!(jitter.max, 0.5);
+        assert!((jitter.rms - (0.5f64 / 3.0).sqrt()).abs() < 1e-9);
+        assert_eq!(model.period(), Some(4.0));
+    }
+
+    #[test]
+    fn dropped_north_marker() {
+        let mut model = RotationModel::new();
+        for seconds in [0.0, 4.0, 8.0] {
+            model.update(&north(seconds));
+        }
+        let revolution = model.update(&north(16.25)).unwrap();
+        assert_eq!(revolution.missed, 1);
+        assert_eq!(revolution.period, 4.125);
+        assert_eq!(model.jitter().unwrap().max, 0.125);
+
+        // not near a multiple of the period
+        let revolution = model.update(&north(22.5)).unwrap();
+        assert_eq!(revolution.missed, 0);
+        assert_eq!(revolution.period, 6.25);
+
+        let mut model = RotationModel::new().with_period(4.0);
+        model.update(&north(0.0));
+        let revolution = model.update(&north(12.0)).unwrap();
+        assert_eq!((revolution.missed, revolution.period, revolution.jitter), (2, 4.0, None));
+    }
+}
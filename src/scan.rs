@@ -0,0 +1,367 @@
+//! Assembly of antenna scans from CAT034 north markers and sector crossings
+//!
+//! A radar sends a North Marker message when the antenna passes north, and a Sector Crossing
+//! message with I034/020 when it enters each sector. `ScanAssembler` uses these to group the
+//! CAT048 plots of one radar into sectors and scans.
+//!
+//! ```rust
+//! use asterix::scan::{Assembled, ScanAssembler};
+//! # use asterix::AsterixMessage;
+//! # let messages: Vec<AsterixMessage> = vec![];
+//!
+//! let mut assembler = ScanAssembler::new(25, 13, 32).unwrap();
+//! for message in messages {
+//!     for assembled in assembler.push(message) {
+//!         match assembled {
+//!             Assembled::Sector(sector) => println!("sector {}", sector.number),
+//!             Assembled::Scan(scan) => println!("scan: {} plots", scan.plots().count()),
+//!         }
+//!     }
+//! }
+//! ```
+
+use crate::data_item::DataSourceIdentifier;
+use crate::error::Error;
+use crate::types::MTYPE;
+use crate::{AsterixMessage, Cat34, Cat48};
+
+/// Plots and service messages between two sector messages
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sector {
+    /// I034/020 of the Sector Crossing message, 0 for the North Marker
+    pub number: u8,
+    /// North Marker or Sector Crossing message that started the sector
+    pub start: Cat34,
+    pub plots: Vec<Cat48>,
+    /// Other CAT034 messages received during the sector, such as jamming strobes
+    pub service: Vec<Cat34>,
+}
+
+/// Problem with the sector messages of a scan
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScanIssue {
+    /// Sector messages between sector `after` and sector `before` were not received
+    MissingSectors { after: u8, before: u8 },
+    /// Sector `number` was received after sector `previous`, which is at the same or a later
+    /// azimuth
+    OutOfOrder { previous: u8, number: u8 },
+    /// Sector Crossing message without I034/020, assumed to be the next sector
+    NoSectorNumber { previous: u8 },
+}
+
+/// Sectors of one antenna revolution
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Scan {
+    /// `None` for the partial scan before the first North Marker
+    pub north_marker: Option<Cat34>,
+    pub sectors: Vec<Sector>,
+    pub issues: Vec<ScanIssue>,
+}
+
+impl Scan {
+    /// Plots of every sector
+    pub fn plots(&self) -> impl Iterator<Item = &Cat48> {
+        self.sectors.iter().flat_map(|s| s.plots.iter())
+    }
+
+    /// Service messages of every sector, including the sector messages
+    pub fn service(&self) -> impl Iterator<Item = &Cat34> {
+        self.sectors.iter().flat_map(|s| core::iter::once(&s.start).chain(s.service.iter()))
+    }
+
+    /// Started by a North Marker, and every sector message was received in order
+    pub fn is_complete(&self) -> bool {
+        self.north_marker.is_some() && self.issues.is_empty()
+    }
+}
+
+/// Completed sector, which stays in its scan
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SectorSummary {
+    /// I034/020 of the Sector Crossing message, 0 for the North Marker
+    pub number: u8,
+    /// Index in `Scan.sectors` of its scan, which is `ScanAssembler::current()` until the scan is
+    /// completed
+    pub index: usize,
+    /// Number of plots
+    pub plots: usize,
+    /// Number of other CAT034 messages
+    pub service: usize,
+}
+
+/// Completed sector or scan
+#[derive(Debug, Clone, PartialEq)]
+pub enum Assembled {
+    Sector(SectorSummary),
+    Scan(Box<Scan>),
+}
+
+/// Groups the records of one radar into sectors and scans
+///
+/// Records from other data sources are ignored, records without I0xx/010 are assumed to be from
+/// this radar. Plots received before the first sector message can't be placed and are counted in
+/// `skipped()`.
+#[derive(Debug, Clone)]
+pub struct ScanAssembler {
+    source: DataSourceIdentifier,
+    sectors: u16,
+    scan: Option<Scan>,
+    sector: Option<Sector>,
+    skipped: usize,
+}
+
+impl ScanAssembler {
+    /// Assemble scans of SAC/SIC, which has `sectors` sectors per revolution
+    pub fn new(sac: u8, sic: u8, sectors: u16) -> Result<Self, Error> {
+        if !(1..=256).contains(&sectors) {
+            return Err(Error::InvalidParam(format!(
+                "scan assembler: {sectors} sectors is outside of 1..=256"
+            )));
+        }
+        Ok(Self {
+            source: DataSourceIdentifier { sac, sic },
+            sectors,
+            scan: None,
+            sector: None,
+            skipped: 0,
+        })
+    }
+
+    /// Plots received before the first sector message
+    pub fn skipped(&self) -> usize {
+        self.skipped
+    }
+
+    /// Scan being assembled
+    pub fn current(&self) -> Option<&Scan> {
+        self.scan.as_ref()
+    }
+
+    /// Add the next record, returning the sectors and scans it completes
+    pub fn push(&mut self, message: AsterixMessage) -> Vec<Assembled> {
+        if message.data_source_identifier().is_some_and(|s| *s != self.source) {
+            return vec![];
+        }
+
+        match message {
+            AsterixMessage::Cat48(plot) => {
+                match &mut self.sector {
+                    Some(sector) => sector.plots.push(plot),
+                    None => self.skipped += 1,
+                }
+                vec![]
+            }
+            AsterixMessage::Cat34(service) => match service.message_type.as_ref().map(|t| t.t) {
+                Some(MTYPE::NorthMarker) => self.north_marker(service),
+                Some(MTYPE::SectorCrossing) => self.sector_crossing(service),
+                _ => {
+                    if let Some(sector) = &mut self.sector {
+                        sector.service.push(service);
+                    }
+                    vec![]
+                }
+            },
+            AsterixMessage::Cat4(_) => vec![],
+        }
+    }
+
+    /// Complete the current sector and scan at the end of the stream
+    pub fn finish(&mut self) -> Vec<Assembled> {
+        let mut assembled = vec![];
+        self.close_sector(&mut assembled);
+        if let Some(scan) = self.scan.take() {
+            assembled.push(Assembled::Scan(Box::new(scan)));
+        }
+        assembled
+    }
+
+    /// Index of sector `number` within a revolution
+    fn index(&self, number: u8) -> u16 {
+        ((u32::from(number) * u32::from(self.sectors) + 128) / 256) as u16 % self.sectors
+    }
+
+    fn close_sector(&mut self, assembled: &mut Vec<Assembled>) {
+        if let Some(sector) = self.sector.take() {
+            let sectors = &mut self.scan.get_or_insert_with(Scan::default).sectors;
+            assembled.push(Assembled::Sector(SectorSummary {
+                number: sector.number,
+                index: sectors.len(),
+                plots: sector.plots.len(),
+                service: sector.service.len(),
+            }));
+            sectors.push(sector);
+        }
+    }
+
+    fn north_marker(&mut self, service: Cat34) -> Vec<Assembled> {
+        let mut assembled = vec![];
+        // the last sector of the revolution ends at north
+        if let Some(previous) = self.sector.as_ref().map(|s| s.number) {
+            if self.index(previous) + 1 != self.sectors {
+                self.scan
+                    .get_or_insert_with(Scan::default)
+                    .issues
+                    .push(ScanIssue::MissingSectors { after: previous, before: 0 });
+            }
+        }
+        self.close_sector(&mut assembled);
+        if let Some(scan) = self.scan.take() {
+            assembled.push(Assembled::Scan(Box::new(scan)));
+        }
+        self.scan = Some(Scan { north_marker: Some(service.clone()), ..Scan::default() });
+        self.sector = Some(Sector { number: 0, start: service, plots: vec![], service: vec![] });
+        assembled
+    }
+
+    fn sector_crossing(&mut self, service: Cat34) -> Vec<Assembled> {
+        let mut assembled = vec![];
+        let number = service.sector_number.as_ref().map(|s| s.num);
+        let Some(previous) = self.sector.as_ref() else {
+            // first sector message of the stream
+            let number = number.unwrap_or(0);
+            self.sector = Some(Sector { number, start: service, plots: vec![], service: vec![] });
+            return assembled;
+        };
+
+        let issue = match number {
+            // some radars also send a crossing of sector 0 with the North Marker
+            Some(number)
+                if self.index(number) == 0
+                    && previous.number == 0
+                    && previous.plots.is_empty()
+                    && previous.start.message_type.as_ref().map(|t| t.t)
+                        == Some(MTYPE::NorthMarker) =>
+            {
+                if let Some(sector) = &mut self.sector {
+                    sector.service.push(service);
+                }
+                return assembled;
+            }
+            Some(number) => {
+                let (from, to) = (self.index(previous.number), self.index(number));
+                if to <= from {
+                    Some(ScanIssue::OutOfOrder { previous: previous.number, number })
+                } else if to > from + 1 {
+                    Some(ScanIssue::MissingSectors { after: previous.number, before: number })
+                } else {
+                    None
+                }
+            }
+            None => Some(ScanIssue::NoSectorNumber { previous: previous.number }),
+        };
+        let number = number.unwrap_or_else(|| {
+            let next = u32::from((self.index(previous.number) + 1) % self.sectors);
+            (next * 256 / u32::from(self.sectors)) as u8
+        });
+
+        self.close_sector(&mut assembled);
+        if let Some(issue) = issue {
+            self.scan.get_or_insert_with(Scan::default).issues.push(issue);
+        }
+        self.sector = Some(Sector { number, start: service, plots: vec![], service: vec![] });
+        assembled
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{cat34, cat48};
+
+    fn service(t: MTYPE, number: Option<u8>) -> AsterixMessage {
+        let service = cat34(t);
+        AsterixMessage::Cat34(match number {
+            Some(num) => service.with_sector(num),
+            None => service,
+        })
+    }
+
+    fn plot(sic: u8) -> AsterixMessage {
+        AsterixMessage::Cat48(cat48().with_source(25, sic))
+    }
+
+    fn scans(assembled: Vec<Assembled>) -> Vec<Scan> {
+        assembled
+            .into_iter()
+            .filter_map(|a| match a {
+                Assembled::Scan(s) => Some(*s),
+                Assembled::Sector(_) => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn assemble() {
+        let mut assembler = ScanAssembler::new(25, 13, 4).unwrap();
+        let mut assembled = vec![];
+        let messages = [
+            plot(13),
+            service(MTYPE::NorthMarker, None),
+            plot(13),
+            plot(14),
+            service(MTYPE::SectorCrossing, Some(64)),
+            plot(13),
+            service(MTYPE::JammingStrobe, None),
+            service(MTYPE::SectorCrossing, Some(128)),
+            service(MTYPE::SectorCrossing, Some(192)),
+            plot(13),
+            service(MTYPE::NorthMarker, None),
+            service(MTYPE::SectorCrossing, Some(0)),
+            plot(13),
+        ];
+        for message in messages {
+            assembled.extend(assembler.push(message));
+        }
+        assert_eq!(assembler.skipped(), 1);
+
+        let sectors: Vec<(u8, usize, usize, usize)> = assembled
+            .iter()
+            .filter_map(|a| match a {
+                Assembled::Sector(s) => Some((s.number, s.index, s.plots, s.service)),
+                Assembled::Scan(_) => None,
+            })
+            .collect();
+        assert_eq!(sectors, [(0, 0, 1, 0), (64, 1, 1, 1), (128, 2, 0, 0), (192, 3, 1, 0)]);
+
+        let scans = scans(assembled);
+        assert_eq!(scans.len(), 1);
+        assert!(scans[0].is_complete());
+        assert_eq!(scans[0].plots().count(), 3);
+        assert_eq!(scans[0].service().count(), 5);
+
+        let scans = self::scans(assembler.finish());
+        assert_eq!(scans[0].sectors[0].service.len(), 1);
+        assert_eq!(scans[0].plots().count(), 1);
+    }
+
+    #[test]
+    fn issues() {
+        let mut assembler = ScanAssembler::new(25, 13, 4).unwrap();
+        let messages = [
+            service(MTYPE::NorthMarker, None),
+            service(MTYPE::SectorCrossing, Some(128)),
+            service(MTYPE::SectorCrossing, Some(64)),
+            service(MTYPE::SectorCrossing, None),
+            service(MTYPE::NorthMarker, None),
+        ];
+        let mut assembled = vec![];
+        for message in messages {
+            assembled.extend(assembler.push(message));
+        }
+        let scans = scans(assembled);
+        assert!(!scans[0].is_complete());
+        assert_eq!(
+            scans[0].issues,
+            [
+                ScanIssue::MissingSectors { after: 0, before: 128 },
+                ScanIssue::OutOfOrder { previous: 128, number: 64 },
+                ScanIssue::NoSectorNumber { previous: 64 },
+                ScanIssue::MissingSectors { after: 128, before: 0 },
+            ]
+        );
+        assert_eq!(
+            ScanAssembler::new(25, 13, 0).unwrap_err(),
+            Error::InvalidParam("scan assembler: 0 sectors is outside of 1..=256".to_string())
+        );
+    }
+}
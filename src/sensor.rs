@@ -0,0 +1,478 @@
+//! Registry of per-sensor configuration, keyed by SAC/SIC
+//!
+//! Each radar has its own edition, Special Purpose Field layout, position and rotation period.
+//! `SensorRegistry` keeps these in one place, so that decoding and position conversion can look
+//! them up from the `DataSourceIdentifier` of a record.
+//!
+//! With the `toml` or `json` feature, the registry can be loaded from a file:
+//! ```toml
+//! [[sensor]]
+//! sac = 25
+//! sic = 13
+//! name = "Frankfurt"
+//! cat48_edition = "1.21"
+//! cat34_edition = "1.27"
+//! rotation_period = 4.0
+//! position = { latitude = 50.0379, longitude = 8.5622, height = 120.0 }
+//! sp_fields = [{ name = "quality", octets = 2 }]
+//! ```
+
+use std::collections::HashMap;
+
+use deku::DekuError;
+
+use crate::data_item::{DataSourceIdentifier, SpecialPurposeField};
+use crate::edition::{Cat34Edition, Cat48Edition, Editions};
+use crate::error::Error;
+use crate::geodesy::{self, Wgs84};
+use crate::{AsterixMessage, AsterixPacket, Cat34, Cat48};
+
+/// Subfield of a sensor's Special Purpose Field
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub struct SpField {
+    pub name: String,
+    /// Length in octets
+    pub octets: usize,
+}
+
+/// Configuration of a single sensor
+#[derive(Debug, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub struct SensorConfig {
+    /// System Area Code
+    pub sac: u8,
+    /// System Identification Code
+    pub sic: u8,
+    pub name: Option<String>,
+    pub cat48_edition: Option<Cat48Edition>,
+    pub cat34_edition: Option<Cat34Edition>,
+    pub position: Option<Wgs84>,
+    /// Expected antenna rotation period in seconds
+    pub rotation_period: Option<f64>,
+    /// Layout of the Special Purpose Field, in order
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub sp_fields: Vec<SpField>,
+}
+
+impl SensorConfig {
+    pub fn new(sac: u8, sic: u8) -> Self {
+        Self { sac, sic, ..Self::default() }
+    }
+
+    /// Split `sp` into the named subfields of `sp_fields`, failing if the lengths don't match
+    pub fn split_sp<'a>(
+        &self,
+        sp: &'a SpecialPurposeField,
+    ) -> Result<Vec<(&str, &'a [u8])>, DekuError> {
+        let expected: usize = self.sp_fields.iter().map(|f| f.octets).sum();
+        if expected != sp.data.len() {
+            return Err(DekuError::Parse(format!(
+                "SP of {}/{}: expected {expected} octets, got {}",
+                self.sac,
+                self.sic,
+                sp.data.len()
+            )));
+        }
+        let mut rest = sp.data.as_slice();
+        let mut fields = Vec::with_capacity(self.sp_fields.len());
+        for field in &self.sp_fields {
+            let (value, new_rest) = rest.split_at(field.octets);
+            fields.push((field.name.as_str(), value));
+            rest = new_rest;
+        }
+        Ok(fields)
+    }
+}
+
+/// Error loading a `SensorRegistry` from a file
+#[cfg(any(feature = "toml", feature = "json"))]
+#[derive(Debug)]
+pub enum RegistryError {
+    /// The file couldn't be read
+    Io(std::io::Error),
+    #[cfg(feature = "toml")]
+    Toml(toml::de::Error),
+    #[cfg(feature = "json")]
+    Json(serde_json::Error),
+    /// The file extension is neither `.toml` nor `.json`, or its feature isn't enabled
+    UnsupportedFileType(std::path::PathBuf),
+    /// More than one entry for the same SAC/SIC
+    DuplicateSensor { sac: u8, sic: u8 },
+}
+
+#[cfg(any(feature = "toml", feature = "json"))]
+impl core::fmt::Display for RegistryError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "sensor registry: {e}"),
+            #[cfg(feature = "toml")]
+            Self::Toml(e) => write!(f, "sensor registry: {e}"),
+            #[cfg(feature = "json")]
+            Self::Json(e) => write!(f, "sensor registry: {e}"),
+            Self::UnsupportedFileType(path) => {
+                write!(f, "sensor registry: {}: unsupported file type", path.display())
+            }
+            Self::DuplicateSensor { sac, sic } => {
+                write!(f, "sensor registry: more than one entry for {sac}/{sic}")
+            }
+        }
+    }
+}
+
+#[cfg(any(feature = "toml", feature = "json"))]
+impl std::error::Error for RegistryError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            #[cfg(feature = "toml")]
+            Self::Toml(e) => Some(e),
+            #[cfg(feature = "json")]
+            Self::Json(e) => Some(e),
+            Self::UnsupportedFileType(_) | Self::DuplicateSensor { .. } => None,
+        }
+    }
+}
+
+#[cfg(any(feature = "toml", feature = "json"))]
+impl From<std::io::Error> for RegistryError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+#[cfg(feature = "toml")]
+impl From<toml::de::Error> for RegistryError {
+    fn from(e: toml::de::Error) -> Self {
+        Self::Toml(e)
+    }
+}
+
+#[cfg(feature = "json")]
+impl From<serde_json::Error> for RegistryError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::Json(e)
+    }
+}
+
+/// Configuration of every known sensor
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SensorRegistry {
+    sensors: HashMap<(u8, u8), SensorConfig>,
+}
+
+#[cfg(any(feature = "toml", feature = "json"))]
+#[derive(serde::Deserialize)]
+struct RegistryFile {
+    #[serde(default)]
+    sensor: Vec<SensorConfig>,
+}
+
+#[cfg(any(feature = "toml", feature = "json"))]
+impl TryFrom<RegistryFile> for SensorRegistry {
+    type Error = RegistryError;
+
+    fn try_from(file: RegistryFile) -> Result<Self, Self::Error> {
+        let mut registry = Self::new();
+        for sensor in file.sensor {
+            let (sac, sic) = (sensor.sac, sensor.sic);
+            if registry.insert(sensor).is_some() {
+                return Err(RegistryError::DuplicateSensor { sac, sic });
+            }
+        }
+        Ok(registry)
+    }
+}
+
+impl SensorRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add or replace the configuration of `sensor.sac`/`sensor.sic`
+    pub fn insert(&mut self, sensor: SensorConfig) -> Option<SensorConfig> {
+        self.sensors.insert((sensor.sac, sensor.sic), sensor)
+    }
+
+    pub fn get(&self, source: &DataSourceIdentifier) -> Option<&SensorConfig> {
+        self.sensors.get(&(source.sac, source.sic))
+    }
+
+    pub fn get_mut(&mut self, source: &DataSourceIdentifier) -> Option<&mut SensorConfig> {
+        self.sensors.get_mut(&(source.sac, source.sic))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &SensorConfig> {
+        self.sensors.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.sensors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sensors.is_empty()
+    }
+
+    /// Position of the sensor, if configured or learned from I034/120
+    pub fn position(&self, source: &DataSourceIdentifier) -> Option<Wgs84> {
+        self.get(source).and_then(|s| s.position)
+    }
+
+    /// WGS-84 position of the plot of `record`, using the position of its sensor
+    ///
+    /// `None` if the sensor position isn't known, or the record has no I048/040 or height
+    pub fn plot_position(&self, record: &Cat48) -> Option<Result<Wgs84, Error>> {
+        let radar = self.position(record.data_source_identifier.as_ref()?)?;
+        geodesy::plot_position(record, &radar)
+    }
+
+    /// Expected antenna rotation period in seconds
+    pub fn rotation_period(&self, source: &DataSourceIdentifier) -> Option<f64> {
+        self.get(source).and_then(|s| s.rotation_period)
+    }
+
+    /// Editions for validating, using the latest edition for unknown sensors
+    pub fn editions(&self) -> Editions {
+        let mut editions = Editions::default();
+        for sensor in self.iter() {
+            if let Some(edition) = sensor.cat48_edition {
+                editions = editions.with_cat48_source(sensor.sac, sensor.sic, edition);
+            }
+            if let Some(edition) = sensor.cat34_edition {
+                editions = editions.with_cat34_source(sensor.sac, sensor.sic, edition);
+            }
+        }
+        editions
+    }
+
+    /// Decode a packet, validate it against the edition of each record's sensor, and learn sensor
+    /// positions from I034/120
+    pub fn decode(&mut self, bytes: &[u8]) -> Result<AsterixPacket, DekuError> {
+        let packet = self.editions().decode_validated(bytes)?;
+        self.update(&packet);
+        Ok(packet)
+    }
+
+    /// Learn sensor positions and rotation periods from CAT034 messages, adding sensors that
+    /// aren't configured yet
+    pub fn update(&mut self, packet: &AsterixPacket) {
+        for message in &packet.messages {
+            if let AsterixMessage::Cat34(message) = message {
+                self.update_cat34(message);
+            }
+        }
+    }
+
+    /// Learn the position and rotation period of the sensor from I034/120 and I034/041, adding
+    /// it if it isn't configured yet
+    ///
+    /// A position that is already known, such as a surveyed one from the configuration, is kept,
+    /// since I034/120 is less precise. The rotation period follows I034/041.
+    pub fn update_cat34(&mut self, record: &Cat34) {
+        let Some(source) = &record.data_source_identifier else {
+            return;
+        };
+        let sensor = self
+            .sensors
+            .entry((source.sac, source.sic))
+            .or_insert_with(|| SensorConfig::new(source.sac, source.sic));
+        if let Some(position) = &record.three_d_position_of_data_source {
+            sensor.position.get_or_insert_with(|| position.into());
+        }
+        if let Some(speed) = &record.antenna_rotation_speed {
+            sensor.rotation_period = Some(speed.seconds());
+        }
+    }
+
+    /// Load from a TOML string with a `[[sensor]]` table per sensor, failing if two tables have
+    /// the same SAC/SIC
+    #[cfg(feature = "toml")]
+    pub fn from_toml_str(s: &str) -> Result<Self, RegistryError> {
+        let file: RegistryFile = toml::from_str(s)?;
+        file.try_into()
+    }
+
+    /// Load from a JSON string of the form `{"sensor": [...]}`, failing if two sensors have the
+    /// same SAC/SIC
+    #[cfg(feature = "json")]
+    pub fn from_json_str(s: &str) -> Result<Self, RegistryError> {
+        let file: RegistryFile = serde_json::from_str(s)?;
+        file.try_into()
+    }
+
+    /// Load from a `.toml` or `.json` file
+    #[cfg(any(feature = "toml", feature = "json"))]
+    pub fn load(path: impl AsRef<std::path::Path>) -> Result<Self, RegistryError> {
+        let path = path.as_ref();
+        let s = std::fs::read_to_string(path)?;
+        match path.extension().and_then(|e| e.to_str()) {
+            #[cfg(feature = "toml")]
+            Some("toml") => Self::from_toml_str(&s),
+            #[cfg(feature = "json")]
+            Some("json") => Self::from_json_str(&s),
+            _ => Err(RegistryError::UnsupportedFileType(path.to_path_buf())),
+        }
+    }
+}
+
+impl FromIterator<SensorConfig> for SensorRegistry {
+    fn from_iter<T: IntoIterator<Item = SensorConfig>>(iter: T) -> Self {
+        let mut registry = Self::new();
+        for sensor in iter {
+            registry.insert(sensor);
+        }
+        registry
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_with_sensor_edition() {
+        // I048/010 25/13, I048/020 with the second extent
+        let bytes = [0x30, 0x00, 0x09, 0b1010_0000, 0x19, 0x0d, 0x01, 0x01, 0b1100_0000];
+        let mut registry = SensorRegistry::new();
+        assert!(registry.decode(&bytes).is_ok());
+
+        registry.insert(SensorConfig {
+            cat48_edition: Some(Cat48Edition::V1_21),
+            ..SensorConfig::new(25, 13)
+        });
+        assert!(registry.decode(&bytes).is_err());
+    }
+
+    #[test]
+    fn learn_from_cat34() {
+        // I034/010 25/13, I034/041 4 s, I034/120
+        let mut bytes = vec![0x22, 0x00, 0x11, 0b1000_1001, 0b0001_0000, 0x19, 0x0d, 0x02, 0x00];
+        bytes.extend([0x00, 0x64, 0x23, 0x95, 0x1f, 0x06, 0x16, 0xb3]);
+        let mut registry = SensorRegistry::new();
+        registry.decode(&bytes).unwrap();
+        let source = DataSourceIdentifier { sac: 25, sic: 13 };
+        assert_eq!(registry.rotation_period(&source), Some(4.0));
+        let position = registry.position(&source).unwrap();
+        assert_eq!(position.height, 100.0);
+        assert!((position.latitude - 50.0379).abs() < 1e-4);
+        assert!((position.longitude - 8.5622).abs() < 1e-4);
+
+        // a configured position is kept
+        let surveyed = Wgs84::new(50.03791, 8.56219, 120.0);
+        registry.insert(SensorConfig { position: Some(surveyed), ..SensorConfig::new(25, 13) });
+        registry.decode(&bytes).unwrap();
+        assert_eq!(registry.position(&source), Some(surveyed));
+        assert_eq!(registry.rotation_period(&source), Some(4.0));
+    }
+
+    #[test]
+    fn plot_position() {
+        use crate::data_item::{
+            FlightLevelInBinaryRepresentation, MeasuredPositionInPolarCoordinates,
+        };
+        use crate::types::{G, V};
+
+        let source = DataSourceIdentifier { sac: 25, sic: 13 };
+        let mut record = Cat48 {
+            data_source_identifier: Some(DataSourceIdentifier { sac: 25, sic: 13 }),
+            measured_position_in_polar_coordinates: Some(MeasuredPositionInPolarCoordinates {
+                rho: 100 * 256,
+                theta: 0,
+            }),
+            ..Cat48::default()
+        };
+        let mut registry = SensorRegistry::new();
+        registry.insert(SensorConfig {
+            position: Some(Wgs84::new(50.0, 8.0, 100.0)),
+            ..SensorConfig::new(source.sac, source.sic)
+        });
+        // no height
+        assert!(registry.plot_position(&record).is_none());
+
+        record.flight_level_in_binary_repre = Some(FlightLevelInBinaryRepresentation {
+            v: V::CodeValidated,
+            g: G::Default,
+            flight_level: 350 * 4,
+        });
+        let position = registry.plot_position(&record).unwrap().unwrap();
+        assert!((position.height - 10_668.0).abs() < 1e-3);
+        assert!((position.longitude - 8.0).abs() < 1e-9);
+        assert!(position.latitude > 51.6 && position.latitude < 51.7);
+    }
+
+    #[test]
+    fn split_sp() {
+        let mut sensor = SensorConfig::new(1, 2);
+        sensor.sp_fields = vec![
+            SpField { name: "a".to_string(), octets: 1 },
+            SpField { name: "b".to_string(), octets: 2 },
+        ];
+        let sp = SpecialPurposeField { len: 4, data: vec![0x01, 0x02, 0x03] };
+        assert_eq!(sensor.split_sp(&sp).unwrap(), [("a", &[0x01][..]), ("b", &[0x02, 0x03][..])]);
+        let sp = SpecialPurposeField { len: 3, data: vec![0x01, 0x02] };
+        assert!(sensor.split_sp(&sp).is_err());
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn from_toml() {
+        let registry = SensorRegistry::from_toml_str(
+            r#"
+            [[sensor]]
+            sac = 25
+            sic = 13
+            name = "Frankfurt"
+            cat48_edition = "1.21"
+            rotation_period = 4.0
+            position = { latitude = 50.0379, longitude = 8.5622, height = 120.0 }
+            sp_fields = [{ name = "quality", octets = 2 }]
+
+            [[sensor]]
+            sac = 25
+            sic = 14
+            "#,
+        )
+        .unwrap();
+        assert_eq!(registry.len(), 2);
+        let sensor = registry.get(&DataSourceIdentifier { sac: 25, sic: 13 }).unwrap();
+        assert_eq!(sensor.name.as_deref(), Some("Frankfurt"));
+        assert_eq!(sensor.cat48_edition, Some(Cat48Edition::V1_21));
+        assert_eq!(sensor.position.unwrap().height, 120.0);
+        assert_eq!(sensor.sp_fields[0].octets, 2);
+        assert!(SensorRegistry::from_toml_str("[[sensor]]\nsac = 256\nsic = 1").is_err());
+        // SAC and SIC are required
+        let err = SensorRegistry::from_toml_str("[[sensor]]\nsac = 25").unwrap_err();
+        assert!(matches!(err, RegistryError::Toml(_)));
+
+        let twice = "[[sensor]]\nsac = 25\nsic = 13\n[[sensor]]\nsac = 25\nsic = 13";
+        let err = SensorRegistry::from_toml_str(twice).unwrap_err();
+        assert!(matches!(err, RegistryError::DuplicateSensor { sac: 25, sic: 13 }));
+        assert_eq!(err.to_string(), "sensor registry: more than one entry for 25/13");
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn from_json() {
+        let registry = SensorRegistry::from_json_str(
+            r#"{"sensor": [{"sac": 1, "sic": 2, "cat34_edition": "1.26"}]}"#,
+        )
+        .unwrap();
+        let sensor = registry.get(&DataSourceIdentifier { sac: 1, sic: 2 }).unwrap();
+        assert_eq!(sensor.cat34_edition, Some(Cat34Edition::V1_26));
+        assert!(SensorRegistry::from_json_str(r#"{"sensor": [{"cat48_edition": "2.0"}]}"#).is_err());
+        assert!(SensorRegistry::from_json_str(r#"{"sensor": [{"sic": 2}]}"#).is_err());
+        let twice = r#"{"sensor": [{"sac": 1, "sic": 2}, {"sac": 1, "sic": 2}]}"#;
+        let err = SensorRegistry::from_json_str(twice).unwrap_err();
+        assert!(matches!(err, RegistryError::DuplicateSensor { sac: 1, sic: 2 }));
+    }
+
+    #[cfg(any(feature = "toml", feature = "json"))]
+    #[test]
+    fn load_errors() {
+        let err = SensorRegistry::load("/nonexistent/sensors.toml").unwrap_err();
+        assert!(matches!(&err, RegistryError::Io(e) if e.kind() == std::io::ErrorKind::NotFound));
+        let err = SensorRegistry::load(file!()).unwrap_err();
+        assert!(matches!(err, RegistryError::UnsupportedFileType(_)));
+    }
+}
@@ -0,0 +1,623 @@
+//! Short Term Conflict Alert on CAT048 tracks, reported as CAT004 STCA messages
+//!
+//! `Stca` keeps the last I048/042 position, I048/200 velocity and I048/090 flight level of each
+//! track, and at each update predicts the separation between the updated track and every other
+//! track of the same radar, in steps over the look-ahead time. Tracks move in a straight line at
+//! their I048/200 velocity, and climb at the flight level rate between their last two records.
+//! A pair is in conflict when both the horizontal and the vertical separation are predicted to be
+//! below the thresholds at the same time.
+//!
+//! `StcaAlert` follows the CAT004 data items of an STCA message, and `StcaAlert::to_cat4` encodes
+//! it as a `Cat4` record. Conflicts are reported when they start, at each update of either track
+//! while they last, and when they end. This is a reference for lab testing, without the filters
+//! of an operational safety net.
+
+use std::collections::HashMap;
+
+use deku::DekuError;
+
+use crate::data_item::{
+    AircraftIdentificationAndCharacteristics, AlertIdentifier, AlertStatus,
+    ConflictTimingAndSeparation, DataSourceIdentifier, SafetyNetMessageType, SdpsIdentifier,
+    TimeOfDay,
+};
+use crate::error::{self, Error};
+use crate::geodesy::METERS_PER_NM;
+use crate::modifier::{self, elapsed};
+use crate::types::{Callsign, SafetyNetMTYPE, Squawk};
+use crate::{Cat4, Cat48};
+
+/// LSB of I004/020 and the I004/070 times in seconds
+const TIME_LSB: f64 = 1.0 / 128.0;
+/// LSB of the I004/070 horizontal separations in m
+const HORIZONTAL_LSB: f64 = 0.5;
+/// LSB of the I004/070 vertical separations in ft
+const VERTICAL_LSB: f64 = 25.0;
+
+/// SAC, SIC and I048/161 of a track
+pub type TrackId = (u8, u8, u16);
+
+/// Parameters of `Stca`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StcaConfig {
+    /// Horizontal separation in NM below which a pair may be in conflict
+    pub horizontal_nm: f64,
+    /// Vertical separation in ft below which a pair may be in conflict
+    pub vertical_ft: f64,
+    /// Seconds ahead that separation is predicted
+    pub look_ahead: f64,
+    /// Seconds between predictions
+    pub step: f64,
+    /// Tracks not updated for this many seconds are dropped, ending their conflicts
+    pub max_age: f64,
+}
+
+impl Default for StcaConfig {
+    fn default() -> Self {
+        Self {
+            horizontal_nm: 3.0,
+            vertical_ft: 1000.0,
+            look_ahead: 120.0,
+            step: 1.0,
+            max_age: 20.0,
+        }
+    }
+}
+
+/// Track of a conflict, as in I004/170 Aircraft Identification and Characteristics
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConflictTrack {
+    /// System Area Code of the radar
+    pub sac: u8,
+    /// System Identification Code of the radar
+    pub sic: u8,
+    /// I048/161
+    pub track_number: u16,
+    /// I048/220
+    pub address: Option<u32>,
+    /// I048/240 of the last record with one
+    pub callsign: Option<Callsign>,
+    /// I048/070 of the last record with one
+    pub squawk: Option<Squawk>,
+    /// I048/090
+    pub flight_level: f64,
+}
+
+impl ConflictTrack {
+    /// I004/170 or I004/171, failing for a callsign of more than seven characters
+    pub fn i004_170(&self) -> Result<AircraftIdentificationAndCharacteristics, DekuError> {
+        let ai = match self.callsign.map(|c| c.trimmed()) {
+            Some(callsign) if callsign.len() > 7 => {
+                return Err(DekuError::InvalidParam(format!(
+                    "I004/170 AI: {callsign} is longer than seven characters"
+                )));
+            }
+            Some(callsign) => {
+                let mut ai = [b' '; 7];
+                ai[..callsign.len()].copy_from_slice(callsign.as_bytes());
+                Some(ai)
+            }
+            None => None,
+        };
+        let m3 = self.squawk.map(|s| s.code());
+        let first = u8::from(ai.is_some()) << 7 | u8::from(m3.is_some()) << 6;
+        let fspec = match self.address {
+            Some(_) => vec![first | FSPEC_FX, 0b100_0000],
+            None => vec![first],
+        };
+        Ok(AircraftIdentificationAndCharacteristics {
+            fspec,
+            ai,
+            m3,
+            cpw: None,
+            cpc: None,
+            tt: None,
+            dt: None,
+            ac: None,
+            ms: self.address,
+            fp: None,
+            cf: None,
+        })
+    }
+}
+
+/// FX bit of the FSPEC of a compound data item
+const FSPEC_FX: u8 = 0b1;
+
+/// Stage of a conflict, as in I004/045 Alert Status
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ConflictStatus {
+    /// The pair is first predicted in conflict
+    Started,
+    /// The pair is still predicted in conflict
+    Continued,
+    /// The pair is no longer predicted in conflict, or a track was dropped
+    Ended,
+}
+
+impl ConflictStatus {
+    /// I004/045 STAT, which is system dependent: 1, 2 and 3 in the order of the stages
+    pub fn stat(&self) -> u8 {
+        match self {
+            Self::Started => 1,
+            Self::Continued => 2,
+            Self::Ended => 3,
+        }
+    }
+}
+
+/// CAT004 STCA message
+#[derive(Debug, Clone, PartialEq)]
+pub struct StcaAlert {
+    /// I004/020 Time of Message, the I048/140 of the update, in seconds since midnight
+    pub time: f64,
+    /// I004/040 Alert Identifier
+    pub alert_id: u16,
+    /// I004/045 Alert Status
+    pub status: ConflictStatus,
+    /// I004/070 TF, seconds until both separations are below the thresholds
+    pub time_to_conflict: f64,
+    /// I004/070 TC, seconds until the closest horizontal approach within the look-ahead time
+    pub time_to_closest_approach: f64,
+    /// I004/070 CHS, in NM
+    pub current_horizontal_nm: f64,
+    /// I004/070 MHS, smallest predicted horizontal separation in NM
+    pub minimum_horizontal_nm: f64,
+    /// I004/070 CVS, in ft
+    pub current_vertical_ft: f64,
+    /// I004/070 MVS, smallest predicted vertical separation in ft
+    pub minimum_vertical_ft: f64,
+    /// I004/170 Aircraft 1 and 2
+    pub tracks: [ConflictTrack; 2],
+}
+
+impl StcaAlert {
+    /// I004/020 as a data item
+    pub fn time_of_day(&self) -> Option<TimeOfDay> {
+        TimeOfDay::from_seconds(self.time).ok()
+    }
+
+    /// I004/020 raw, LSB of 1/128 s
+    pub fn i004_020(&self) -> Result<u32, DekuError> {
+        Ok(modifier::unsigned(self.time, TIME_LSB, 24, "I004/020 time of message")? as u32)
+    }
+
+    /// I004/070 TF, LSB of 1/128 s
+    pub fn tf(&self) -> Result<u32, DekuError> {
+        Ok(modifier::unsigned(self.time_to_conflict, TIME_LSB, 24, "I004/070 TF")? as u32)
+    }
+
+    /// I004/070 TC, LSB of 1/128 s
+    pub fn tc(&self) -> Result<u32, DekuError> {
+        Ok(modifier::unsigned(self.time_to_closest_approach, TIME_LSB, 24, "I004/070 TC")? as u32)
+    }
+
+    /// I004/070 CHS, LSB of 0.5 m
+    pub fn chs(&self) -> Result<u32, DekuError> {
+        let meters = self.current_horizontal_nm * METERS_PER_NM;
+        Ok(modifier::unsigned(meters, HORIZONTAL_LSB, 24, "I004/070 CHS")? as u32)
+    }
+
+    /// I004/070 MHS, LSB of 0.5 m
+    pub fn mhs(&self) -> Result<u16, DekuError> {
+        let meters = self.minimum_horizontal_nm * METERS_PER_NM;
+        Ok(modifier::unsigned(meters, HORIZONTAL_LSB, 16, "I004/070 MHS")? as u16)
+    }
+
+    /// I004/070 CVS, LSB of 25 ft
+    pub fn cvs(&self) -> Result<u16, DekuError> {
+        Ok(modifier::unsigned(self.current_vertical_ft, VERTICAL_LSB, 16, "I004/070 CVS")? as u16)
+    }
+
+    /// I004/070 MVS, LSB of 25 ft
+    pub fn mvs(&self) -> Result<u16, DekuError> {
+        Ok(modifier::unsigned(self.minimum_vertical_ft, VERTICAL_LSB, 16, "I004/070 MVS")? as u16)
+    }
+
+    /// CAT004 record of the alert from the safety net `source`
+    ///
+    /// The record has I004/010, I004/000, I004/015 with the radars of the tracks, I004/020,
+    /// I004/040, I004/045, I004/070, and I004/170 and I004/171 with the callsign, Mode 3/A code
+    /// and Mode S address that are known.
+    pub fn to_cat4(&self, source: DataSourceIdentifier) -> Result<Cat4, DekuError> {
+        let mut sdps: Vec<DataSourceIdentifier> = vec![];
+        for track in &self.tracks {
+            let radar = DataSourceIdentifier { sac: track.sac, sic: track.sic };
+            if !sdps.contains(&radar) {
+                sdps.push(radar);
+            }
+        }
+        let mut record = Cat4 {
+            data_source_identifier: Some(source),
+            message_type: Some(SafetyNetMessageType { t: SafetyNetMTYPE::ShortTermConflictAlert }),
+            sdps_identifier: Some(SdpsIdentifier { count: sdps.len() as u8, sdps }),
+            time_of_message: Some(TimeOfDay { time: self.i004_020()? }),
+            alert_identifier: Some(AlertIdentifier { id: self.alert_id }),
+            alert_status: Some(AlertStatus { reserved: 0, stat: self.status.stat() }),
+            aircraft_1: Some(self.tracks[0].i004_170()?),
+            conflict_timing_and_separation: Some(ConflictTimingAndSeparation {
+                fspec: vec![0b1111_1100],
+                tf: Some(self.tf()?),
+                tc: Some(self.tc()?),
+                chs: Some(self.chs()?),
+                mhs: Some(self.mhs()?),
+                cvs: Some(self.cvs()?),
+                mvs: Some(self.mvs()?),
+            }),
+            aircraft_2: Some(self.tracks[1].i004_170()?),
+            ..Cat4::default()
+        };
+        record.update_fspec();
+        Ok(record)
+    }
+}
+
+/// Last state of a track
+#[derive(Debug, Clone)]
+struct Track {
+    info: ConflictTrack,
+    time: f64,
+    /// Radar Cartesian position in NM
+    x: f64,
+    y: f64,
+    /// NM/s
+    vx: f64,
+    vy: f64,
+    /// FL per second
+    fl_rate: f64,
+}
+
+impl Track {
+    /// Position and flight level predicted `dt` seconds after `time`
+    fn predict(&self, time: f64, dt: f64) -> (f64, f64, f64) {
+        let dt = elapsed(self.time, time) + dt;
+        (self.x + self.vx * dt, self.y + self.vy * dt, self.info.flight_level + self.fl_rate * dt)
+    }
+}
+
+/// Predicted separation of a pair
+struct Prediction {
+    time_to_conflict: Option<f64>,
+    time_to_closest_approach: f64,
+    current_horizontal_nm: f64,
+    minimum_horizontal_nm: f64,
+    current_vertical_ft: f64,
+    minimum_vertical_ft: f64,
+}
+
+/// Conflict detection between the tracks of each radar, see the module documentation
+#[derive(Debug, Clone)]
+pub struct Stca {
+    config: StcaConfig,
+    tracks: HashMap<TrackId, Track>,
+    /// Alert identifier of each pair in conflict
+    conflicts: HashMap<(TrackId, TrackId), u16>,
+    next_id: u16,
+}
+
+impl StcaConfig {
+    /// `step` positive and the other parameters not negative
+    fn validate(&self) -> Result<(), Error> {
+        error::positive("stca", &[("step", self.step)])?;
+        error::non_negative(
+            "stca",
+            &[
+                ("horizontal_nm", self.horizontal_nm),
+                ("vertical_ft", self.vertical_ft),
+                ("look_ahead", self.look_ahead),
+                ("max_age", self.max_age),
+            ],
+        )
+    }
+}
+
+impl Stca {
+    /// Errors on a `config` out of the ranges of `StcaConfig`, such as `step` zero
+    pub fn new(config: StcaConfig) -> Result<Self, Error> {
+        config.validate()?;
+        Ok(Self { config, tracks: HashMap::new(), conflicts: HashMap::new(), next_id: 0 })
+    }
+
+    /// Pairs of tracks in conflict
+    pub fn conflicts(&self) -> impl Iterator<Item = (TrackId, TrackId)> + '_ {
+        self.conflicts.keys().copied()
+    }
+
+    /// Update a track, and predict its separation from the other tracks of its radar
+    ///
+    /// Records need I048/010, I048/161, I048/140, I048/042, I048/200 and I048/090, others are
+    /// ignored.
+    pub fn update(&mut self, record: &Cat48) -> Vec<StcaAlert> {
+        let Some(track) = self.track(record) else {
+            return vec![];
+        };
+        let key = (track.info.sac, track.info.sic, track.info.track_number);
+        let time = track.time;
+        self.tracks.insert(key, track);
+
+        let mut alerts = vec![];
+        let max_age = self.config.max_age;
+        let expired: Vec<_> = self
+            .tracks
+            .iter()
+            .filter(|(_, t)| elapsed(t.time, time) > max_age)
+            .map(|(k, _)| *k)
+            .collect();
+        for other in expired {
+            for pair in self.pairs_of(other) {
+                alerts.extend(self.end(pair, time));
+            }
+            self.tracks.remove(&other);
+        }
+
+        let others: Vec<_> = self
+            .tracks
+            .keys()
+            .filter(|k| **k != key && (k.0, k.1) == (key.0, key.1))
+            .copied()
+            .collect();
+        for other in others {
+            let pair = if key < other { (key, other) } else { (other, key) };
+            let prediction = self.predict(&self.tracks[&pair.0], &self.tracks[&pair.1], time);
+            match (prediction.time_to_conflict, self.conflicts.get(&pair).copied()) {
+                (Some(_), id) => {
+                    let (id, status) = match id {
+                        Some(id) => (id, ConflictStatus::Continued),
+                        None => {
+                            let id = self.next_id;
+                            self.next_id = id.wrapping_add(1);
+                            self.conflicts.insert(pair, id);
+                            (id, ConflictStatus::Started)
+                        }
+                    };
+                    alerts.push(self.alert(pair, id, status, time, &prediction));
+                }
+                (None, Some(_)) => alerts.extend(self.end(pair, time)),
+                (None, None) => {}
+            }
+        }
+        alerts
+    }
+
+    fn track(&self, record: &Cat48) -> Option<Track> {
+        let source = record.data_source_identifier.as_ref()?;
+        let track_number = record.track_number.as_ref()?.number;
+        let time = record.time_of_day.as_ref()?.seconds();
+        let position = record.calculated_position_cartesian_coor.as_ref()?;
+        let velocity = record.calculated_track_velocity.as_ref()?;
+        let flight_level = record.flight_level_in_binary_repre.as_ref()?.fl();
+
+        let previous = self.tracks.get(&(source.sac, source.sic, track_number));
+        let fl_rate = previous.map_or(0.0, |p| {
+            let dt = elapsed(p.time, time);
+            if dt > 0.0 {
+                (flight_level - p.info.flight_level) / dt
+            } else {
+                p.fl_rate
+            }
+        });
+        let (sin, cos) = velocity.heading_deg().to_radians().sin_cos();
+        let speed = velocity.groundspeed_nm_s();
+        Some(Track {
+            info: ConflictTrack {
+                sac: source.sac,
+                sic: source.sic,
+                track_number,
+                address: record.aircraft_address.as_ref().map(|a| a.address),
+                callsign: record
+                    .aircraft_identification
+                    .as_ref()
+                    .map(|a| a.identification)
+                    .or(previous.and_then(|p| p.info.callsign)),
+                squawk: record.squawk().or(previous.and_then(|p| p.info.squawk)),
+                flight_level,
+            },
+            time,
+            x: position.x_nm(),
+            y: position.y_nm(),
+            vx: speed * sin,
+            vy: speed * cos,
+            fl_rate,
+        })
+    }
+
+    /// Separation of `a` and `b` from `time` over the look-ahead time
+    fn predict(&self, a: &Track, b: &Track, time: f64) -> Prediction {
+        let StcaConfig { horizontal_nm, vertical_ft, look_ahead, step, .. } = self.config;
+        let separation = |dt: f64| {
+            let (ax, ay, afl) = a.predict(time, dt);
+            let (bx, by, bfl) = b.predict(time, dt);
+            ((ax - bx).hypot(ay - by), (afl - bfl).abs() * 100.0)
+        };
+        let (current_horizontal_nm, current_vertical_ft) = separation(0.0);
+        let mut prediction = Prediction {
+            time_to_conflict: None,
+            time_to_closest_approach: 0.0,
+            current_horizontal_nm,
+            minimum_horizontal_nm: current_horizontal_nm,
+            current_vertical_ft,
+            minimum_vertical_ft: current_vertical_ft,
+        };
+        let steps = (look_ahead / step).floor() as u32;
+        for dt in (0..=steps).map(|n| f64::from(n) * step) {
+            let (horizontal, vertical) = separation(dt);
+            if horizontal < prediction.minimum_horizontal_nm {
+                prediction.minimum_horizontal_nm = horizontal;
+                prediction.time_to_closest_approach = dt;
+            }
+            prediction.minimum_vertical_ft = prediction.minimum_vertical_ft.min(vertical);
+            if horizontal < horizontal_nm && vertical < vertical_ft {
+                prediction.time_to_conflict.get_or_insert(dt);
+            }
+        }
+        prediction
+    }
+
+    fn alert(
+        &self,
+        pair: (TrackId, TrackId),
+        alert_id: u16,
+        status: ConflictStatus,
+        time: f64,
+        prediction: &Prediction,
+    ) -> StcaAlert {
+        StcaAlert {
+            time,
+            alert_id,
+            status,
+            time_to_conflict: prediction.time_to_conflict.unwrap_or_default(),
+            time_to_closest_approach: prediction.time_to_closest_approach,
+            current_horizontal_nm: prediction.current_horizontal_nm,
+            minimum_horizontal_nm: prediction.minimum_horizontal_nm,
+            current_vertical_ft: prediction.current_vertical_ft,
+            minimum_vertical_ft: prediction.minimum_vertical_ft,
+            tracks: [self.tracks[&pair.0].info.clone(), self.tracks[&pair.1].info.clone()],
+        }
+    }
+
+    fn pairs_of(&self, key: TrackId) -> Vec<(TrackId, TrackId)> {
+        self.conflicts.keys().filter(|(a, b)| *a == key || *b == key).copied().collect()
+    }
+
+    /// End the conflict of `pair`, while both tracks are still known
+    fn end(&mut self, pair: (TrackId, TrackId), time: f64) -> Option<StcaAlert> {
+        let id = self.conflicts.remove(&pair)?;
+        let prediction = self.predict(&self.tracks[&pair.0], &self.tracks[&pair.1], time);
+        Some(self.alert(pair, id, ConflictStatus::Ended, time, &prediction))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::cat48;
+    use crate::{AsterixMessage, AsterixPacket};
+    use deku::{DekuContainerRead, DekuContainerWrite};
+
+    fn record(number: u16, time: f64, (x, y): (f64, f64), heading: f64, fl: f64) -> Cat48 {
+        cat48()
+            .with_track_number(number)
+            .with_time(time)
+            .with_cartesian(x, y)
+            .with_velocity(0.125, heading)
+            .with_flight_level(fl)
+    }
+
+    #[test]
+    fn conflict() {
+        let mut stca = Stca::new(StcaConfig::default()).unwrap();
+        // head on at 0.125 NM/s each, 20 NM apart, closest in 80 s
+        assert!(stca.update(&record(1, 0.0, (0.0, -10.0), 0.0, 100.0)).is_empty());
+        let alerts = stca.update(&record(2, 0.0, (0.0, 10.0), 180.0, 105.0));
+        let [alert] = &alerts[..] else { panic!("{alerts:?}") };
+        assert_eq!(alert.status, ConflictStatus::Started);
+        assert_eq!(alert.time_to_closest_approach, 80.0);
+        assert_eq!(alert.time_to_conflict, 69.0);
+        assert!(alert.minimum_horizontal_nm < 0.01);
+        assert_eq!(alert.current_vertical_ft, 500.0);
+        assert_eq!((alert.tracks[0].track_number, alert.tracks[1].track_number), (1, 2));
+        assert_eq!((alert.i004_020().unwrap(), alert.tf().unwrap()), (0, 69 * 128));
+        assert_eq!((alert.tc().unwrap(), alert.mhs().unwrap()), (80 * 128, 0));
+        assert_eq!(alert.chs().unwrap(), 74_080);
+        assert_eq!((alert.cvs().unwrap(), alert.mvs().unwrap()), (20, 20));
+        assert_eq!(stca.conflicts().count(), 1);
+
+        let alerts = stca.update(&record(1, 4.0, (0.0, -9.5), 0.0, 100.0));
+        assert_eq!(alerts[0].status, ConflictStatus::Continued);
+        // turned onto a parallel track
+        let alerts = stca.update(&record(2, 4.0, (5.0, 9.5), 0.0, 105.0));
+        assert_eq!((alerts[0].status, alerts[0].alert_id), (ConflictStatus::Ended, alert.alert_id));
+        assert_eq!(stca.conflicts().count(), 0);
+
+        // far apart vertically
+        assert!(stca.update(&record(3, 5.0, (0.0, 9.6), 180.0, 200.0)).is_empty());
+    }
+
+    #[test]
+    fn i004_070_out_of_range() {
+        let mut stca = Stca::new(StcaConfig::default()).unwrap();
+        stca.update(&record(1, 0.0, (0.0, -10.0), 0.0, 100.0));
+        let mut alert = stca.update(&record(2, 0.0, (0.0, 10.0), 180.0, 105.0)).remove(0);
+        alert.minimum_horizontal_nm = 20.0;
+        alert.current_vertical_ft = -25.0;
+        alert.time = f64::NAN;
+        assert_eq!(
+            alert.mhs().unwrap_err(),
+            DekuError::InvalidParam("I004/070 MHS: 37040 is outside of 0..=32767.5".to_string())
+        );
+        assert!(matches!(alert.cvs(), Err(DekuError::InvalidParam(_))));
+        assert!(matches!(alert.i004_020(), Err(DekuError::InvalidParam(_))));
+    }
+
+    #[test]
+    fn cat4() {
+        let track = |track_number, callsign: Option<&str>, code| ConflictTrack {
+            sac: 25,
+            sic: 13,
+            track_number,
+            address: Some(0xabcdef).filter(|_| track_number == 1),
+            callsign: callsign.map(|c| Callsign::new(c).unwrap()),
+            squawk: Squawk::new(code),
+            flight_level: 100.0,
+        };
+        let mut alert = StcaAlert {
+            time: 10.0,
+            alert_id: 0x0102,
+            status: ConflictStatus::Started,
+            time_to_conflict: 69.0,
+            time_to_closest_approach: 80.0,
+            current_horizontal_nm: 20.0,
+            minimum_horizontal_nm: 0.0,
+            current_vertical_ft: 500.0,
+            minimum_vertical_ft: 500.0,
+            tracks: [track(1, Some("DLH123"), 0o1234), track(2, None, 0o7000)],
+        };
+        let record = alert.to_cat4(DataSourceIdentifier { sac: 1, sic: 2 }).unwrap();
+        let mut packet = AsterixPacket { category: 4, ..AsterixPacket::default() };
+        packet.messages = vec![AsterixMessage::Cat4(record)];
+        packet.finalize().unwrap();
+
+        let mut expected = vec![0x04, 0x00, 0x33, 0xfd, 0x51, 0x20];
+        // I004/010, I004/000, I004/015, I004/020, I004/040, I004/045
+        expected.extend([0x01, 0x02, 0x07, 0x01, 0x19, 0x0d, 0x00, 0x05, 0x00, 0x01, 0x02, 0x01]);
+        // I004/170 AI, M3 and MS
+        expected.extend([0xc1, 0x40, b'D', b'L', b'H', b'1', b'2', b'3', b' ', 0x02, 0x9c]);
+        expected.extend([0xab, 0xcd, 0xef]);
+        // I004/070 TF, TC, CHS, MHS, CVS and MVS
+        expected.extend([0xfc, 0x00, 0x22, 0x80, 0x00, 0x28, 0x00, 0x01, 0x21, 0x60]);
+        expected.extend([0x00, 0x00, 0x00, 0x14, 0x00, 0x14]);
+        // I004/171 M3
+        expected.extend([0x40, 0x0e, 0x00]);
+        assert_eq!(packet.to_bytes().unwrap(), expected);
+        assert_eq!(AsterixPacket::from_bytes((&expected, 0)).unwrap().1, packet);
+
+        alert.tracks[1].callsign = Some(Callsign::new("ABCD1234").unwrap());
+        assert_eq!(
+            alert.to_cat4(DataSourceIdentifier { sac: 1, sic: 2 }).unwrap_err(),
+            DekuError::InvalidParam("I004/170 AI: ABCD1234 is longer than seven characters".into())
+        );
+    }
+
+    #[test]
+    fn config() {
+        let config = StcaConfig::default();
+        for bad in [
+            StcaConfig { step: 0.0, ..config },
+            StcaConfig { step: f64::NAN, ..config },
+            StcaConfig { look_ahead: -1.0, ..config },
+            StcaConfig { horizontal_nm: f64::INFINITY, ..config },
+        ] {
+            assert!(matches!(Stca::new(bad), Err(Error::InvalidParam(_))));
+        }
+        assert_eq!(
+            Stca::new(StcaConfig { step: -1.0, ..config }).unwrap_err(),
+            Error::InvalidParam("stca: step -1 is not a finite positive number".to_string())
+        );
+        assert_eq!(
+            Stca::new(StcaConfig { horizontal_nm: f64::INFINITY, ..config }).unwrap_err(),
+            Error::InvalidParam(
+                "stca: horizontal_nm inf is not a finite non-negative number".to_string()
+            )
+        );
+    }
+}
@@ -0,0 +1,140 @@
+//! Records and data items shared by the unit tests
+//!
+//! Records start from [`cat48`] or [`cat34`] and carry only the items a test adds to them.
+
+use crate::data_item::{
+    AircraftAddress, AircraftIdentification, CalculatedPositionCartesianCorr,
+    CalculatedTrackVelocity, DataSourceIdentifier, FlightLevelInBinaryRepresentation,
+    MeasuredPositionInPolarCoordinates, MessageType, Mode3ACodeInOctalRepresentation, SectorNumber,
+    TargetReportDescriptor, TimeOfDay, TrackNumber,
+};
+use crate::types::{Callsign, Squawk, G, L, MTYPE, TYP, V};
+use crate::{Cat34, Cat48};
+use deku::bitvec::BitView;
+use deku::ctx::Endian;
+use deku::DekuRead;
+
+/// SAC/SIC 25/13
+pub(crate) const SOURCE: DataSourceIdentifier = DataSourceIdentifier { sac: 25, sic: 13 };
+
+pub(crate) fn tod(seconds: f64) -> TimeOfDay {
+    TimeOfDay::from_seconds(seconds).unwrap()
+}
+
+/// Target report of `SOURCE` without any other item
+pub(crate) fn cat48() -> Cat48 {
+    Cat48 { data_source_identifier: Some(SOURCE), ..Cat48::default() }
+}
+
+/// Service message of type `t` from `SOURCE`
+pub(crate) fn cat34(t: MTYPE) -> Cat34 {
+    Cat34 {
+        data_source_identifier: Some(SOURCE),
+        message_type: Some(MessageType { t }),
+        ..Cat34::default()
+    }
+}
+
+impl Cat48 {
+    /// I048/140 at `seconds`, wrapped into a day
+    pub(crate) fn with_time(mut self, seconds: f64) -> Self {
+        self.time_of_day = Some(tod(seconds.rem_euclid(86_400.0)));
+        self
+    }
+
+    pub(crate) fn with_source(mut self, sac: u8, sic: u8) -> Self {
+        self.data_source_identifier = Some(DataSourceIdentifier { sac, sic });
+        self
+    }
+
+    /// I048/020 of type `typ` without extents
+    pub(crate) fn with_type(mut self, typ: TYP) -> Self {
+        let octet = [(typ as u8) << 5];
+        let (_, descriptor) = TargetReportDescriptor::read(octet.view_bits(), Endian::Big).unwrap();
+        self.target_report_descriptor = Some(descriptor);
+        self
+    }
+
+    /// I048/040 at `rho` NM, `theta` degrees wrapped into 0..360
+    pub(crate) fn with_polar(mut self, rho: f64, theta: f64) -> Self {
+        let mut polar = MeasuredPositionInPolarCoordinates { rho: 0, theta: 0 };
+        polar.set_rho_nm(rho).unwrap();
+        polar.set_theta_deg_wrapped(theta).unwrap();
+        self.measured_position_in_polar_coordinates = Some(polar);
+        self
+    }
+
+    /// I048/042 at `x`, `y` NM
+    pub(crate) fn with_cartesian(mut self, x: f64, y: f64) -> Self {
+        let mut cartesian = CalculatedPositionCartesianCorr { x: 0, y: 0 };
+        cartesian.set_x_nm(x).unwrap();
+        cartesian.set_y_nm(y).unwrap();
+        self.calculated_position_cartesian_coor = Some(cartesian);
+        self
+    }
+
+    /// I048/200 at `groundspeed` NM/s on `heading` degrees
+    pub(crate) fn with_velocity(mut self, groundspeed: f64, heading: f64) -> Self {
+        let mut velocity = CalculatedTrackVelocity { groundspeed: 0, heading: 0 };
+        velocity.set_groundspeed_nm_s(groundspeed).unwrap();
+        velocity.set_heading_deg(heading).unwrap();
+        self.calculated_track_velocity = Some(velocity);
+        self
+    }
+
+    /// I048/220
+    pub(crate) fn with_address(mut self, address: u32) -> Self {
+        self.aircraft_address = Some(AircraftAddress { address });
+        self
+    }
+
+    /// Validated I048/090 at `fl`
+    pub(crate) fn with_flight_level(mut self, fl: f64) -> Self {
+        let mut level = FlightLevelInBinaryRepresentation {
+            v: V::CodeValidated,
+            g: G::Default,
+            flight_level: 0,
+        };
+        level.set_fl(fl).unwrap();
+        self.flight_level_in_binary_repre = Some(level);
+        self
+    }
+
+    /// I048/240
+    pub(crate) fn with_callsign(mut self, callsign: &str) -> Self {
+        let identification = Callsign::new(callsign).unwrap();
+        self.aircraft_identification = Some(AircraftIdentification { identification });
+        self
+    }
+
+    /// I048/161
+    pub(crate) fn with_track_number(mut self, number: u16) -> Self {
+        self.track_number = Some(TrackNumber { reserved: 0, number });
+        self
+    }
+
+    /// Validated I048/070 of octal `code`
+    pub(crate) fn with_squawk(mut self, code: u16) -> Self {
+        self.mode_3_a_code_in_octal_representation = Some(Mode3ACodeInOctalRepresentation {
+            v: V::CodeValidated,
+            g: G::Default,
+            l: L::Mode3CodeDerivedFromTheReplyOfTheTransponder,
+            reserved: 0,
+            reply: Squawk::new(code).unwrap(),
+        });
+        self
+    }
+}
+
+impl Cat34 {
+    /// I034/030 at `seconds`
+    pub(crate) fn with_time(mut self, seconds: f64) -> Self {
+        self.time_of_day = Some(tod(seconds));
+        self
+    }
+
+    pub(crate) fn with_sector(mut self, num: u8) -> Self {
+        self.sector_number = Some(SectorNumber { num });
+        self
+    }
+}
@@ -1,15 +1,16 @@
 use crate::data_item::{
     AntennaRotationSpeed, CollimationError, DataFilter, DataSourceIdentifier, GenericPolarWindow,
-    MessageCountValues, MessageType, SectorNumber, SystemConfigurationAndStatus,
-    SystemProcessingMode, ThreeDPositionOfDataSource, TimeOfDay,
+    MessageCountValues, MessageType, ReservedExpansionField, SectorNumber, SpecialPurposeField,
+    SystemConfigurationAndStatus, SystemProcessingMode, ThreeDPositionOfDataSource, TimeOfDay,
 };
+use crate::edition::Cat34Edition;
 use crate::fspec::{add_fx, is_fspec, trim_fspec};
 use crate::FSPEC_IDENT;
 use asterix_derive::UpdateFspec;
 use deku::prelude::*;
 
 /// Transmission of Monoradar Service Messages
-#[derive(Debug, Default, PartialEq, DekuRead, DekuWrite, UpdateFspec)]
+#[derive(Debug, Default, PartialEq, Clone, DekuRead, DekuWrite, UpdateFspec)]
 #[deku(endian = "big")]
 pub struct Cat34 {
     #[deku(until = "|b: &u8| *b & FSPEC_IDENT == 0")]
@@ -22,6 +23,7 @@ pub struct Cat34 {
     pub message_type: Option<MessageType>,
     /// FRN 3
     #[deku(skip, cond = "is_fspec(TimeOfDay::FRN_34, fspec, 0)")]
+    #[deku(ctx = "34")]
     pub time_of_day: Option<TimeOfDay>,
     /// FRN 4
     #[deku(skip, cond = "is_fspec(SectorNumber::FRN_34, fspec, 0)")]
@@ -50,6 +52,31 @@ pub struct Cat34 {
     /// FRN 12
     #[deku(skip, cond = "is_fspec(CollimationError::FRN_34, fspec, 1)")]
     pub collimation_error: Option<CollimationError>,
-    // FRN 13: Reserved Expansion Field
-    // FRN 14: Special Purpose Field
+    /// FRN 13
+    #[deku(skip, cond = "is_fspec(ReservedExpansionField::FRN_34, fspec, 1)")]
+    pub reserved_expansion_field: Option<ReservedExpansionField>,
+    /// FRN 14
+    #[deku(skip, cond = "is_fspec(SpecialPurposeField::FRN_34, fspec, 1)")]
+    pub special_purpose_field: Option<SpecialPurposeField>,
+    /// Edition the record is checked against, see `Editions`
+    ///
+    /// `None` after `AsterixPacket::from_bytes`, which can't tell the edition of the sender, and
+    /// then the record isn't checked.
+    #[deku(skip)]
+    pub edition: Option<Cat34Edition>,
+}
+
+impl Cat34 {
+    /// Check that every data item present is defined by `edition`, if it is set
+    pub fn check_edition(&self) -> Result<(), DekuError> {
+        let Some(edition) = self.edition else {
+            return Ok(());
+        };
+        if !edition.has_reserved_expansion_field() && self.reserved_expansion_field.is_some() {
+            return Err(DekuError::Assertion(format!(
+                "I034/RE is not defined by CAT034 edition {edition}"
+            )));
+        }
+        Ok(())
+    }
 }
@@ -0,0 +1,499 @@
+//! Mono-radar tracking of CAT048 plots
+//!
+//! Some radars deliver plots only, without I048/161 Track Number and the other track data items.
+//! `Tracker` associates the plots of one radar from scan to scan and smooths them with an
+//! alpha-beta filter in the radar Cartesian plane, filling in I048/161, I048/042, I048/200,
+//! I048/170 and I048/210.
+//!
+//! Plots are associated by Mode S address first, then by position gating, preferring tracks with
+//! the same Mode-3/A code. Plots need I048/040 and I048/140, and must be given in time order.
+//!
+//! ```rust
+//! use asterix::tracker::{Tracker, TrackerConfig};
+//! # let plots: Vec<asterix::Cat48> = vec![];
+//!
+//! let mut tracker = Tracker::new(TrackerConfig::default())?;
+//! for mut plot in plots {
+//!     tracker.update(&mut plot);
+//! }
+//! # Ok::<(), asterix::error::Error>(())
+//! ```
+
+use crate::data_item::{
+    CalculatedPositionCartesianCorr, CalculatedTrackVelocity, TrackNumber, TrackQuality,
+    TrackStatus,
+};
+use crate::error::{self, Error};
+use crate::modifier::{elapsed, GROUNDSPEED, HEADING_12};
+use crate::types::{Squawk, CDM, CNF, DOU, FX, G, MAH, RAD, TYP, V};
+use crate::{geodesy, Cat48};
+
+/// Largest track number of I048/161
+const MAX_TRACK_NUMBER: u16 = 0x0fff;
+/// LSB of the I048/210 position standard deviations
+const STDDEV_LSB: f64 = 1.0 / 128.0;
+
+/// Raw value of an unsigned 8 bit item, saturating at its range
+fn saturating_u8(value: f64, lsb: f64) -> u8 {
+    (value / lsb).round().clamp(0.0, f64::from(u8::MAX)) as u8
+}
+
+/// Parameters of `Tracker`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrackerConfig {
+    /// Position gain of the alpha-beta filter
+    pub alpha: f64,
+    /// Velocity gain of the alpha-beta filter
+    pub beta: f64,
+    /// Largest distance in NM between a plot and the predicted track position
+    pub gate_nm: f64,
+    /// Plots less than this many seconds after the last update of a track are from the same scan,
+    /// and aren't associated with it
+    pub min_interval: f64,
+    /// Tracks not updated for this many seconds are dropped
+    pub max_coast: f64,
+    /// Updates before a track is confirmed
+    pub confirm_after: u32,
+    /// Standard deviation of plot positions in NM, the initial track quality
+    pub plot_stddev_nm: f64,
+    /// Turn rate in degrees per second above which a track is manoeuvring
+    pub manoeuvre_deg_s: f64,
+    /// Rate of change of the flight level in FL per second above which a track is climbing or
+    /// descending
+    pub climb_fl_s: f64,
+}
+
+impl Default for TrackerConfig {
+    fn default() -> Self {
+        Self {
+            alpha: 0.5,
+            beta: 0.2,
+            gate_nm: 2.0,
+            min_interval: 1.0,
+            max_coast: 20.0,
+            confirm_after: 3,
+            plot_stddev_nm: 0.1,
+            manoeuvre_deg_s: 1.5,
+            climb_fl_s: 0.1,
+        }
+    }
+}
+
+/// State of a track, in the radar Cartesian plane
+#[derive(Debug, Clone, PartialEq)]
+pub struct Track {
+    /// I048/161
+    pub number: u16,
+    /// East position in NM
+    pub x: f64,
+    /// North position in NM
+    pub y: f64,
+    /// East velocity in NM/s, zero until the second update
+    pub vx: f64,
+    /// North velocity in NM/s, zero until the second update
+    pub vy: f64,
+    /// I048/140 of the last update, in seconds since midnight
+    pub time: f64,
+    /// Seconds between the last two updates
+    pub interval: Option<f64>,
+    /// I048/220 of the last plot with one
+    pub address: Option<u32>,
+    /// Validated I048/070 of the last plot with one
+    pub squawk: Option<Squawk>,
+    /// Plots associated with the track
+    pub updates: u32,
+    /// I048/090 of the last plot with one
+    pub fl: Option<f64>,
+    /// Smoothed rate of change of the flight level, in FL per second
+    pub fl_rate: Option<f64>,
+    /// Smoothed squared residuals of x and y, in NM²
+    pub variance: (f64, f64),
+    /// Sensors of the last plot
+    pub rad: RAD,
+    /// Turn rate was above `TrackerConfig::manoeuvre_deg_s` at the last update
+    pub manoeuvring: bool,
+}
+
+impl Track {
+    /// Position predicted at `time`
+    pub fn predict(&self, time: f64) -> (f64, f64) {
+        let dt = elapsed(self.time, time);
+        (self.x + self.vx * dt, self.y + self.vy * dt)
+    }
+
+    /// Velocity is known
+    pub fn has_velocity(&self) -> bool {
+        self.updates >= 2
+    }
+
+    /// Groundspeed in NM/s
+    pub fn groundspeed_nm_s(&self) -> f64 {
+        self.vx.hypot(self.vy)
+    }
+
+    /// Heading in degrees, clockwise from north
+    pub fn heading_deg(&self) -> f64 {
+        self.vx.atan2(self.vy).to_degrees().rem_euclid(360.0)
+    }
+}
+
+/// Plot in the radar Cartesian plane
+struct Plot {
+    x: f64,
+    y: f64,
+    time: f64,
+    address: Option<u32>,
+    squawk: Option<Squawk>,
+    fl: Option<f64>,
+    rad: RAD,
+}
+
+impl Plot {
+    fn new(record: &Cat48) -> Option<Self> {
+        let polar = record.measured_position_in_polar_coordinates.as_ref()?;
+        let time = record.time_of_day.as_ref()?.seconds();
+        let (x, y) = geodesy::polar_to_cartesian(polar.rho_nm(), polar.theta_deg());
+        let squawk = record
+            .mode_3_a_code_in_octal_representation
+            .as_ref()
+            .filter(|m| m.v == V::CodeValidated && m.g == G::Default)
+            .map(|m| m.reply);
+        let fl = record
+            .flight_level_in_binary_repre
+            .as_ref()
+            .filter(|f| f.v == V::CodeValidated && f.g == G::Default)
+            .map(|f| f.fl());
+        let rad = match record.target_report_descriptor.as_ref().map(|d| d.typ) {
+            Some(TYP::SinglePSRDetection) => RAD::PSRTrack,
+            Some(TYP::SingleSSRDetection | TYP::SingleModeSAllCall | TYP::SingleModeSRollCall) => {
+                RAD::SSRModeSTrack
+            }
+            Some(_) => RAD::CombinedTrack,
+            None => RAD::Invalid,
+        };
+        let address = record.aircraft_address.as_ref().map(|a| a.address);
+        Some(Self { x, y, time, address, squawk, fl, rad })
+    }
+}
+
+/// Tracks of one radar, see the module documentation
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tracker {
+    config: TrackerConfig,
+    tracks: Vec<Track>,
+    next_number: u16,
+}
+
+impl TrackerConfig {
+    /// Gains in 0..=1, `min_interval` positive and the other parameters not negative
+    fn validate(&self) -> Result<(), Error> {
+        let gains = [("alpha", self.alpha), ("beta", self.beta)];
+        if let Some((name, value)) = gains.iter().find(|(_, v)| !(0.0..=1.0).contains(v)) {
+            return Err(Error::InvalidParam(format!(
+                "tracker: {name} {value} is outside of 0..=1"
+            )));
+        }
+        error::positive("tracker", &[("min_interval", self.min_interval)])?;
+        error::non_negative(
+            "tracker",
+            &[
+                ("gate_nm", self.gate_nm),
+                ("max_coast", self.max_coast),
+                ("plot_stddev_nm", self.plot_stddev_nm),
+                ("manoeuvre_deg_s", self.manoeuvre_deg_s),
+                ("climb_fl_s", self.climb_fl_s),
+            ],
+        )
+    }
+}
+
+impl Tracker {
+    /// Errors on a `config` out of the ranges of `TrackerConfig`, such as `min_interval` zero,
+    /// which would divide by a zero interval
+    pub fn new(config: TrackerConfig) -> Result<Self, Error> {
+        config.validate()?;
+        Ok(Self { config, tracks: vec![], next_number: 0 })
+    }
+
+    /// Tracks being followed
+    pub fn tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+
+    /// Track with I048/161 `number`
+    pub fn track(&self, number: u16) -> Option<&Track> {
+        self.tracks.iter().find(|t| t.number == number)
+    }
+
+    /// Associate a plot with a track, and fill in its track data items
+    ///
+    /// Returns the track number, or `None` for a plot without I048/040 or I048/140, which is left
+    /// unchanged. Call `AsterixPacket::finalize` before encoding the record.
+    pub fn update(&mut self, record: &mut Cat48) -> Option<u16> {
+        let plot = Plot::new(record)?;
+        let max_coast = self.config.max_coast;
+        self.tracks.retain(|t| elapsed(t.time, plot.time) <= max_coast);
+
+        let by_address = plot
+            .address
+            .and_then(|address| self.tracks.iter().position(|t| t.address == Some(address)));
+        let index = match by_address.or_else(|| self.nearest(&plot)) {
+            Some(index) if self.eligible(&self.tracks[index], &plot) => {
+                self.filter(index, &plot);
+                index
+            }
+            // another plot of a Mode S track in the same scan is reported as the track, without
+            // updating it, rather than starting a second track with the same address
+            Some(index) if by_address.is_some() => index,
+            _ => {
+                let number = self.allocate_number();
+                let variance = self.config.plot_stddev_nm.powi(2);
+                self.tracks.push(Track {
+                    number,
+                    x: plot.x,
+                    y: plot.y,
+                    vx: 0.0,
+                    vy: 0.0,
+                    time: plot.time,
+                    interval: None,
+                    address: plot.address,
+                    squawk: plot.squawk,
+                    updates: 1,
+                    fl: plot.fl,
+                    fl_rate: None,
+                    variance: (variance, variance),
+                    rad: plot.rad,
+                    manoeuvring: false,
+                });
+                self.tracks.len() - 1
+            }
+        };
+        let track = &self.tracks[index];
+        self.fill(track, record);
+        Some(track.number)
+    }
+
+    /// Track updated at least `min_interval` before `plot`
+    fn eligible(&self, track: &Track, plot: &Plot) -> bool {
+        elapsed(track.time, plot.time) >= self.config.min_interval
+    }
+
+    /// Nearest eligible track in the gate, preferring tracks with the same Mode-3/A code
+    fn nearest(&self, plot: &Plot) -> Option<usize> {
+        self.tracks
+            .iter()
+            .enumerate()
+            .filter(|(_, t)| self.eligible(t, plot))
+            .filter(|(_, t)| t.address.is_none() || plot.address.is_none())
+            .filter_map(|(index, t)| {
+                let (x, y) = t.predict(plot.time);
+                let distance = (plot.x - x).hypot(plot.y - y);
+                let other_code =
+                    plot.squawk.is_some() && t.squawk.is_some() && plot.squawk != t.squawk;
+                (distance <= self.config.gate_nm).then_some((other_code, distance, index))
+            })
+            .min_by(|a, b| {
+                (a.0, a.1).partial_cmp(&(b.0, b.1)).unwrap_or(core::cmp::Ordering::Equal)
+            })
+            .map(|(_, _, index)| index)
+    }
+
+    /// Alpha-beta update of a track with `plot`
+    fn filter(&mut self, index: usize, plot: &Plot) {
+        let TrackerConfig { alpha, beta, manoeuvre_deg_s, .. } = self.config;
+        let track = &mut self.tracks[index];
+        let dt = elapsed(track.time, plot.time);
+        let heading = track.has_velocity().then(|| track.heading_deg());
+
+        let (x, y) = track.predict(plot.time);
+        let (rx, ry) = (plot.x - x, plot.y - y);
+        if track.has_velocity() {
+            track.x = x + alpha * rx;
+            track.y = y + alpha * ry;
+            track.vx += beta * rx / dt;
+            track.vy += beta * ry / dt;
+            track.variance.0 += alpha * (rx * rx - track.variance.0);
+            track.variance.1 += alpha * (ry * ry - track.variance.1);
+        } else {
+            track.vx = (plot.x - track.x) / dt;
+            track.vy = (plot.y - track.y) / dt;
+            track.x = plot.x;
+            track.y = plot.y;
+        }
+
+        track.manoeuvring = heading.is_some_and(|heading| {
+            let turn = (track.heading_deg() - heading + 180.0).rem_euclid(360.0) - 180.0;
+            turn.abs() / dt > manoeuvre_deg_s
+        });
+        if let Some(fl) = plot.fl {
+            if let Some(previous) = track.fl {
+                let rate = (fl - previous) / dt;
+                track.fl_rate = Some(track.fl_rate.map_or(rate, |r| r + alpha * (rate - r)));
+            }
+            track.fl = Some(fl);
+        }
+        track.time = plot.time;
+        track.interval = Some(dt);
+        track.address = plot.address.or(track.address);
+        track.squawk = plot.squawk.or(track.squawk);
+        track.rad = plot.rad;
+        track.updates += 1;
+    }
+
+    /// Next track number not in use
+    fn allocate_number(&mut self) -> u16 {
+        loop {
+            let number = self.next_number;
+            self.next_number = if number == MAX_TRACK_NUMBER { 0 } else { number + 1 };
+            if self.track(number).is_none() {
+                return number;
+            }
+        }
+    }
+
+    /// Fill in the track data items of `record`
+    fn fill(&self, track: &Track, record: &mut Cat48) {
+        record.track_number = Some(TrackNumber { reserved: 0, number: track.number });
+
+        let mut position = CalculatedPositionCartesianCorr { x: 0, y: 0 };
+        let outside = position.set_x_nm(track.x).and(position.set_y_nm(track.y)).is_err();
+        record.calculated_position_cartesian_coor = (!outside).then_some(position);
+
+        let speed = track.groundspeed_nm_s();
+        let (sx, sy) = (track.variance.0.sqrt(), track.variance.1.sqrt());
+        let speed_stddev = track.interval.map_or(0.0, |dt| self.config.beta * sx.hypot(sy) / dt);
+        let mut velocity = CalculatedTrackVelocity { groundspeed: 0, heading: 0 };
+        let valid = track.has_velocity()
+            && velocity.set_groundspeed_nm_s(speed).is_ok()
+            && velocity.set_heading_deg_wrapped(track.heading_deg()).is_ok();
+        record.calculated_track_velocity = valid.then_some(velocity);
+
+        let cdm = match track.fl_rate {
+            None => CDM::Unknown,
+            Some(rate) if rate > self.config.climb_fl_s => CDM::Climbing,
+            Some(rate) if rate < -self.config.climb_fl_s => CDM::Descending,
+            Some(_) => CDM::Maintaining,
+        };
+        let confirmed = track.updates >= self.config.confirm_after;
+        record.track_status = Some(TrackStatus {
+            cnf: if confirmed { CNF::ConfirmedTrack } else { CNF::TentativeTrack },
+            rad: track.rad,
+            dou: DOU::NormalConfidence,
+            mah: if track.manoeuvring {
+                MAH::HorizontalManSensed
+            } else {
+                MAH::NoHorizontalManSensed
+            },
+            cdm,
+            fx1: FX::EndOfDataItem,
+            tre: None,
+            gho: None,
+            sup: None,
+            tcc: None,
+            reserved: None,
+            fx2: None,
+            spare: None,
+            fx3: None,
+            extents: vec![],
+        });
+
+        let heading_stddev = speed_stddev.atan2(speed).to_degrees();
+        record.track_quality = Some(TrackQuality {
+            horizontal_stddev: saturating_u8(sx, STDDEV_LSB),
+            vertical_stddev: saturating_u8(sy, STDDEV_LSB),
+            groundspeed_stddev: saturating_u8(speed_stddev, GROUNDSPEED),
+            heading_stddev: saturating_u8(heading_stddev, HEADING_12),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{cat48, tod};
+
+    fn plot(x: f64, y: f64, time: f64) -> Cat48 {
+        let (rho, theta) = geodesy::cartesian_to_polar(x, y);
+        cat48().with_polar(rho, theta).with_time(time)
+    }
+
+    #[test]
+    fn straight_line() {
+        let mut tracker = Tracker::new(TrackerConfig::default()).unwrap();
+        let mut numbers = vec![];
+        let mut record = Cat48::default();
+        // 0.1 NM/s east, with a second target 1.5 NM south on the same course
+        for scan in 0..10 {
+            let time = 86_390.0 + 4.0 * f64::from(scan);
+            let x = 0.4 * f64::from(scan);
+            record = plot(x, 50.0, time).with_squawk(0o1234).with_address(0xabcdef);
+            numbers.push(tracker.update(&mut record).unwrap());
+            let mut other = plot(x, 48.5, time).with_squawk(0o4321);
+            assert_ne!(tracker.update(&mut other), Some(numbers[0]));
+        }
+        assert!(numbers.iter().all(|n| *n == numbers[0]));
+        assert_eq!(tracker.tracks().len(), 2);
+
+        let velocity = record.calculated_track_velocity.as_ref().unwrap();
+        assert!((velocity.groundspeed_kt() - 360.0).abs() < 1.0);
+        assert!((velocity.heading_deg() - 90.0).abs() < 0.5);
+        let position = record.calculated_position_cartesian_coor.as_ref().unwrap();
+        assert!((position.x_nm() - 3.6).abs() < 0.05);
+        assert!((position.y_nm() - 50.0).abs() < 0.05);
+        let status = record.track_status.as_ref().unwrap();
+        assert_eq!(status.cnf, CNF::ConfirmedTrack);
+        assert_eq!(status.mah, MAH::NoHorizontalManSensed);
+        assert!(record.track_quality.is_some());
+
+        // dropped after coasting
+        let mut late = plot(0.0, 10.0, 86_390.0 + 100.0);
+        tracker.update(&mut late);
+        assert_eq!(tracker.tracks().len(), 1);
+        assert_eq!(late.track_status.unwrap().cnf, CNF::TentativeTrack);
+        assert!(late.calculated_track_velocity.is_none());
+    }
+
+    #[test]
+    fn same_scan() {
+        let mut tracker = Tracker::new(TrackerConfig::default()).unwrap();
+        let first = tracker.update(&mut plot(0.0, 50.0, 0.0));
+        // split plot in the same scan starts another track
+        let second = tracker.update(&mut plot(0.1, 50.0, 0.01));
+        assert_ne!(first, second);
+        assert_eq!(tracker.update(&mut Cat48::default()), None);
+
+        // but not for a Mode S address with a track
+        let mut record = plot(20.0, 50.0, 0.0).with_address(0xabcdef);
+        let number = tracker.update(&mut record.clone());
+        record.time_of_day = Some(tod(0.01));
+        assert_eq!(tracker.update(&mut record), number);
+        assert_eq!(tracker.tracks().len(), 3);
+        assert_eq!(tracker.track(number.unwrap()).unwrap().updates, 1);
+    }
+
+    #[test]
+    fn config() {
+        let config = TrackerConfig::default();
+        for bad in [
+            TrackerConfig { min_interval: 0.0, ..config },
+            TrackerConfig { alpha: 1.5, ..config },
+            TrackerConfig { beta: f64::NAN, ..config },
+            TrackerConfig { gate_nm: -1.0, ..config },
+            TrackerConfig { max_coast: f64::INFINITY, ..config },
+        ] {
+            assert!(matches!(Tracker::new(bad), Err(Error::InvalidParam(_))));
+        }
+        assert_eq!(
+            Tracker::new(TrackerConfig { min_interval: 0.0, ..config }).unwrap_err(),
+            Error::InvalidParam(
+                "tracker: min_interval 0 is not a finite positive number".to_string()
+            )
+        );
+        assert_eq!(
+            Tracker::new(TrackerConfig { max_coast: f64::NAN, ..config }).unwrap_err(),
+            Error::InvalidParam(
+                "tracker: max_coast NaN is not a finite non-negative number".to_string()
+            )
+        );
+    }
+}
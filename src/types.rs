@@ -2,7 +2,7 @@
 
 use deku::prelude::*;
 
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, Clone, Copy, DekuRead, DekuWrite)]
 #[deku(type = "u8", bits = "3")]
 pub enum TYP {
     NoDetection = 0x00,
@@ -15,28 +15,28 @@ pub enum TYP {
     ModeSRollCallPlusPSR = 0x07,
 }
 
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, Clone, Copy, DekuRead, DekuWrite)]
 #[deku(type = "u8", bits = "1")]
 pub enum SIM {
     ActualTargetReport = 0x00,
     SimulatedTargetReport = 0x01,
 }
 
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, Clone, Copy, DekuRead, DekuWrite)]
 #[deku(type = "u8", bits = "1")]
 pub enum RDP {
     ReportFromRDPChain1 = 0x00,
     ReportFromRDPChain2 = 0x01,
 }
 
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, Clone, Copy, DekuRead, DekuWrite)]
 #[deku(type = "u8", bits = "1")]
 pub enum SPI {
     AbsenceOfSPI = 0x00,
     SpecialPositionIdentification = 0x01,
 }
 
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, Clone, Copy, DekuRead, DekuWrite)]
 #[deku(type = "u8", bits = "1")]
 pub enum RAB {
     ReportFromAircraftTransponder = 0x00,
@@ -50,35 +50,35 @@ pub enum FX {
     ExtensionIntoFirstExtent = 0x01,
 }
 
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, Clone, Copy, DekuRead, DekuWrite)]
 #[deku(type = "u8", bits = "1")]
 pub enum V {
     CodeValidated = 0x00,
     CodeNotValidated = 0x01,
 }
 
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, Clone, Copy, DekuRead, DekuWrite)]
 #[deku(type = "u8", bits = "1")]
 pub enum G {
     Default = 0x00,
     GarbledCode = 0x01,
 }
 
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, Clone, Copy, DekuRead, DekuWrite)]
 #[deku(type = "u8", bits = "1")]
 pub enum L {
     Mode3CodeDerivedFromTheReplyOfTheTransponder = 0x00,
     Mode3CodeNotExtractedDuringTheLastScan = 0x01,
 }
 
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, Clone, Copy, DekuRead, DekuWrite)]
 #[deku(type = "u8", bits = "1")]
 pub enum CNF {
     ConfirmedTrack = 0x00,
     TentativeTrack = 0x01,
 }
 
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, Clone, Copy, DekuRead, DekuWrite)]
 #[deku(type = "u8", bits = "2")]
 pub enum RAD {
     CombinedTrack = 0x00,
@@ -87,21 +87,21 @@ pub enum RAD {
     Invalid = 0x03,
 }
 
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, Clone, Copy, DekuRead, DekuWrite)]
 #[deku(type = "u8", bits = "1")]
 pub enum DOU {
     NormalConfidence = 0x00,
     LowConfidence = 0x01,
 }
 
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, Clone, Copy, DekuRead, DekuWrite)]
 #[deku(type = "u8", bits = "1")]
 pub enum MAH {
     NoHorizontalManSensed = 0x00,
     HorizontalManSensed = 0x01,
 }
 
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, Clone, Copy, DekuRead, DekuWrite)]
 #[deku(type = "u8", bits = "2")]
 pub enum CDM {
     Maintaining = 0x00,
@@ -110,35 +110,35 @@ pub enum CDM {
     Unknown = 0x03,
 }
 
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, Clone, Copy, DekuRead, DekuWrite)]
 #[deku(type = "u8", bits = "1")]
 pub enum TRE {
     TrackStillAlive = 0x00,
     EndOfTrackLifetime = 0x01,
 }
 
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, Clone, Copy, DekuRead, DekuWrite)]
 #[deku(type = "u8", bits = "1")]
 pub enum GHO {
     TrueTargetTrack = 0x00,
     GhostTargetTrack = 0x01,
 }
 
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, Clone, Copy, DekuRead, DekuWrite)]
 #[deku(type = "u8", bits = "1")]
 pub enum SUP {
     No = 0x00,
     Yes = 0x01,
 }
 
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, Clone, Copy, DekuRead, DekuWrite)]
 #[deku(type = "u8", bits = "1")]
 pub enum TCC {
     RadarPlanePlotTransformation = 0x00,
     SlantRangePlotTransformation = 0x01,
 }
 
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, Clone, Copy, DekuRead, DekuWrite)]
 #[deku(type = "u8", bits = "3")]
 pub enum COM {
     NoCommunicationsSurveillanceOnly = 0x00,
@@ -150,7 +150,7 @@ pub enum COM {
     NoAssigned,
 }
 
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, Clone, Copy, DekuRead, DekuWrite)]
 #[deku(type = "u8", bits = "3")]
 pub enum STAT {
     NoAlertNoSPIAircraftAirborne = 0x00,
@@ -163,35 +163,35 @@ pub enum STAT {
     Unknown = 0x07,
 }
 
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, Clone, Copy, DekuRead, DekuWrite)]
 #[deku(type = "u8", bits = "1")]
 pub enum SI {
     SICodeCapable = 0x00,
     IICodeCapable = 0x01,
 }
 
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, Clone, Copy, DekuRead, DekuWrite)]
 #[deku(type = "u8", bits = "1")]
 pub enum MSSC {
     No = 0x00,
     Yes = 0x01,
 }
 
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, Clone, Copy, DekuRead, DekuWrite)]
 #[deku(type = "u8", bits = "1")]
 pub enum ARC {
     Resolution100ft = 0x00,
     Resolution25ft = 0x01,
 }
 
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, Clone, Copy, DekuRead, DekuWrite)]
 #[deku(type = "u8", bits = "1")]
 pub enum AIC {
     No = 0x00,
     Yes = 0x01,
 }
 
-#[derive(Debug, PartialEq, DekuRead, DekuWrite)]
+#[derive(Debug, PartialEq, Clone, Copy, DekuRead, DekuWrite)]
 #[deku(type = "u8", bits = "8")]
 pub enum MTYPE {
     NorthMarker = 0x01,
@@ -200,6 +200,30 @@ pub enum MTYPE {
     JammingStrobe = 0x04,
 }
 
+/// Safety net function of a CAT004 message
+#[derive(Debug, PartialEq, Clone, Copy, DekuRead, DekuWrite)]
+#[deku(type = "u8", bits = "8")]
+pub enum SafetyNetMTYPE {
+    #[deku(id = "1")]
+    AliveMessage,
+    #[deku(id = "2")]
+    RouteAdherenceMonitorLongitudinalDeviation,
+    #[deku(id = "3")]
+    RouteAdherenceMonitorHeadingDeviation,
+    #[deku(id = "4")]
+    MinimumSafeAltitudeWarning,
+    #[deku(id = "5")]
+    AreaProximityWarning,
+    #[deku(id = "6")]
+    ClearanceLevelAdherenceMonitor,
+    #[deku(id = "7")]
+    ShortTermConflictAlert,
+    #[deku(id = "8")]
+    ApproachFunctionDeviationAlert,
+    #[deku(id_pat = "_")]
+    Other(u8),
+}
+
 #[derive(Debug, PartialEq, Clone, Copy, DekuRead, DekuWrite)]
 #[deku(type = "u16", bits = "7")]
 pub enum CODE {
@@ -432,6 +456,22 @@ pub enum MessageCounterTYP {
     FilterForPSREnhancedSurveillancePlusAllSSRModeSData = 16,
 }
 
+impl From<TYP> for MessageCounterTYP {
+    /// I034/070 counter of a CAT048 target report of type `typ`
+    fn from(typ: TYP) -> Self {
+        match typ {
+            TYP::NoDetection => Self::NoDetection,
+            TYP::SinglePSRDetection => Self::SinglePSRTargetReports,
+            TYP::SingleSSRDetection => Self::SingleSSRTargetReports,
+            TYP::SSRPlusPSRDetection => Self::SSRPSRTargetReports,
+            TYP::SingleModeSAllCall => Self::SingleAllCallTargetReports,
+            TYP::SingleModeSRollCall => Self::SingleRollCallTargetReports,
+            TYP::ModeSAllCallPlusPSR => Self::AllCallPSRModeSTargetReports,
+            TYP::ModeSRollCallPlusPSR => Self::RollCallPSRModeSTargetReports,
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Clone, Copy, DekuRead, DekuWrite)]
 #[deku(type = "u8", bits = "8")]
 pub enum DataFilterTYP {
@@ -490,3 +530,289 @@ pub enum FOEFRI {
     UnknownTarget = 0b10,
     NoReply = 0b11,
 }
+
+/// Element populated bit of the I048/020 second extent
+#[derive(Debug, PartialEq, Clone, Copy, DekuRead, DekuWrite)]
+#[deku(type = "u8", bits = "1")]
+pub enum EP {
+    ElementNotPopulated = 0b0,
+    ElementPopulated = 0b1,
+}
+
+/// Value bit of the I048/020 second extent, only meaningful when the matching `EP` is populated
+#[derive(Debug, PartialEq, Clone, Copy, DekuRead, DekuWrite)]
+#[deku(type = "u8", bits = "1")]
+pub enum VAL {
+    NotAvailable = 0b0,
+    Available = 0b1,
+}
+
+/// Mode-1/2/3A code as four octal digits, stored in the 12 bit on-wire layout
+/// (A4 A2 A1 B4 B2 B1 C4 C2 C1 D4 D2 D1)
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Default, DekuRead, DekuWrite)]
+pub struct Squawk {
+    #[deku(bits = "12", endian = "big")]
+    code: u16,
+}
+
+impl Squawk {
+    /// Largest code that fits in four octal digits, 7777
+    pub const MAX: u16 = 0o7777;
+
+    /// Unlawful interference
+    pub const HIJACK: Self = Self { code: 0o7500 };
+    /// Radio communications failure
+    pub const RADIO_FAILURE: Self = Self { code: 0o7600 };
+    /// General emergency
+    pub const EMERGENCY: Self = Self { code: 0o7700 };
+
+    /// Create from the 12 bit octal representation, `None` if more than 12 bits are used
+    pub const fn new(code: u16) -> Option<Self> {
+        if code > Self::MAX {
+            None
+        } else {
+            Some(Self { code })
+        }
+    }
+
+    /// 12 bit octal representation as found on the wire
+    pub const fn code(&self) -> u16 {
+        self.code
+    }
+
+    /// Four octal digits, most significant (A) first
+    pub const fn digits(&self) -> [u8; 4] {
+        [
+            ((self.code >> 9) & 0o7) as u8,
+            ((self.code >> 6) & 0o7) as u8,
+            ((self.code >> 3) & 0o7) as u8,
+            (self.code & 0o7) as u8,
+        ]
+    }
+
+    /// Special meaning assigned to this code, if any
+    pub const fn special(&self) -> Option<SpecialCode> {
+        match self.code {
+            0o7500 => Some(SpecialCode::UnlawfulInterference),
+            0o7600 => Some(SpecialCode::RadioFailure),
+            0o7700 => Some(SpecialCode::Emergency),
+            0o2000 => Some(SpecialCode::NoCodeAssigned),
+            0o1200 => Some(SpecialCode::VfrUs),
+            0o7000 => Some(SpecialCode::VfrIcao),
+            _ => None,
+        }
+    }
+
+    /// Code is one of 7500, 7600 or 7700
+    pub const fn is_emergency(&self) -> bool {
+        matches!(
+            self.special(),
+            Some(
+                SpecialCode::UnlawfulInterference
+                    | SpecialCode::RadioFailure
+                    | SpecialCode::Emergency
+            )
+        )
+    }
+}
+
+impl core::fmt::Display for Squawk {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{:04o}", self.code)
+    }
+}
+
+impl core::str::FromStr for Squawk {
+    type Err = DekuError;
+
+    /// Parse exactly four octal digits, such as "7700"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.len() != 4 || !s.bytes().all(|b| (b'0'..=b'7').contains(&b)) {
+            return Err(DekuError::Parse(format!("squawk {s:?} is not four octal digits")));
+        }
+        let code = u16::from_str_radix(s, 8)
+            .map_err(|e| DekuError::Parse(format!("squawk {s:?}: {e}")))?;
+        Ok(Self { code })
+    }
+}
+
+/// Mode-1 code as two octal digits, A (A4 A2 A1) and B (B2 B1), stored in the 5 bit on-wire
+/// layout
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Default)]
+pub struct Mode1Code {
+    code: u8,
+}
+
+impl Mode1Code {
+    /// Largest code that fits in 5 bits, 73
+    pub const MAX: u8 = 0b1_1111;
+
+    /// Create from the 5 bit representation, `None` if more than 5 bits are used
+    pub const fn new(code: u8) -> Option<Self> {
+        if code > Self::MAX {
+            None
+        } else {
+            Some(Self { code })
+        }
+    }
+
+    /// 5 bit representation as found on the wire
+    pub const fn code(&self) -> u8 {
+        self.code
+    }
+
+    /// Digits A (0-7) and B (0-3)
+    pub const fn digits(&self) -> [u8; 2] {
+        [self.code >> 2, self.code & 0b11]
+    }
+}
+
+impl core::fmt::Display for Mode1Code {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let [a, b] = self.digits();
+        write!(f, "{a}{b}")
+    }
+}
+
+impl core::str::FromStr for Mode1Code {
+    type Err = DekuError;
+
+    /// Parse exactly two digits, A of 0-7 and B of 0-3, such as "73"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.as_bytes() {
+            [a @ b'0'..=b'7', b @ b'0'..=b'3'] => Ok(Self { code: (a - b'0') << 2 | (b - b'0') }),
+            _ => {
+                Err(DekuError::Parse(format!("mode 1 code {s:?} is not two octal digits A and B")))
+            }
+        }
+    }
+}
+
+/// Mode-3/A codes with a reserved meaning
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum SpecialCode {
+    /// 7500
+    UnlawfulInterference,
+    /// 7600
+    RadioFailure,
+    /// 7700
+    Emergency,
+    /// 2000, entering an SSR area without an assigned code
+    NoCodeAssigned,
+    /// 1200, VFR conspicuity code (North America)
+    VfrUs,
+    /// 7000, VFR conspicuity code (ICAO)
+    VfrIcao,
+}
+
+/// Change into, out of, or between emergency codes, see `Cat48::emergency_code_change`
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct EmergencyCodeChange {
+    pub previous: Option<Squawk>,
+    pub current: Option<Squawk>,
+}
+
+impl EmergencyCodeChange {
+    /// The new code is an emergency code
+    pub fn is_declared(&self) -> bool {
+        self.current.is_some_and(|s| s.is_emergency())
+    }
+}
+
+/// Eight character aircraft identification, such as a callsign or registration, stored as the
+/// six bit ICAO characters found on the wire
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, DekuRead, DekuWrite)]
+pub struct Callsign {
+    #[deku(bits = "6", endian = "big")]
+    codes: [u8; Self::LEN],
+}
+
+impl Callsign {
+    /// Number of characters on the wire
+    pub const LEN: usize = 8;
+
+    const SPACE: u8 = 0x20;
+
+    /// Create from at most eight characters of `A-Z`, `0-9` and space, padding with spaces
+    pub fn new(callsign: &str) -> Result<Self, DekuError> {
+        if callsign.len() > Self::LEN {
+            return Err(DekuError::InvalidParam(format!(
+                "callsign {callsign:?} is longer than {} characters",
+                Self::LEN
+            )));
+        }
+        let mut codes = [Self::SPACE; Self::LEN];
+        for (code, c) in codes.iter_mut().zip(callsign.bytes()) {
+            *code = match c {
+                b'A'..=b'Z' => c - 0x40,
+                b'0'..=b'9' | b' ' => c,
+                _ => {
+                    return Err(DekuError::InvalidParam(format!(
+                        "callsign {callsign:?} has {:?}, which is not in the ICAO six bit alphabet",
+                        char::from(c)
+                    )))
+                }
+            };
+        }
+        Ok(Self { codes })
+    }
+
+    /// Six bit characters as found on the wire
+    pub const fn codes(&self) -> [u8; Self::LEN] {
+        self.codes
+    }
+
+    /// All eight characters, including trailing spaces
+    ///
+    /// Codes outside of the ICAO alphabet are shown as their six bit IA5 character, so that
+    /// every code has a distinct character
+    pub fn padded(&self) -> String {
+        self.codes.iter().map(|&code| Self::to_char(code)).collect()
+    }
+
+    /// Characters without trailing spaces, such as "DLH65A"
+    pub fn trimmed(&self) -> String {
+        let mut callsign = self.padded();
+        callsign.truncate(callsign.trim_end_matches(' ').len());
+        callsign
+    }
+
+    /// Every character is in the ICAO alphabet of `A-Z`, `0-9` and space
+    pub fn is_valid(&self) -> bool {
+        self.codes.iter().all(|&code| matches!(code, 0x01..=0x1a | 0x20 | 0x30..=0x39))
+    }
+
+    const fn to_char(code: u8) -> char {
+        let code = code & 0b11_1111;
+        if code < 0x20 {
+            (code + 0x40) as char
+        } else {
+            code as char
+        }
+    }
+}
+
+impl Default for Callsign {
+    fn default() -> Self {
+        Self { codes: [Self::SPACE; Self::LEN] }
+    }
+}
+
+impl core::fmt::Display for Callsign {
+    /// Characters without trailing spaces, use `{:#}` to keep them
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        if f.alternate() {
+            f.write_str(&self.padded())
+        } else {
+            f.write_str(&self.trimmed())
+        }
+    }
+}
+
+impl core::str::FromStr for Callsign {
+    type Err = DekuError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::new(s)
+    }
+}
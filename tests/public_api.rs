@@ -1,24 +1,27 @@
 use assert_hex::assert_eq_hex;
 use asterix::data_item::{
-    CodeFx, DataSourceIdentifier, HeightMeasuredBy3dRadar, MBData, MessageType,
-    Mode3ACodeConfidenceIndicator, ModeCCodeAndConfidenceIndicator, SectorNumber, TimeOfDay,
-    TrackQuality, WarningErrorConditionsTargetClass,
+    AircraftIdentification, CalculatedPositionCartesianCorr, CalculatedTrackVelocity, CodeFx,
+    DataSourceIdentifier, HeightMeasuredBy3dRadar, MBData, MeasuredPositionInPolarCoordinates,
+    MessageType, Mode3ACodeConfidenceIndicator, Mode3ACodeInOctalRepresentation,
+    ModeCCodeAndConfidenceIndicator, SectorNumber, TimeOfDay, TrackQuality,
+    WarningErrorConditionsTargetClass,
 };
+use asterix::edition::{Cat34Edition, Cat48Edition, Editions};
+use asterix::types::{Callsign, Mode1Code, SpecialCode, Squawk};
 use asterix::types::{
     AIC, ARC, CDM, CNF, CODE, COM, DOU, FX, G, GHO, L, MAH, MSSC, MTYPE, RAB, RAD, RDP, SI, SIM,
     SPI, STAT, SUP, TCC, TRE, TYP, V,
 };
 use asterix::{AsterixMessage, AsterixPacket, Cat34, Cat48};
-use deku::{DekuContainerRead, DekuContainerWrite};
+use deku::{DekuContainerRead, DekuContainerWrite, DekuError};
 
 #[test]
 fn it_works() {
     let bytes = vec![
         0x30, 0x00, 0x30, 0xfd, 0xf7, 0x02, 0x19, 0xc9, 0x35, 0x6d, 0x4d, 0xa0, 0xc5, 0xaf, 0xf1,
-        0xe0, 0x02, 0x00, 0x05, 0x28, 0x3c, 0x66, 0x0c, 0x10, 0xc2, 0x36, 0xd4, 0x18,
-        //0x20 in wireshark, but last 6 bits don't matter and will 0x00 by writer
-        0x00, 0x01, 0xc0, 0x78, 0x00, 0x31, 0xbc, 0x00, 0x00, 0x40, 0x0d, 0xeb, 0x07, 0xb9, 0x58,
-        0x2e, 0x41, 0x00, 0x20, 0xf5,
+        0xe0, 0x02, 0x00, 0x05, 0x28, 0x3c, 0x66, 0x0c, 0x10, 0xc2, 0x36, 0xd4, 0x18, 0x20, 0x01,
+        0xc0, 0x78, 0x00, 0x31, 0xbc, 0x00, 0x00, 0x40, 0x0d, 0xeb, 0x07, 0xb9, 0x58, 0x2e, 0x41,
+        0x00, 0x20, 0xf5,
     ];
     let (_, mut packet) = AsterixPacket::from_bytes((&bytes, 0)).unwrap();
 
@@ -34,7 +37,8 @@ fn it_works() {
         assert_eq!(data_source_identifier.sic, 201);
 
         let time_of_day = message.time_of_day.as_ref().unwrap();
-        assert_eq!(time_of_day.time, 27354.602);
+        assert_eq!(time_of_day.time, 3_501_389);
+        assert_eq!(time_of_day.seconds(), 27_354.601_562_5);
 
         let target_report_descriptor = message.target_report_descriptor.as_ref().unwrap();
         assert_eq!(target_report_descriptor.typ, TYP::SingleModeSRollCall);
@@ -46,8 +50,10 @@ fn it_works() {
 
         let measured_position_in_polar_coordinates =
             message.measured_position_in_polar_coordinates.as_ref().unwrap();
-        assert_eq!(measured_position_in_polar_coordinates.rho, 197.6836);
-        assert_eq!(measured_position_in_polar_coordinates.theta, 340.13672);
+        assert_eq!(measured_position_in_polar_coordinates.rho, 50607);
+        assert_eq!(measured_position_in_polar_coordinates.theta, 61920);
+        assert_eq!(measured_position_in_polar_coordinates.rho_nm(), 197.683_593_75);
+        assert_eq!(measured_position_in_polar_coordinates.theta_deg(), 340.136_718_75);
 
         let mode_3_a_code_in_octal_representation =
             message.mode_3_a_code_in_octal_representation.as_ref().unwrap();
@@ -57,34 +63,41 @@ fn it_works() {
             mode_3_a_code_in_octal_representation.l,
             L::Mode3CodeDerivedFromTheReplyOfTheTransponder
         );
-        //TODO add squawk?
+        assert_eq!(mode_3_a_code_in_octal_representation.reply.to_string(), "1000");
 
         let flight_level_in_binary_repre = message.flight_level_in_binary_repre.as_ref().unwrap();
         assert_eq!(flight_level_in_binary_repre.v, V::CodeValidated);
         assert_eq!(flight_level_in_binary_repre.g, G::Default);
-        assert_eq!(flight_level_in_binary_repre.flight_level, 330);
+        assert_eq!(flight_level_in_binary_repre.flight_level, 1320);
+        assert_eq!(flight_level_in_binary_repre.fl(), 330.0);
 
         let aircraft_address = message.aircraft_address.as_ref().unwrap();
         assert_eq!(aircraft_address.address, 0x003c_660c);
 
         let aircraft_identification = message.aircraft_identification.as_ref().unwrap();
-        assert_eq!(aircraft_identification.identification, "DLH65A ");
+        assert_eq!(aircraft_identification.identification.padded(), "DLH65A  ");
+        assert_eq!(aircraft_identification.identification.trimmed(), "DLH65A");
 
         let mode_smb_data = message.mode_smb_data.as_ref().unwrap();
         assert_eq!(mode_smb_data.count, 1);
         assert_eq_hex!(
             mode_smb_data.mb_data,
-            vec![MBData { data: [0xc0, 0x78, 0x00, 0x31, 0xbc, 0x00, 0x00].to_vec() }]
+            vec![MBData {
+                data: [0xc0, 0x78, 0x00, 0x31, 0xbc, 0x00, 0x00].to_vec(),
+                bds1: 4,
+                bds2: 0
+            }]
         );
-        // TODO assert BDS1
-        // TODO assert BDS2
 
         let track_number = message.track_number.as_ref().unwrap();
         assert_eq!(track_number.number, 3563);
 
         let calculated_track_velocity = message.calculated_track_velocity.as_ref().unwrap();
-        assert_eq!(calculated_track_velocity.groundspeed, 0.120_666_504);
-        assert_eq!(calculated_track_velocity.heading, 124.002_686);
+        assert_eq!(calculated_track_velocity.groundspeed, 1977);
+        assert_eq!(calculated_track_velocity.heading, 22574);
+        assert_eq!(calculated_track_velocity.groundspeed_nm_s(), 0.120_666_503_906_25);
+        assert_eq!(calculated_track_velocity.groundspeed_kt(), 434.399_414_062_5);
+        assert_eq!(calculated_track_velocity.heading_deg(), 124.002_685_546_875);
 
         let track_status = message.track_status.as_ref().unwrap();
         assert_eq!(track_status.cnf, CNF::ConfirmedTrack);
@@ -172,7 +185,7 @@ fn third_packet() {
         assert_eq!(data_source_identifier.sic, 13);
 
         let time_of_day = message.time_of_day.as_ref().unwrap();
-        assert_eq!(time_of_day.time, 27_355.86);
+        assert_eq!(time_of_day.seconds(), 27_355.859_375);
 
         let target_report_descriptor = message.target_report_descriptor.as_ref().unwrap();
         assert_eq!(target_report_descriptor.typ, TYP::SingleModeSRollCall);
@@ -184,8 +197,8 @@ fn third_packet() {
 
         let measured_position_in_polar_coordinates =
             message.measured_position_in_polar_coordinates.as_ref().unwrap();
-        assert_eq!(measured_position_in_polar_coordinates.rho, 194.824_22);
-        assert_eq!(measured_position_in_polar_coordinates.theta, 128.759_77);
+        assert_eq!(measured_position_in_polar_coordinates.rho_nm(), 194.824_218_75);
+        assert_eq!(measured_position_in_polar_coordinates.theta_deg(), 128.759_765_625);
 
         let mode_3_a_code_in_octal_representation =
             message.mode_3_a_code_in_octal_representation.as_ref().unwrap();
@@ -195,38 +208,43 @@ fn third_packet() {
             mode_3_a_code_in_octal_representation.l,
             L::Mode3CodeDerivedFromTheReplyOfTheTransponder
         );
-        //TODO add squawk?
+        assert_eq!(mode_3_a_code_in_octal_representation.reply.to_string(), "2303");
 
         let flight_level_in_binary_repre = message.flight_level_in_binary_repre.as_ref().unwrap();
         assert_eq!(flight_level_in_binary_repre.v, V::CodeValidated);
         assert_eq!(flight_level_in_binary_repre.g, G::Default);
-        assert_eq!(flight_level_in_binary_repre.flight_level, 360);
+        assert_eq!(flight_level_in_binary_repre.fl(), 360.0);
 
         let aircraft_address = message.aircraft_address.as_ref().unwrap();
         assert_eq!(aircraft_address.address, 0x004b_aacd);
 
         let aircraft_identification = message.aircraft_identification.as_ref().unwrap();
-        assert_eq!(aircraft_identification.identification, "THY9TX ");
+        assert_eq!(aircraft_identification.identification.padded(), "THY9TX @");
+        assert!(!aircraft_identification.identification.is_valid());
 
         let mode_smb_data = message.mode_smb_data.as_ref().unwrap();
         assert_eq!(mode_smb_data.count, 1);
         assert_eq!(
             mode_smb_data.mb_data,
-            vec![MBData { data: [0xc6, 0x56, 0x32, 0xb0, 0xa8, 0x00, 0x00].to_vec() }]
+            vec![MBData {
+                data: [0xc6, 0x56, 0x32, 0xb0, 0xa8, 0x00, 0x00].to_vec(),
+                bds1: 4,
+                bds2: 0
+            }]
         );
-        assert_eq!(mode_smb_data.bds1, 4);
-        assert_eq!(mode_smb_data.bds2, 0);
 
         let track_number = message.track_number.as_ref().unwrap();
         assert_eq!(track_number.number, 482);
 
         let cal_pos_cartesian_coor = message.calculated_position_cartesian_coor.as_ref().unwrap();
-        assert_eq!(cal_pos_cartesian_coor.x, 151.921_88);
-        assert_eq!(cal_pos_cartesian_coor.y, -121.96875);
+        assert_eq!(cal_pos_cartesian_coor.x, 19446);
+        assert_eq!(cal_pos_cartesian_coor.y, -15612);
+        assert_eq!(cal_pos_cartesian_coor.x_nm(), 151.921_875);
+        assert_eq!(cal_pos_cartesian_coor.y_nm(), -121.968_75);
 
         let calculated_track_velocity = message.calculated_track_velocity.as_ref().unwrap();
-        assert_eq!(calculated_track_velocity.groundspeed, 0.126_831_05);
-        assert_eq!(calculated_track_velocity.heading, 263.600_46);
+        assert_eq!(calculated_track_velocity.groundspeed_nm_s(), 0.126_831_054_687_5);
+        assert_eq!(calculated_track_velocity.heading_deg(), 263.600_463_867_187_5);
 
         let track_status = message.track_status.as_ref().unwrap();
         assert_eq!(track_status.cnf, CNF::ConfirmedTrack);
@@ -281,10 +299,11 @@ fn test_34() {
         assert_eq!(message_type.t, MTYPE::SectorCrossing);
 
         let time_of_day = message.time_of_day.as_ref().unwrap();
-        assert_eq!(time_of_day.time, 27355.953);
+        assert_eq!(time_of_day.seconds(), 27_355.953_125);
 
         let sector_number = message.sector_number.as_ref().unwrap();
-        assert_eq!(sector_number.num, 135);
+        assert_eq!(sector_number.num, 96);
+        assert_eq!(sector_number.deg(), 135.0);
     } else {
         unreachable!("Not Cat 34");
     }
@@ -322,12 +341,13 @@ fn test_four_messages() {
 }
 
 #[test]
+#[allow(clippy::field_reassign_with_default)]
 fn test_not_from_bytes() {
     let mut thirty_eight = Cat34::default();
     thirty_eight.data_source_identifier = Some(DataSourceIdentifier { sac: 25, sic: 13 });
     thirty_eight.message_type = Some(MessageType { t: MTYPE::SectorCrossing });
-    thirty_eight.time_of_day = Some(TimeOfDay { time: 27355.953 });
-    thirty_eight.sector_number = Some(SectorNumber { num: 135 });
+    thirty_eight.time_of_day = Some(TimeOfDay::from_seconds(27_355.953_125).unwrap());
+    thirty_eight.sector_number = Some(SectorNumber::from_deg(135.0).unwrap());
 
     let mut packet = AsterixPacket {
         category: 34,
@@ -342,13 +362,14 @@ fn test_not_from_bytes() {
 // The following data items don't have pcap captures, and are my own testing
 
 #[test]
+#[allow(clippy::field_reassign_with_default)]
 fn test_48_track_quality() {
     let mut fourty_eight = Cat48::default();
     fourty_eight.track_quality = Some(TrackQuality {
-        horizontal_stddev: 0.0,
-        vertical_stddev: 0.0,
-        groundspeed_stddev: 0.0,
-        heading_stddev: 0.0,
+        horizontal_stddev: 0,
+        vertical_stddev: 0,
+        groundspeed_stddev: 0,
+        heading_stddev: 0,
     });
     let mut packet = AsterixPacket {
         category: 48,
@@ -363,11 +384,16 @@ fn test_48_track_quality() {
 
     let mut fourty_eight = Cat48::default();
     fourty_eight.track_quality = Some(TrackQuality {
-        horizontal_stddev: 32000.0,
-        vertical_stddev: 32000.0,
-        groundspeed_stddev: 0.015_563_965,
-        heading_stddev: 22.412_11,
+        horizontal_stddev: 0xfa,
+        vertical_stddev: 0xfa,
+        groundspeed_stddev: 0xff,
+        heading_stddev: 0xff,
     });
+    let track_quality = fourty_eight.track_quality.as_ref().unwrap();
+    assert_eq!(track_quality.horizontal_stddev_nm(), 1.953_125);
+    assert_eq!(track_quality.vertical_stddev_nm(), 1.953_125);
+    assert_eq!(track_quality.groundspeed_stddev_nm_s(), 0.015_563_964_843_75);
+    assert_eq!(track_quality.heading_stddev_deg(), 22.412_109_375);
     let mut packet = AsterixPacket {
         category: 48,
         messages: vec![asterix::AsterixMessage::Cat48(fourty_eight)],
@@ -449,7 +475,8 @@ fn test_48_mode_c_code_confidence() {
 #[test]
 fn test_48_height_3d() {
     let mut fourty_eight = Cat48::default();
-    let height = HeightMeasuredBy3dRadar { reserved: 0, height: 25 };
+    let height = HeightMeasuredBy3dRadar { reserved: 0, height: 1 };
+    assert_eq!(height.height_ft(), 25.0);
     fourty_eight.height_measured_by_3d_radar = Some(height);
     let mut packet = AsterixPacket {
         category: 48,
@@ -463,7 +490,9 @@ fn test_48_height_3d() {
     assert_eq_hex!(packet, exp_packet);
 
     let mut fourty_eight = Cat48::default();
-    let height = HeightMeasuredBy3dRadar { reserved: 0, height: 37200 };
+    let mut height = HeightMeasuredBy3dRadar { reserved: 0, height: 0 };
+    height.set_height_ft(37200.0).unwrap();
+    assert_eq!(height.height, 1488);
     fourty_eight.height_measured_by_3d_radar = Some(height);
     let mut packet = AsterixPacket {
         category: 48,
@@ -477,6 +506,126 @@ fn test_48_height_3d() {
     assert_eq_hex!(packet, exp_packet);
 }
 
+#[test]
+fn test_48_track_status_extents() {
+    // I048/170 with a first extent, a second extent and two further extents
+    let bytes = vec![0x30, 0x00, 0x0a, 0x01, 0b0000_0010, 0x01, 0x01, 0x81, 0x01, 0x00];
+    let (_, mut packet) = AsterixPacket::from_bytes((&bytes, 0)).unwrap();
+    if let AsterixMessage::Cat48(ref message) = packet.messages[0] {
+        let track_status = message.track_status.as_ref().unwrap();
+        assert_eq!(track_status.fx2, Some(FX::ExtensionIntoFirstExtent));
+        assert_eq!(track_status.spare, Some(0x40));
+        assert_eq!(track_status.fx3, Some(FX::ExtensionIntoFirstExtent));
+        assert_eq!(track_status.extents, [0x01, 0x00]);
+    } else {
+        unreachable!("Not Cat 48");
+    }
+    packet.finalize().unwrap();
+    assert_eq_hex!(packet.to_bytes().unwrap(), bytes);
+
+    // an FX bit announcing an extent that isn't there is rejected instead of truncating the item
+    let AsterixMessage::Cat48(ref mut message) = packet.messages[0] else {
+        unreachable!("Not Cat 48");
+    };
+    let track_status = message.track_status.as_mut().unwrap();
+    track_status.extents = vec![0x01];
+    assert!(packet.finalize().is_err());
+
+    let AsterixMessage::Cat48(ref mut message) = packet.messages[0] else {
+        unreachable!("Not Cat 48");
+    };
+    let track_status = message.track_status.as_mut().unwrap();
+    track_status.spare = None;
+    track_status.fx3 = None;
+    track_status.extents = vec![];
+    assert_eq!(
+        packet.finalize().unwrap_err(),
+        DekuError::InvalidParam(
+            "I048/170 second extent: announced by the FX bit, but not every subfield is set".into()
+        )
+    );
+
+    let AsterixMessage::Cat48(ref mut message) = packet.messages[0] else {
+        unreachable!("Not Cat 48");
+    };
+    message.track_status.as_mut().unwrap().fx2 = Some(FX::EndOfDataItem);
+    packet.finalize().unwrap();
+    assert_eq_hex!(
+        packet.to_bytes().unwrap(),
+        vec![0x30, 0x00, 0x07, 0x01, 0b0000_0010, 0x01, 0x00]
+    );
+}
+
+#[test]
+fn test_48_signed_items() {
+    // FL -1.25, 3D height -500 ft
+    let bytes = vec![0x30, 0x00, 0x0a, 0b0000_0101, 0x01, 0b1000, 0x3f, 0xfb, 0x3f, 0xec];
+    let (_, mut packet) = AsterixPacket::from_bytes((&bytes, 0)).unwrap();
+    if let AsterixMessage::Cat48(ref message) = packet.messages[0] {
+        let flight_level = message.flight_level_in_binary_repre.as_ref().unwrap();
+        assert_eq!(flight_level.flight_level, -5);
+        assert_eq!(flight_level.fl(), -1.25);
+        let height = message.height_measured_by_3d_radar.as_ref().unwrap();
+        assert_eq!(height.height_ft(), -500.0);
+    } else {
+        unreachable!("Not Cat 48");
+    }
+    packet.finalize().unwrap();
+    assert_eq_hex!(packet.to_bytes().unwrap(), bytes);
+
+    let mut height = HeightMeasuredBy3dRadar { reserved: 0, height: 0 };
+    assert!(height.set_height_ft(-204_800.0).is_ok());
+    assert!(height.set_height_ft(204_800.0).is_err());
+}
+
+#[test]
+fn test_encode_out_of_range_signed() {
+    let thirty_eight = Cat48 {
+        height_measured_by_3d_radar: Some(HeightMeasuredBy3dRadar { reserved: 0, height: 8192 }),
+        ..Cat48::default()
+    };
+    let mut packet = AsterixPacket {
+        category: 48,
+        messages: vec![AsterixMessage::Cat48(thirty_eight)],
+        ..AsterixPacket::default()
+    };
+    assert_eq!(
+        packet.finalize().unwrap_err(),
+        DekuError::InvalidParam("I048/110 height: 8192 does not fit in 14 signed bits".into())
+    );
+}
+
+#[test]
+fn test_34_position_of_data_source() {
+    // I034/120: -100 m, 50.0379 N, 8.5622 E
+    let bytes = vec![
+        0x22,
+        0x00,
+        0x0d,
+        0b0000_0001,
+        0b0001_0000,
+        0xff,
+        0x9c,
+        0x23,
+        0x95,
+        0x1f,
+        0x06,
+        0x16,
+        0xb3,
+    ];
+    let (_, mut packet) = AsterixPacket::from_bytes((&bytes, 0)).unwrap();
+    if let AsterixMessage::Cat34(ref message) = packet.messages[0] {
+        let position = message.three_d_position_of_data_source.as_ref().unwrap();
+        assert_eq!(position.height_of_wgs_84, -100);
+        assert!((position.latitude_deg() - 50.0379).abs() < 1e-4);
+        assert!((position.longitude_deg() - 8.5622).abs() < 1e-4);
+    } else {
+        unreachable!("Not Cat 34");
+    }
+    packet.finalize().unwrap();
+    assert_eq_hex!(packet.to_bytes().unwrap(), bytes);
+}
+
 #[test]
 fn test_48_radial_dopplerspeed() {
     // test the first subfield
@@ -550,6 +699,8 @@ fn test_mode1code_octal_representation() {
         assert_eq!(field.g, G::Default);
         assert_eq!(field.l, L::Mode3CodeDerivedFromTheReplyOfTheTransponder);
         assert_eq!(field.data, 0x01);
+        assert_eq!(field.code(), "01".parse().unwrap());
+        assert_eq!(field.code().digits(), [0, 1]);
     }
 }
 
@@ -567,7 +718,7 @@ fn test_mode2code_octal_representation() {
         assert_eq!(field.v, V::CodeValidated);
         assert_eq!(field.g, G::Default);
         assert_eq!(field.l, L::Mode3CodeDerivedFromTheReplyOfTheTransponder);
-        assert_eq!(field.data, 0x01);
+        assert_eq!(field.data, Squawk::new(0x01).unwrap());
     }
 }
 
@@ -612,6 +763,404 @@ fn test_34_antenna_rotation_speed() {
         assert_eq_hex!(message.fspec, &[0b0000_1000]);
 
         let field = message.antenna_rotation_speed.as_ref().unwrap();
-        assert_eq!(field.period, 1.0 / 128_f32);
+        assert_eq!(field.period, 1);
+        assert_eq!(field.seconds(), 1.0 / 128.0);
+    }
+}
+
+#[test]
+fn test_callsign() {
+    let callsign = Callsign::new("BAW123AB").unwrap();
+    assert_eq!(callsign.to_string(), "BAW123AB");
+    let callsign: Callsign = "DLH65A".parse().unwrap();
+    assert_eq!(callsign.trimmed(), "DLH65A");
+    assert_eq!(format!("{callsign:#}"), "DLH65A  ");
+    assert_eq!(callsign.codes()[..2], [0x04, 0x0c]);
+    assert!(callsign.is_valid());
+
+    assert!(Callsign::new("BAW123ABC").is_err());
+    assert!(Callsign::new("dlh65a").is_err());
+    assert!(Callsign::new("DLH-65").is_err());
+    assert!(Callsign::new("ÄÖ").is_err());
+
+    // all eight characters are encoded, padded with spaces
+    for (callsign, exp) in [
+        ("BAW123AB", [0x08, 0x15, 0xf1, 0xcb, 0x30, 0x42]),
+        ("DLH65A", [0x10, 0xc2, 0x36, 0xd4, 0x18, 0x20]),
+    ] {
+        let thirty_eight = Cat48 {
+            aircraft_identification: Some(AircraftIdentification {
+                identification: Callsign::new(callsign).unwrap(),
+            }),
+            ..Cat48::default()
+        };
+        let mut packet = AsterixPacket {
+            category: 48,
+            messages: vec![AsterixMessage::Cat48(thirty_eight)],
+            ..AsterixPacket::default()
+        };
+        packet.finalize().unwrap();
+        let mut exp_bytes = vec![0x30, 0x00, 0x0b, 0x01, 0b0100_0000];
+        exp_bytes.extend_from_slice(&exp);
+        assert_eq_hex!(packet.to_bytes().unwrap(), exp_bytes);
+        let (_, exp_packet) = AsterixPacket::from_bytes((&exp_bytes, 0)).unwrap();
+        assert_eq!(packet, exp_packet);
     }
 }
+
+#[test]
+fn test_squawk() {
+    let squawk: Squawk = "7700".parse().unwrap();
+    assert_eq!(squawk.code(), 0o7700);
+    assert_eq!(squawk.digits(), [7, 7, 0, 0]);
+    assert_eq!(squawk.to_string(), "7700");
+    assert_eq!(squawk.special(), Some(SpecialCode::Emergency));
+    assert!(squawk.is_emergency());
+
+    assert_eq!("0040".parse::<Squawk>().unwrap().to_string(), "0040");
+    assert_eq!("7000".parse::<Squawk>().unwrap().special(), Some(SpecialCode::VfrIcao));
+    assert!(!"2000".parse::<Squawk>().unwrap().is_emergency());
+    assert!("7800".parse::<Squawk>().is_err());
+    assert!("770".parse::<Squawk>().is_err());
+    assert!("77000".parse::<Squawk>().is_err());
+    assert!("+770".parse::<Squawk>().is_err());
+    assert_eq!(Squawk::new(0o10000), None);
+}
+
+#[test]
+fn test_mode1_code() {
+    let code: Mode1Code = "73".parse().unwrap();
+    assert_eq!(code.code(), 0b1_1111);
+    assert_eq!(code.digits(), [7, 3]);
+    assert_eq!(code.to_string(), "73");
+    assert_eq!(Mode1Code::new(0b0_0101).unwrap().to_string(), "11");
+    assert_eq!(Mode1Code::new(0b10_0000), None);
+    assert!("74".parse::<Mode1Code>().is_err());
+    assert!("8".parse::<Mode1Code>().is_err());
+    assert!("123".parse::<Mode1Code>().is_err());
+}
+
+#[test]
+fn test_48_emergency_code_change() {
+    let with_code = |code: &str| Cat48 {
+        mode_3_a_code_in_octal_representation: Some(Mode3ACodeInOctalRepresentation {
+            v: V::CodeValidated,
+            g: G::Default,
+            l: L::Mode3CodeDerivedFromTheReplyOfTheTransponder,
+            reserved: 0,
+            reply: code.parse().unwrap(),
+        }),
+        ..Cat48::default()
+    };
+
+    let normal = with_code("2303");
+    let emergency = with_code("7700");
+    let change = emergency.emergency_code_change(&normal).unwrap();
+    assert!(change.is_declared());
+    assert_eq!(change.previous, Some(Squawk::new(0o2303).unwrap()));
+    assert_eq!(change.current, Some(Squawk::EMERGENCY));
+
+    let change = normal.emergency_code_change(&emergency).unwrap();
+    assert!(!change.is_declared());
+
+    assert_eq!(emergency.emergency_code_change(&emergency), None);
+    assert_eq!(normal.emergency_code_change(&with_code("1000")), None);
+    assert!(emergency.emergency_code_change(&Cat48::default()).unwrap().is_declared());
+}
+
+#[test]
+fn test_48_mode_s_mb_data_blocks() {
+    // I048/250 with BDS 4,0 and BDS 3,0
+    let mut bytes = vec![0x30, 0x00, 0x16, 0b0000_0001, 0b0010_0000, 0x02];
+    bytes.extend([0xc0, 0x78, 0x00, 0x31, 0xbc, 0x00, 0x00, 0x40]);
+    bytes.extend([0x30, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x30]);
+    let (_, packet) = AsterixPacket::from_bytes((&bytes, 0)).unwrap();
+    if let AsterixMessage::Cat48(ref message) = packet.messages[0] {
+        let mode_smb_data = message.mode_smb_data.as_ref().unwrap();
+        let bds: Vec<_> = mode_smb_data.mb_data.iter().map(|mb| (mb.bds1, mb.bds2)).collect();
+        assert_eq!(bds, [(4, 0), (3, 0)]);
+    } else {
+        unreachable!("Message is not CAT48");
+    }
+    assert_eq_hex!(packet.to_bytes().unwrap(), bytes);
+}
+
+#[test]
+fn test_lossless_round_trip() {
+    // I048/090 at quarter FL, I048/240 with a code outside of the ICAO subset, and I048/250
+    let bytes = vec![
+        0x30,
+        0x00,
+        0x16,
+        0b0000_0101,
+        0b0110_0000,
+        0x05,
+        0x29,
+        0x10,
+        0xc2,
+        0x36,
+        0xd4,
+        0x18,
+        0x00,
+        0x01,
+        0xc0,
+        0x78,
+        0x00,
+        0x31,
+        0xbc,
+        0x00,
+        0x00,
+        0x40,
+    ];
+    let (_, packet) = AsterixPacket::from_bytes((&bytes, 0)).unwrap();
+    if let AsterixMessage::Cat48(ref message) = packet.messages[0] {
+        let flight_level = message.flight_level_in_binary_repre.as_ref().unwrap();
+        assert_eq!(flight_level.fl(), 330.25);
+        let identification = message.aircraft_identification.as_ref().unwrap();
+        assert_eq!(identification.identification.padded(), "DLH65A @");
+        let mode_smb_data = message.mode_smb_data.as_ref().unwrap();
+        assert_eq!(mode_smb_data.mb_data[0].bds1, 4);
+    } else {
+        unreachable!("Message is not CAT48");
+    }
+    assert_eq_hex!(packet.to_bytes().unwrap(), bytes);
+
+    // trailing empty fspec octet is kept by finalize_lossless, and dropped by finalize
+    let bytes = vec![0x22, 0x00, 0x07, 0b1000_0001, 0x00, 0x19, 0x0d];
+    let (_, mut packet) = AsterixPacket::from_bytes((&bytes, 0)).unwrap();
+    packet.finalize_lossless().unwrap();
+    assert_eq_hex!(packet.to_bytes().unwrap(), bytes);
+    packet.finalize().unwrap();
+    assert_eq_hex!(packet.to_bytes().unwrap(), vec![0x22, 0x00, 0x06, 0b1000_0000, 0x19, 0x0d]);
+}
+
+#[test]
+fn test_scaled_setters_round_trip() {
+    // every representable value survives a trip through the physical unit setters
+    let mut position = MeasuredPositionInPolarCoordinates { rho: 0, theta: 0 };
+    for raw in (0..=u16::MAX).step_by(7) {
+        position.set_rho_nm(f64::from(raw) / 256.0).unwrap();
+        position.set_theta_deg(f64::from(raw) * 360.0 / 65536.0).unwrap();
+        assert_eq!((position.rho, position.theta), (raw, raw));
+    }
+
+    // nearest LSB, instead of truncating
+    let mut velocity = CalculatedTrackVelocity { groundspeed: 0, heading: 0 };
+    velocity.set_groundspeed_kt(450.0).unwrap();
+    assert_eq!(velocity.groundspeed, 2048);
+    velocity.set_heading_deg(359.994).unwrap();
+    assert_eq!(velocity.heading, 65535);
+    // rounds up to north
+    velocity.set_heading_deg(359.999).unwrap();
+    assert_eq!(velocity.heading, 0);
+    assert_eq!(TimeOfDay::from_seconds(86_399.992).unwrap().time, 0xa8_c000 - 1);
+    assert_eq!(TimeOfDay::from_seconds(86_399.999).unwrap().time, 0);
+    assert_eq!(TimeOfDay::from_seconds(0.004).unwrap().time, 1);
+
+    let mut cartesian = CalculatedPositionCartesianCorr { x: 0, y: 0 };
+    cartesian.set_x_nm(-121.968_75).unwrap();
+    cartesian.set_y_nm(-0.003).unwrap();
+    assert_eq!((cartesian.x, cartesian.y), (-15612, 0));
+}
+
+#[test]
+fn test_scaled_setters_out_of_range() {
+    let mut velocity = CalculatedTrackVelocity { groundspeed: 0, heading: 0 };
+    let err = velocity.set_heading_deg(-90.0).unwrap_err();
+    assert_eq!(err, DekuError::InvalidParam("I048/200 heading: -90 is outside of 0..360".into()));
+    assert_eq!(velocity.heading, 0);
+    assert!(velocity.set_heading_deg(f64::NAN).is_err());
+    assert!(velocity.set_groundspeed_kt(-1.0).is_err());
+
+    let mut position = MeasuredPositionInPolarCoordinates { rho: 0, theta: 0 };
+    let err = position.set_rho_nm(300.0).unwrap_err();
+    assert_eq!(
+        err,
+        DekuError::InvalidParam("I048/040 rho: 300 is outside of 0..=255.99609375".into())
+    );
+    assert!(position.set_theta_deg(360.0).is_err());
+
+    assert!(TimeOfDay::from_seconds(90_000.0).is_err());
+    assert!(TimeOfDay::from_seconds(-0.5).is_err());
+    assert!(SectorNumber::from_deg(-1.0).is_err());
+
+    let mut cartesian = CalculatedPositionCartesianCorr { x: 0, y: 0 };
+    assert!(cartesian.set_x_nm(-256.0).is_ok());
+    assert!(cartesian.set_x_nm(256.0).is_err());
+
+    let mut quality = TrackQuality {
+        horizontal_stddev: 0,
+        vertical_stddev: 0,
+        groundspeed_stddev: 0,
+        heading_stddev: 0,
+    };
+    assert!(quality.set_horizontal_stddev_nm(2.0).is_err());
+}
+
+#[test]
+fn test_scaled_setters_wrapped() {
+    let mut velocity = CalculatedTrackVelocity { groundspeed: 0, heading: 0 };
+    velocity.set_heading_deg_wrapped(-90.0).unwrap();
+    assert_eq!(velocity.heading_deg(), 270.0);
+    velocity.set_heading_deg_wrapped(720.0).unwrap();
+    assert_eq!(velocity.heading, 0);
+
+    let mut position = MeasuredPositionInPolarCoordinates { rho: 0, theta: 0 };
+    position.set_theta_deg_wrapped(450.0).unwrap();
+    assert_eq!(position.theta_deg(), 90.0);
+
+    assert_eq!(SectorNumber::from_deg_wrapped(-45.0).unwrap().deg(), 315.0);
+
+    // NaN and infinity can't be wrapped, and are rejected instead of encoded as 0
+    assert!(velocity.set_heading_deg_wrapped(f64::NAN).is_err());
+    assert!(position.set_theta_deg_wrapped(f64::INFINITY).is_err());
+    assert_eq!(position.theta_deg(), 90.0);
+    assert!(SectorNumber::from_deg_wrapped(f64::NEG_INFINITY).is_err());
+}
+
+#[test]
+fn test_encode_out_of_range_raw() {
+    // raw fields wider than the item are rejected instead of silently truncated
+    let thirty_eight = Cat48 {
+        data_source_identifier: Some(DataSourceIdentifier { sac: 1, sic: 2 }),
+        time_of_day: Some(TimeOfDay { time: 1 << 24 }),
+        ..Cat48::default()
+    };
+    let mut packet = AsterixPacket {
+        category: 48,
+        messages: vec![AsterixMessage::Cat48(thirty_eight)],
+        ..AsterixPacket::default()
+    };
+    let err = packet.finalize().unwrap_err();
+    assert_eq!(
+        err,
+        DekuError::InvalidParam(
+            "I048/140 time of day: 16777216 does not fit in 24 unsigned bits".into()
+        )
+    );
+
+    // the same data item is named after its category
+    let thirty_four = Cat34 {
+        data_source_identifier: Some(DataSourceIdentifier { sac: 1, sic: 2 }),
+        time_of_day: Some(TimeOfDay { time: 1 << 24 }),
+        ..Cat34::default()
+    };
+    let mut packet = AsterixPacket {
+        category: 34,
+        messages: vec![AsterixMessage::Cat34(thirty_four)],
+        ..AsterixPacket::default()
+    };
+    let err = packet.finalize().unwrap_err();
+    assert_eq!(
+        err,
+        DekuError::InvalidParam(
+            "I034/030 time of day: 16777216 does not fit in 24 unsigned bits".into()
+        )
+    );
+}
+
+#[test]
+fn test_48_editions() {
+    // I048/010 25/13, I048/020 with the second extent: ADSB EP and VAL
+    let bytes = vec![0x30, 0x00, 0x09, 0b1010_0000, 0x19, 0x0d, 0x01, 0x01, 0b1100_0000];
+
+    let editions = Editions::new(Cat48Edition::V1_15, Cat34Edition::V1_26);
+    let err = editions.decode_validated(&bytes).unwrap_err();
+    assert_eq!(
+        err,
+        DekuError::Assertion("I048/020 second extent is not defined by CAT048 edition 1.15".into())
+    );
+
+    let editions = editions.with_cat48_source(25, 13, Cat48Edition::V1_31);
+    let mut packet = editions.decode_validated(&bytes).unwrap();
+    if let AsterixMessage::Cat48(ref mut message) = packet.messages[0] {
+        assert_eq!(message.edition, Some(Cat48Edition::V1_31));
+        // encoding checks the record against its edition
+        message.edition = Some(Cat48Edition::V1_21);
+    } else {
+        unreachable!("Not Cat 48");
+    }
+    assert!(packet.finalize().is_err());
+
+    // without an edition, decoding and encoding don't check the record
+    let (_, mut packet) = AsterixPacket::from_bytes((&bytes, 0)).unwrap();
+    if let AsterixMessage::Cat48(ref message) = packet.messages[0] {
+        assert_eq!(message.edition, None);
+    } else {
+        unreachable!("Not Cat 48");
+    }
+    packet.finalize().unwrap();
+    assert_eq_hex!(packet.to_bytes().unwrap(), bytes);
+
+    // I048/030 codes added in later editions
+    let thirty_eight = Cat48 {
+        warning_error_con_target_class: Some(WarningErrorConditionsTargetClass {
+            codefxs: vec![CodeFx { code: CODE::WrongDFReplyFormatDetected, fx: FX::EndOfDataItem }],
+        }),
+        edition: Some(Cat48Edition::V1_21),
+        ..Cat48::default()
+    };
+    let mut packet = AsterixPacket {
+        category: 48,
+        messages: vec![AsterixMessage::Cat48(thirty_eight)],
+        ..AsterixPacket::default()
+    };
+    assert!(packet.finalize().is_err());
+}
+
+#[test]
+fn test_special_purpose_reserved_expansion() {
+    // I048/SP and I048/RE
+    let bytes = vec![0x30, 0x00, 0x0c, 0x01, 0x01, 0x01, 0b0000_0110, 0x02, 0xaa, 0x03, 0xbb, 0xcc];
+    let (_, mut packet) = AsterixPacket::from_bytes((&bytes, 0)).unwrap();
+    if let AsterixMessage::Cat48(ref message) = packet.messages[0] {
+        assert_eq!(message.special_purpose_field.as_ref().unwrap().data, [0xaa]);
+        assert_eq!(message.reserved_expansion_field.as_ref().unwrap().data, [0xbb, 0xcc]);
+    } else {
+        unreachable!("Not Cat 48");
+    }
+    packet.finalize().unwrap();
+    assert_eq_hex!(packet.to_bytes().unwrap(), bytes);
+
+    let editions = Editions::new(Cat48Edition::V1_15, Cat34Edition::V1_26);
+    assert!(editions.decode_validated(&bytes).is_err());
+
+    // I034/RE
+    let bytes = vec![0x22, 0x00, 0x07, 0x01, 0b0000_0100, 0x02, 0xdd];
+    let editions = Editions::new(Cat48Edition::V1_31, Cat34Edition::V1_27);
+    let packet = editions.decode_validated(&bytes).unwrap();
+    if let AsterixMessage::Cat34(ref message) = packet.messages[0] {
+        assert_eq!(message.edition, Some(Cat34Edition::V1_27));
+        assert_eq!(message.reserved_expansion_field.as_ref().unwrap().data, [0xdd]);
+    } else {
+        unreachable!("Not Cat 34");
+    }
+    let editions = editions.with_cat34_source(0, 0, Cat34Edition::V1_26);
+    assert_eq!(editions.cat34(None), Cat34Edition::V1_27);
+    assert!(Editions::new(Cat48Edition::V1_31, Cat34Edition::V1_26)
+        .decode_validated(&bytes)
+        .is_err());
+}
+
+#[test]
+fn test_48_position_consistency() {
+    // 10 NM at 90 degrees, and 10 NM east
+    let mut thirty_eight = Cat48 {
+        measured_position_in_polar_coordinates: Some(MeasuredPositionInPolarCoordinates {
+            rho: 2560,
+            theta: 16384,
+        }),
+        ..Cat48::default()
+    };
+    assert_eq!(thirty_eight.is_position_consistent(0.1), None);
+
+    thirty_eight.calculated_position_cartesian_coor =
+        Some(CalculatedPositionCartesianCorr { x: 1280, y: 0 });
+    assert!(thirty_eight.polar_cartesian_difference_nm().unwrap() < 1e-9);
+    assert_eq!(thirty_eight.is_position_consistent(0.1), Some(true));
+
+    thirty_eight.calculated_position_cartesian_coor =
+        Some(CalculatedPositionCartesianCorr { x: 1280, y: 64 });
+    assert!((thirty_eight.polar_cartesian_difference_nm().unwrap() - 0.5).abs() < 1e-9);
+    assert_eq!(thirty_eight.is_position_consistent(0.1), Some(false));
+}